@@ -0,0 +1,344 @@
+// Local HTTP/JSON API for editor plugins, launchers and scripts
+// Listens on 127.0.0.1 only and every request needs the token: Authorization: Bearer <token>
+//
+//   GET    /entries?limit=&offset=               entries, as load_all_entries returns them (50 by default)
+//   GET    /search?q=&mode=query|semantic|hybrid  query language as in retention rules, or ranked
+//   GET    /entry?path=                          one entry with its entities
+//   GET    /thumbnail?path=&size=                PNG thumbnail of an indexed screenshot
+//   GET    /tags                                 every tag with its screenshot count
+//   POST   /tags  {"path", "tag"}                add a custom tag
+//   DELETE /tags  {"path", "tag"}                remove a custom tag
+//
+// Text and entities of screenshots flagged sensitive are withheld, as over MCP
+
+use crate::indexer::Indexer;
+use crate::{
+    add_entry_tag, load_entries, load_entry_by_path, load_entry_details, query, query_shared_entry_paths, ranked_search,
+    remove_entry_tag, tag_counts, DbEntry, WITHHELD_TEXT,
+};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{Cursor, Read};
+use std::path::Path;
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+const DEFAULT_LIMIT: usize = 50;
+const DEFAULT_THUMBNAIL_SIZE: u32 = 256;
+const MAX_THUMBNAIL_SIZE: u32 = 1024;
+const MAX_BODY_BYTES: u64 = 64 * 1024;
+
+pub(crate) struct ApiServer {
+    server: Arc<Server>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl ApiServer {
+    // Bind to the loopback port and answer requests on a thread of its own
+    pub(crate) fn start(indexer: Indexer, port: u16, token: String) -> Result<ApiServer, String> {
+        let conn = indexer.connect()
+            .map_err(|e| format!("Database error: {}", e))?;
+        let server = Server::http(("127.0.0.1", port))
+            .map_err(|e| format!("Failed to listen on 127.0.0.1:{}: {}", port, e))?;
+        let server = Arc::new(server);
+
+        let listener = Arc::clone(&server);
+        let thread = std::thread::spawn(move || {
+            for request in listener.incoming_requests() {
+                handle(&indexer, &conn, &token, request);
+            }
+        });
+        println!("[API] ✅ Listening on http://127.0.0.1:{}", port);
+        Ok(ApiServer { server, thread: Some(thread) })
+    }
+
+    pub(crate) fn stop(mut self) {
+        self.server.unblock();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+        println!("[API] Stopped");
+    }
+}
+
+// Hex-encoded random bytes for the bearer token
+pub(crate) fn generate_token() -> Result<String, String> {
+    let mut bytes = [0u8; 32];
+    getrandom::fill(&mut bytes)
+        .map_err(|e| format!("Failed to generate API token: {}", e))?;
+    Ok(bytes.iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+enum Reply {
+    Json(serde_json::Value),
+    Png(Vec<u8>),
+}
+
+struct ApiError {
+    status: u16,
+    message: String,
+}
+
+impl ApiError {
+    fn new(status: u16, message: impl Into<String>) -> ApiError {
+        ApiError { status, message: message.into() }
+    }
+}
+
+// Core errors are plain strings; anything that isn't a bad request is reported as a server error
+impl From<String> for ApiError {
+    fn from(message: String) -> ApiError {
+        ApiError::new(500, message)
+    }
+}
+
+#[derive(Deserialize)]
+struct TagRequest {
+    path: String,
+    tag: String,
+}
+
+#[derive(Serialize)]
+struct TagsReply {
+    path: String,
+    custom_tags: Vec<String>,
+}
+
+fn handle(indexer: &Indexer, conn: &Connection, token: &str, mut request: Request) {
+    let result = if authorized(&request, token) {
+        route(indexer, conn, &mut request)
+    } else {
+        Err(ApiError::new(401, "Missing or wrong API token"))
+    };
+
+    let response = match result {
+        Ok(Reply::Json(value)) => Response::from_data(value.to_string()).with_header(content_type("application/json")),
+        Ok(Reply::Png(bytes)) => Response::from_data(bytes).with_header(content_type("image/png")),
+        Err(error) => {
+            if error.status >= 500 {
+                eprintln!("[API] {} {}: {}", request.method(), request.url(), error.message);
+            }
+            Response::from_data(serde_json::json!({ "error": error.message }).to_string())
+                .with_status_code(error.status)
+                .with_header(content_type("application/json"))
+        }
+    };
+    if let Err(e) = request.respond(response) {
+        eprintln!("[API] Failed to respond: {}", e);
+    }
+}
+
+fn content_type(value: &str) -> Header {
+    Header::from_bytes("Content-Type", value).expect("valid header")
+}
+
+fn authorized(request: &Request, token: &str) -> bool {
+    request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv("Authorization"))
+        .and_then(|header| header.value.as_str().strip_prefix("Bearer "))
+        .is_some_and(|given| constant_time_eq(given.trim().as_bytes(), token.as_bytes()))
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+fn route(indexer: &Indexer, conn: &Connection, request: &mut Request) -> Result<Reply, ApiError> {
+    let (path, params) = split_url(request.url());
+    match (request.method(), path.as_str()) {
+        (Method::Get, "/entries") => {
+            let entries = load_entries(conn)
+                .map_err(|e| format!("Failed to load entries: {}", e))?;
+            let offset = number_param(&params, "offset")?.unwrap_or(0);
+            let limit = number_param(&params, "limit")?.unwrap_or(DEFAULT_LIMIT);
+            let entries: Vec<_> = entries.into_iter().skip(offset).take(limit).map(|mut entry| { withhold(&mut entry); entry }).collect();
+            json(&entries)
+        }
+        (Method::Get, "/search") => search(indexer.data_dir(), conn, &params),
+        (Method::Get, "/entry") => {
+            let path = required_param(&params, "path")?;
+            let mut details = load_entry_details(conn, path).map_err(|e| ApiError::new(404, e))?;
+            if details.entry.sensitive {
+                details.entities.clear();
+            }
+            withhold(&mut details.entry);
+            json(&details)
+        }
+        (Method::Get, "/thumbnail") => thumbnail(conn, &params),
+        (Method::Get, "/tags") => json(&tag_counts(conn)?),
+        (Method::Post, "/tags") | (Method::Delete, "/tags") => {
+            let body: TagRequest = serde_json::from_str(&read_body(request)?)
+                .map_err(|e| ApiError::new(400, format!("Expected {{\"path\", \"tag\"}}: {}", e)))?;
+            if load_entry_by_path(conn, &body.path).is_none() {
+                return Err(ApiError::new(404, format!("{} isn't indexed", body.path)));
+            }
+            let tag = body.tag.trim();
+            if tag.is_empty() {
+                return Err(ApiError::new(400, "Tag can't be empty"));
+            }
+            let custom_tags = if *request.method() == Method::Post {
+                add_entry_tag(conn, &body.path, tag)?
+            } else {
+                remove_entry_tag(conn, &body.path, tag)?
+            };
+            json(&TagsReply { path: body.path, custom_tags })
+        }
+        (_, "/entries" | "/search" | "/entry" | "/thumbnail" | "/tags") => Err(ApiError::new(405, "Method not allowed")),
+        _ => Err(ApiError::new(404, format!("No endpoint {}", path))),
+    }
+}
+
+fn search(data_dir: &Path, conn: &Connection, params: &HashMap<String, String>) -> Result<Reply, ApiError> {
+    let query = required_param(params, "q")?;
+    let limit = number_param(params, "limit")?.unwrap_or(DEFAULT_LIMIT).max(1);
+    match params.get("mode").map(String::as_str).unwrap_or("query") {
+        "query" => {
+            let query = query::Query::parse(query).map_err(|e| ApiError::new(400, e))?;
+            let entries: Vec<_> = query_shared_entry_paths(conn, &query)?
+                .iter()
+                .take(limit)
+                .filter_map(|path| load_entry_by_path(conn, path))
+                .map(|mut entry| { withhold(&mut entry); entry })
+                .collect();
+            json(&entries)
+        }
+        mode @ ("semantic" | "hybrid") => {
+            let mut hits = ranked_search(data_dir, conn, query, limit, mode == "hybrid", true)?;
+            for hit in &mut hits {
+                withhold(&mut hit.entry);
+            }
+            json(&hits)
+        }
+        other => Err(ApiError::new(400, format!("Unknown search mode '{}' (use query, semantic or hybrid)", other))),
+    }
+}
+
+// Only screenshots in the index are served, never arbitrary files or sensitive ones
+fn thumbnail(conn: &Connection, params: &HashMap<String, String>) -> Result<Reply, ApiError> {
+    let path = required_param(params, "path")?;
+    match load_entry_by_path(conn, path) {
+        None => return Err(ApiError::new(404, format!("{} isn't indexed", path))),
+        Some(entry) if entry.sensitive => return Err(ApiError::new(403, format!("{} is flagged sensitive", path))),
+        Some(_) => {}
+    }
+    let size = number_param(params, "size")?
+        .map(|size| (size as u32).clamp(16, MAX_THUMBNAIL_SIZE))
+        .unwrap_or(DEFAULT_THUMBNAIL_SIZE);
+
+    let img = image::open(path)
+        .map_err(|e| ApiError::new(404, format!("Failed to open image: {}", e)))?;
+    let mut bytes = Cursor::new(Vec::new());
+    img.thumbnail(size, size)
+        .write_to(&mut bytes, image::ImageFormat::Png)
+        .map_err(|e| format!("Failed to encode thumbnail: {}", e))?;
+    Ok(Reply::Png(bytes.into_inner()))
+}
+
+fn withhold(entry: &mut DbEntry) {
+    if entry.sensitive {
+        entry.text = WITHHELD_TEXT.to_string();
+    }
+}
+
+fn json<T: Serialize>(value: &T) -> Result<Reply, ApiError> {
+    serde_json::to_value(value)
+        .map(Reply::Json)
+        .map_err(|e| ApiError::from(format!("Failed to serialize response: {}", e)))
+}
+
+fn read_body(request: &mut Request) -> Result<String, ApiError> {
+    let mut body = String::new();
+    request
+        .as_reader()
+        .take(MAX_BODY_BYTES)
+        .read_to_string(&mut body)
+        .map_err(|e| ApiError::new(400, format!("Failed to read body: {}", e)))?;
+    Ok(body)
+}
+
+fn required_param<'a>(params: &'a HashMap<String, String>, name: &str) -> Result<&'a str, ApiError> {
+    params
+        .get(name)
+        .map(String::as_str)
+        .filter(|value| !value.trim().is_empty())
+        .ok_or_else(|| ApiError::new(400, format!("Missing parameter '{}'", name)))
+}
+
+fn number_param(params: &HashMap<String, String>, name: &str) -> Result<Option<usize>, ApiError> {
+    params
+        .get(name)
+        .map(|value| value.parse().map_err(|_| ApiError::new(400, format!("'{}' must be a number", name))))
+        .transpose()
+}
+
+// "/search?q=a%20b&mode=hybrid" -> ("/search", {q: "a b", mode: "hybrid"})
+fn split_url(url: &str) -> (String, HashMap<String, String>) {
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+    let params = query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (percent_decode(key), percent_decode(value))
+        })
+        .collect();
+    (percent_decode(path), params)
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' if i + 2 < bytes.len() && bytes[i + 1].is_ascii_hexdigit() && bytes[i + 2].is_ascii_hexdigit() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap_or("00");
+                decoded.push(u8::from_str_radix(hex, 16).unwrap_or(0));
+                i += 2;
+            }
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percent_decoding() {
+        assert_eq!(percent_decode("a%20b+c"), "a b c");
+        assert_eq!(percent_decode("%2FUsers%2Fme%2Fshot.png"), "/Users/me/shot.png");
+        assert_eq!(percent_decode("caf%C3%A9"), "café");
+        // Malformed escapes are kept as they are
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%4"), "%4");
+        assert_eq!(percent_decode("%zz"), "%zz");
+        assert_eq!(percent_decode("%41"), "A");
+        assert_eq!(percent_decode("%FF"), "\u{FFFD}");
+    }
+
+    #[test]
+    fn url_splitting() {
+        let (path, params) = split_url("/search?q=tag%3Areceipts+older%3A1y&limit=5&empty&=x");
+        assert_eq!(path, "/search");
+        assert_eq!(params["q"], "tag:receipts older:1y");
+        assert_eq!(params["limit"], "5");
+        assert_eq!(params["empty"], "");
+        assert_eq!(split_url("/entries").1.len(), 0);
+    }
+
+    #[test]
+    fn token_comparison() {
+        assert!(constant_time_eq(b"secret", b"secret"));
+        assert!(!constant_time_eq(b"secret", b"secreT"));
+        assert!(!constant_time_eq(b"secret", b"secret2"));
+    }
+}
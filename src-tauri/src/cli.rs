@@ -0,0 +1,478 @@
+// Headless `chronicle` command: the app's index, OCR and search without a window
+// Works on the same chronicle.db, so it can run over SSH, from cron or in shell pipelines
+//
+//   chronicle index <dir> [--recursive]           OCR and index new screenshots in a folder
+//   chronicle search <query> [--semantic|--hybrid] query language as in retention rules, or ranked
+//   chronicle show <path>                         text, tags and entities of one screenshot
+//   chronicle tags                                every tag with its screenshot count
+//   chronicle similar <path>                      near-duplicates and lookalikes
+//   chronicle export [query]                      entries and saved searches as JSON Lines
+//   chronicle import <file|bundle>                merge an export into the index, OCR only where text is missing
+//   chronicle reocr <path>... | --all             run OCR again on indexed screenshots
+//   chronicle mcp                                 MCP server on stdio for local assistants (see src/mcp.rs)
+//
+// Results go to stdout (one path per line, or JSON with --json); logs and progress go to stderr
+
+use crate::indexer::{Indexer, NoopSink};
+use crate::{
+    derive_database_key, exclusion_rules, export, format_millis, get_file_created_at, import_from, is_encrypted_file,
+    is_hidden, is_png, library_root, load_encryption_config, load_entry_by_path, load_entry_details,
+    load_exported_entries, mcp, now_millis_string, parse_json_list, query, query_entry_paths, ranked_search,
+    read_setting, saved_searches, similar_entries, tag_counts, EntryDetails, DB_FILE, LIBRARY_DIR_KEY,
+};
+use rusqlite::Connection;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::Arc;
+
+const DATA_DIR_ENV: &str = "CHRONICLE_DATA_DIR";
+const PASSPHRASE_ENV: &str = "CHRONICLE_PASSPHRASE";
+const DEFAULT_LIMIT: usize = 20;
+// The app's data folder is named after its bundle identifier
+const TAURI_CONFIG: &str = include_str!("../tauri.conf.json");
+
+const USAGE: &str = "Usage: chronicle <command> [options]
+
+Commands:
+  index <dir>        OCR and index screenshots in a folder that aren't indexed yet
+  search <query>     Screenshots matching a query, e.g. 'invoice tag:Receipts newer:30d'
+  show <path>        A screenshot's text, tags and entities
+  tags               Every tag with the number of screenshots that have it
+  similar <path>     Screenshots that look like this one
+  export [query]     Matching entries (all without a query) and saved searches as JSON Lines
+  import <source>    Merge a JSON Lines export or an export bundle folder into the index
+  reocr <path>...    Run OCR again on indexed screenshots (--all for every one)
+  mcp                Serve the index to local AI assistants over MCP (stdio)
+
+Options:
+  --json             Print JSON instead of paths
+  -n, --limit <n>    Maximum number of results
+  -r, --recursive    index: include subfolders
+  --semantic         search: rank by meaning (needs the text embedding model)
+  --hybrid           search: mix meaning and keyword ranking
+  --all              reocr: every indexed screenshot
+  --data-dir <dir>   Chronicle's data folder (default: the app's, or $CHRONICLE_DATA_DIR)
+
+Encrypted indexes unlock with their recorded keyfile, or with $CHRONICLE_PASSPHRASE.";
+
+#[derive(Default)]
+struct Args {
+    command: String,
+    positional: Vec<String>,
+    json: bool,
+    recursive: bool,
+    all: bool,
+    semantic: bool,
+    hybrid: bool,
+    limit: Option<usize>,
+    data_dir: Option<PathBuf>,
+}
+
+fn parse_args(mut raw: impl Iterator<Item = String>) -> Result<Args, String> {
+    let mut args = Args::default();
+    let mut options_done = false;
+    while let Some(arg) = raw.next() {
+        if options_done || !arg.starts_with('-') || arg == "-" {
+            if args.command.is_empty() {
+                args.command = arg;
+            } else {
+                args.positional.push(arg);
+            }
+            continue;
+        }
+        match arg.as_str() {
+            "--" => options_done = true,
+            "-h" | "--help" => args.command = "help".to_string(),
+            "--json" => args.json = true,
+            "-r" | "--recursive" => args.recursive = true,
+            "--all" => args.all = true,
+            "--semantic" => args.semantic = true,
+            "--hybrid" => args.hybrid = true,
+            "-n" | "--limit" => {
+                let value = raw.next().ok_or("--limit needs a number")?;
+                let limit = value.parse().map_err(|_| format!("Invalid limit '{}'", value))?;
+                args.limit = Some(limit);
+            }
+            "--data-dir" => args.data_dir = Some(PathBuf::from(raw.next().ok_or("--data-dir needs a folder")?)),
+            // Negated query terms, e.g. `chronicle search invoice -is:pinned`
+            _ if matches!(args.command.as_str(), "search" | "export") => args.positional.push(arg),
+            _ => return Err(format!("Unknown option '{}'", arg)),
+        }
+    }
+    Ok(args)
+}
+
+// Results only; stops quietly once the reader goes away (`chronicle search x | head -1`)
+struct Output {
+    writer: Box<dyn Write>,
+    closed: bool,
+}
+
+impl Output {
+    fn line(&mut self, line: &str) {
+        if !self.closed && writeln!(self.writer, "{}", line).is_err() {
+            self.closed = true;
+        }
+    }
+
+    fn json<T: Serialize>(&mut self, value: &T) {
+        match serde_json::to_string(value) {
+            Ok(json) => self.line(&json),
+            Err(e) => eprintln!("Failed to serialize result: {}", e),
+        }
+    }
+}
+
+// The indexing core logs with println!, so stdout is pointed at stderr for the whole run
+// and results get their own handle on the real stdout
+#[cfg(unix)]
+fn results_stdout() -> Box<dyn Write> {
+    use std::os::fd::FromRawFd;
+    // SAFETY: only descriptors 1 and 2 and our own duplicate are touched, before any output
+    unsafe {
+        let results = libc::dup(libc::STDOUT_FILENO);
+        if results >= 0 {
+            if libc::dup2(libc::STDERR_FILENO, libc::STDOUT_FILENO) >= 0 {
+                return Box::new(std::io::LineWriter::new(fs::File::from_raw_fd(results)));
+            }
+            libc::close(results);
+        }
+    }
+    Box::new(std::io::stdout())
+}
+
+#[cfg(not(unix))]
+fn results_stdout() -> Box<dyn Write> {
+    Box::new(std::io::stdout())
+}
+
+pub fn run() -> ExitCode {
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("chronicle: {}\n\n{}", e, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+    let mut out = Output { writer: results_stdout(), closed: false };
+
+    let result = match args.command.as_str() {
+        "" | "help" => {
+            out.line(USAGE);
+            Ok(())
+        }
+        "index" | "search" | "show" | "tags" | "similar" | "export" | "import" | "reocr" | "mcp" => open(&args)
+            .and_then(|(indexer, conn)| run_command(&args, &indexer, &conn, &mut out)),
+        other => Err(format!("Unknown command '{}' - see chronicle --help", other)),
+    };
+    let _ = out.writer.flush();
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("chronicle: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run_command(args: &Args, indexer: &Indexer, conn: &Connection, out: &mut Output) -> Result<(), String> {
+    match args.command.as_str() {
+        "index" => index(args, indexer, conn, out),
+        "search" => search(args, indexer.data_dir(), conn, out),
+        "show" => show(args, conn, out),
+        "tags" => tags(args, conn, out),
+        "similar" => similar(args, conn, out),
+        "export" => export(args, conn, out),
+        "import" => import(args, indexer, conn, out),
+        "mcp" => {
+            mcp::serve(indexer.data_dir(), conn, std::io::stdin().lock(), |message| out.json(message));
+            Ok(())
+        }
+        _ => reocr(args, indexer, conn, out),
+    }
+}
+
+// Where Tauri's app_data_dir() points: the platform's data folder plus the identifier
+fn app_data_dir() -> Result<PathBuf, String> {
+    let config: serde_json::Value = serde_json::from_str(TAURI_CONFIG)
+        .map_err(|e| format!("Invalid tauri.conf.json: {}", e))?;
+    let identifier = config["identifier"].as_str().ok_or("tauri.conf.json has no identifier")?;
+    dirs::data_dir()
+        .map(|dir| dir.join(identifier))
+        .ok_or_else(|| "Couldn't find the user data folder - pass --data-dir".to_string())
+}
+
+// The indexing pipeline over the app's data folder, and an open index; only `index` and `import` may start a new one
+// Nothing listens for pipeline events here - progress is reported on stderr instead
+fn open(args: &Args) -> Result<(Indexer, Connection), String> {
+    let data_dir = match args.data_dir.clone().or_else(|| std::env::var_os(DATA_DIR_ENV).map(PathBuf::from)) {
+        Some(dir) => dir,
+        None => app_data_dir()?,
+    };
+    let db_path = data_dir.join(DB_FILE);
+    if !db_path.exists() {
+        if !matches!(args.command.as_str(), "index" | "import") {
+            return Err(format!("No index at {} - index a folder first, or pass --data-dir", db_path.display()));
+        }
+        fs::create_dir_all(&data_dir)
+            .map_err(|e| format!("Failed to create {}: {}", data_dir.display(), e))?;
+    }
+
+    let key = database_key(&data_dir, &db_path)?;
+    let indexer = Indexer::new(data_dir, key, Arc::new(NoopSink));
+    let conn = indexer.connect()
+        .map_err(|e| format!("Failed to open {}: {}", db_path.display(), e))?;
+    Ok((indexer, conn))
+}
+
+// Encrypted indexes unlock like the app's: the passphrase from the environment, or the recorded keyfile
+fn database_key(data_dir: &Path, db_path: &Path) -> Result<Option<String>, String> {
+    if !is_encrypted_file(db_path) {
+        return Ok(None);
+    }
+    if let Ok(passphrase) = std::env::var(PASSPHRASE_ENV) {
+        return derive_database_key(Some(passphrase), None).map(|(key, _)| Some(key));
+    }
+    match load_encryption_config(data_dir).and_then(|config| config.keyfile) {
+        Some(keyfile) => derive_database_key(None, Some(keyfile)).map(|(key, _)| Some(key)),
+        None => Err(format!("{} is encrypted - set {} to unlock it", db_path.display(), PASSPHRASE_ENV)),
+    }
+}
+
+// Entries are stored by absolute path, so resolve what the user typed the same way
+fn entry_path(arg: &str) -> String {
+    fs::canonicalize(arg)
+        .map(|path| path.to_string_lossy().to_string())
+        .unwrap_or_else(|_| arg.to_string())
+}
+
+fn first_arg<'a>(args: &'a Args, what: &str) -> Result<&'a str, String> {
+    args.positional
+        .first()
+        .map(String::as_str)
+        .ok_or_else(|| format!("{} needs a {}", args.command, what))
+}
+
+fn print_entries(args: &Args, conn: &Connection, paths: &[String], out: &mut Output) {
+    for path in paths {
+        if !args.json {
+            out.line(path);
+        } else if let Some(entry) = load_entry_by_path(conn, path) {
+            out.json(&entry);
+        }
+    }
+}
+
+fn collect_screenshots(dir: &Path, recursive: bool, found: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if is_hidden(&path) {
+            continue;
+        }
+        if path.is_dir() {
+            if recursive {
+                collect_screenshots(&path, recursive, found);
+            }
+        } else if is_png(&path) {
+            found.push(path);
+        }
+    }
+}
+
+fn index(args: &Args, indexer: &Indexer, conn: &Connection, out: &mut Output) -> Result<(), String> {
+    let dir = first_arg(args, "folder")?;
+    let dir = fs::canonicalize(dir).map_err(|e| format!("Can't read {}: {}", dir, e))?;
+
+    let mut found = Vec::new();
+    collect_screenshots(&dir, args.recursive, &mut found);
+    found.sort();
+    // A file at a trashed entry's path is new: indexing it brings the entry back
+    let known: HashSet<String> = {
+        let mut stmt = conn.prepare("SELECT path FROM entries WHERE COALESCE(trashed, 0) = 0")
+            .map_err(|e| format!("Query error: {}", e))?;
+        let rows = stmt.query_map([], |row| row.get(0))
+            .map_err(|e| format!("Query map error: {}", e))?;
+        rows.collect::<Result<_, _>>()
+            .map_err(|e| format!("Row error: {}", e))?
+    };
+    let pending: Vec<PathBuf> = found
+        .iter()
+        .filter(|path| !known.contains(path.to_string_lossy().as_ref()))
+        .cloned()
+        .collect();
+    eprintln!("{} screenshots in {}, {} not indexed yet", found.len(), dir.display(), pending.len());
+
+    let rules = exclusion_rules(conn);
+    let mut indexed = 0;
+    for (i, path) in pending.iter().enumerate() {
+        eprintln!("[{}/{}] {}", i + 1, pending.len(), path.display());
+        let created_at = get_file_created_at(path).unwrap_or_else(now_millis_string);
+        match indexer.index_file(conn, &rules, path, &created_at) {
+            Ok(true) => {
+                indexed += 1;
+                out.line(&path.to_string_lossy());
+            }
+            Ok(false) => {}
+            Err(e) => eprintln!("Skipped {}: {}", path.display(), e),
+        }
+    }
+    eprintln!("Indexed {} screenshots", indexed);
+    Ok(())
+}
+
+fn search(args: &Args, data_dir: &Path, conn: &Connection, out: &mut Output) -> Result<(), String> {
+    let query = args.positional.join(" ");
+    if query.trim().is_empty() {
+        return Err("search needs a query".to_string());
+    }
+    let limit = args.limit.unwrap_or(DEFAULT_LIMIT);
+
+    if args.semantic || args.hybrid {
+        for hit in ranked_search(data_dir, conn, &query, limit.max(1), args.hybrid, false)? {
+            if args.json {
+                out.json(&hit);
+            } else {
+                out.line(&hit.entry.path);
+            }
+        }
+        return Ok(());
+    }
+
+    let mut paths = query_entry_paths(conn, &query::Query::parse(&query)?)?;
+    paths.truncate(limit);
+    print_entries(args, conn, &paths, out);
+    Ok(())
+}
+
+fn show(args: &Args, conn: &Connection, out: &mut Output) -> Result<(), String> {
+    let path = entry_path(first_arg(args, "path")?);
+    let details = load_entry_details(conn, &path)?;
+    if args.json {
+        out.json(&details);
+        return Ok(());
+    }
+    let EntryDetails { entry, entities } = details;
+
+    out.line(&entry.path);
+    if let Some(date) = format_millis(&entry.at) {
+        out.line(&format!("Date: {}", date));
+    }
+    let tags = parse_json_list(entry.tags.clone());
+    let custom_tags = parse_json_list(entry.custom_tags.clone());
+    if !tags.is_empty() {
+        out.line(&format!("Tags: {}", tags.join(", ")));
+    }
+    if !custom_tags.is_empty() {
+        out.line(&format!("Custom tags: {}", custom_tags.join(", ")));
+    }
+    if entry.pinned {
+        out.line("Pinned");
+    }
+    if entry.sensitive {
+        out.line("Sensitive");
+    }
+    for entity in &entities {
+        out.line(&format!("{}: {}", entity.kind, entity.value));
+    }
+    out.line("");
+    out.line(&entry.text);
+    Ok(())
+}
+
+fn tags(args: &Args, conn: &Connection, out: &mut Output) -> Result<(), String> {
+    for tag in &tag_counts(conn)? {
+        if args.json {
+            out.json(tag);
+        } else {
+            out.line(&format!("{}\t{}", tag.count, tag.tag));
+        }
+    }
+    Ok(())
+}
+
+fn similar(args: &Args, conn: &Connection, out: &mut Output) -> Result<(), String> {
+    let path = entry_path(first_arg(args, "path")?);
+    let (results, _) = similar_entries(conn, &path, args.limit.unwrap_or(DEFAULT_LIMIT).max(1))?;
+    for result in &results {
+        if args.json {
+            out.json(result);
+        } else {
+            out.line(&result.entry.path);
+        }
+    }
+    Ok(())
+}
+
+// Same lossless JSON Lines as export_index
+fn export(args: &Args, conn: &Connection, out: &mut Output) -> Result<(), String> {
+    let query = query::Query::parse(&args.positional.join(" "))?;
+    let entries = load_exported_entries(conn, &query)?;
+    let searches = saved_searches(conn)?;
+    for record in export::records(&now_millis_string(), &entries, &searches) {
+        out.json(&record);
+    }
+    Ok(())
+}
+
+// Same merge as import_index; bundle images go into the app's library folder
+fn import(args: &Args, indexer: &Indexer, conn: &Connection, out: &mut Output) -> Result<(), String> {
+    let source = first_arg(args, "file or bundle folder")?;
+    let library = library_root(read_setting(conn, LIBRARY_DIR_KEY));
+    let summary = import_from(indexer, conn, Path::new(source), library.as_deref())?;
+    for path in &summary.missing {
+        eprintln!("No image for {}", path);
+    }
+    if args.json {
+        out.json(&summary);
+    } else {
+        out.line(&format!(
+            "Imported {}, merged {}, skipped {}, {} without an image, {} saved searches",
+            summary.imported, summary.merged, summary.skipped, summary.missing.len(), summary.saved_searches
+        ));
+    }
+    Ok(())
+}
+
+// Keeps each screenshot's capture date, pin and custom tags; only the text and what's derived from it change
+fn reocr(args: &Args, indexer: &Indexer, conn: &Connection, out: &mut Output) -> Result<(), String> {
+    let paths: Vec<String> = if args.all {
+        let mut stmt = conn.prepare("SELECT path FROM entries WHERE COALESCE(trashed, 0) = 0 ORDER BY created_at")
+            .map_err(|e| format!("Query error: {}", e))?;
+        let rows = stmt.query_map([], |row| row.get(0))
+            .map_err(|e| format!("Query map error: {}", e))?;
+        rows.collect::<Result<_, _>>()
+            .map_err(|e| format!("Row error: {}", e))?
+    } else if args.positional.is_empty() {
+        return Err("reocr needs paths, or --all".to_string());
+    } else {
+        args.positional.iter().map(|arg| entry_path(arg)).collect()
+    };
+
+    let rules = exclusion_rules(conn);
+    let mut updated = 0;
+    for (i, path) in paths.iter().enumerate() {
+        eprintln!("[{}/{}] {}", i + 1, paths.len(), path);
+        let Some(entry) = load_entry_by_path(conn, path) else {
+            eprintln!("Skipped {}: not indexed", path);
+            continue;
+        };
+        match indexer.index_file(conn, &rules, Path::new(path), &entry.at) {
+            Ok(true) => {
+                updated += 1;
+                out.line(path);
+            }
+            Ok(false) => eprintln!("Skipped {}: excluded", path),
+            Err(e) => eprintln!("Skipped {}: {}", path, e),
+        }
+    }
+    eprintln!("Re-ran OCR on {} screenshots", updated);
+    Ok(())
+}
@@ -0,0 +1,320 @@
+// Optional CLIP zero-shot classifier for screenshots
+// Expects an exported CLIP model under <app data>/models/clip/:
+//   vision_model.onnx  (pixel_values -> image_embeds)
+//   text_model.onnx    (input_ids [+ attention_mask] -> text_embeds)
+//   tokenizer.json
+// Scores are cached per entry in entries.clip_scores and keyed by the model fingerprint and the
+// image's content hash;
+// the image embedding is kept alongside in entries.clip_embedding for similarity search
+
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use crate::embeddings::{blob_to_vector, vector_to_blob};
+use std::collections::HashMap;
+use std::path::Path;
+
+// Minimum softmax probability before the model's label wins over the heuristics
+const CLIP_MIN_CONFIDENCE: f32 = 0.45;
+
+// Collection probabilities plus the normalized image embedding
+type ZeroShot = (Vec<(String, f32)>, Vec<f32>);
+
+#[derive(Serialize, Deserialize)]
+struct CachedClipScores {
+    model: String,
+    // content_hash of the image the scores are for (absent in caches from older versions)
+    #[serde(default)]
+    image: Option<String>,
+    scores: HashMap<String, f32>,
+}
+
+// Collection probabilities for an entry, highest first
+// Returns None when no CLIP model is installed (or the app was built without the `onnx` feature)
+pub(crate) fn collection_scores(data_dir: &Path, conn: &Connection, path: &str) -> Option<Vec<(String, f32)>> {
+    let dir = crate::onnx::model_dir(data_dir, "clip");
+    let fingerprint = crate::onnx::model_fingerprint(&dir.join("vision_model.onnx"))?;
+
+    let image: Option<String> = conn
+        .query_row("SELECT content_hash FROM entries WHERE path = ?1", rusqlite::params![path], |row| row.get(0))
+        .ok()
+        .flatten();
+    if let Some(cached) = load_cached_scores(conn, path, &fingerprint, image.as_deref()) {
+        return Some(sorted_scores(cached));
+    }
+
+    let (scores, embedding) = match backend::zero_shot(&dir, &fingerprint, path) {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("[CLIP] Zero-shot classification failed for {}: {}", path, e);
+            return None;
+        }
+    };
+
+    let cached = CachedClipScores {
+        model: fingerprint,
+        image,
+        scores: scores.iter().cloned().collect(),
+    };
+    let json = serde_json::to_string(&cached).unwrap_or_else(|_| "{}".to_string());
+    if let Err(e) = conn.execute(
+        "UPDATE entries SET clip_scores = ?1, clip_embedding = ?2 WHERE path = ?3",
+        rusqlite::params![json, vector_to_blob(&embedding), path],
+    ) {
+        eprintln!("[CLIP] Failed to cache scores for {}: {}", path, e);
+    }
+
+    Some(sorted_scores(cached.scores))
+}
+
+// The top label, if the model is confident enough to skip the heuristics
+pub(crate) fn confident_tags(scores: &[(String, f32)]) -> Vec<String> {
+    match scores.first() {
+        Some((label, score)) if *score >= CLIP_MIN_CONFIDENCE => vec![label.clone()],
+        _ => vec![],
+    }
+}
+
+// Cached CLIP image embeddings (L2-normalized) by path
+// Only entries classified since the model was installed have one
+pub(crate) fn image_embeddings(conn: &Connection) -> HashMap<String, Vec<f32>> {
+    let mut stmt = match conn.prepare("SELECT path, clip_embedding FROM entries WHERE clip_embedding IS NOT NULL") {
+        Ok(stmt) => stmt,
+        Err(e) => {
+            eprintln!("[CLIP] Failed to query embeddings: {}", e);
+            return HashMap::new();
+        }
+    };
+    stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, Vec<u8>>(1)?)))
+        .map(|rows| {
+            rows.filter_map(|row| row.ok())
+                .map(|(path, blob)| (path, blob_to_vector(&blob)))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn load_cached_scores(conn: &Connection, path: &str, fingerprint: &str, image: Option<&str>) -> Option<HashMap<String, f32>> {
+    let json: Option<String> = conn
+        .query_row(
+            "SELECT clip_scores FROM entries WHERE path = ?1",
+            rusqlite::params![path],
+            |row| row.get(0),
+        )
+        .ok()?;
+    let cached: CachedClipScores = serde_json::from_str(&json?).ok()?;
+    // A different model was installed since these scores were computed
+    if cached.model != fingerprint {
+        return None;
+    }
+    // Or the file at this path changed
+    if image.is_none() || cached.image.as_deref() != image {
+        return None;
+    }
+    Some(cached.scores)
+}
+
+fn sorted_scores(scores: HashMap<String, f32>) -> Vec<(String, f32)> {
+    let mut sorted: Vec<(String, f32)> = scores.into_iter().collect();
+    sorted.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+    sorted
+}
+
+#[cfg(not(feature = "onnx"))]
+mod backend {
+    use std::path::Path;
+    use std::sync::Once;
+
+    pub(super) fn zero_shot(dir: &Path, _fingerprint: &str, _path: &str) -> Result<super::ZeroShot, String> {
+        static WARNED: Once = Once::new();
+        WARNED.call_once(|| {
+            println!("[CLIP] Model found in {} but this build has no `onnx` feature - using visual heuristics",
+                     dir.display());
+        });
+        Err("ONNX support not compiled in".to_string())
+    }
+}
+
+#[cfg(feature = "onnx")]
+mod backend {
+    use crate::onnx::{ensure_runtime, l2_normalize};
+    use image::imageops::FilterType;
+    use ort::session::{builder::GraphOptimizationLevel, Session, SessionOutputs};
+    use ort::value::Tensor;
+    use std::path::Path;
+    use std::sync::Mutex;
+    use tokenizers::Tokenizer;
+
+    const INPUT_SIZE: u32 = 224;
+    // CLIP's learned logit scale (exp(4.6052) ~= 100)
+    const LOGIT_SCALE: f32 = 100.0;
+    const MEAN: [f32; 3] = [0.481_454_7, 0.457_827_5, 0.408_210_7];
+    const STD: [f32; 3] = [0.268_629_5, 0.261_302_6, 0.275_777_1];
+
+    // Zero-shot prompt for each collection - CLIP matches images against sentences, not bare labels
+    const COLLECTION_PROMPTS: [(&str, &str); 8] = [
+        ("Messages", "a screenshot of a chat conversation with message bubbles"),
+        ("Code", "a screenshot of source code in a code editor"),
+        ("Design", "a screenshot of a design tool with shapes and color palettes"),
+        ("Receipts", "a screenshot of a receipt or invoice with prices"),
+        ("Browser", "a screenshot of a web page in a web browser"),
+        ("Terminal", "a screenshot of a terminal window with command line output"),
+        ("Errors", "a screenshot of an error message dialog"),
+        ("Images", "a photo or picture"),
+    ];
+
+    struct ClipModel {
+        vision: Session,
+        label_embeddings: Vec<(String, Vec<f32>)>,
+    }
+
+    // Fingerprint of the loaded model plus the model itself (None if loading failed,
+    // so we don't retry the load for every screenshot)
+    static MODEL: Mutex<Option<(String, Option<ClipModel>)>> = Mutex::new(None);
+
+    pub(super) fn zero_shot(dir: &Path, fingerprint: &str, path: &str) -> Result<super::ZeroShot, String> {
+        let mut guard = MODEL.lock().unwrap();
+        let stale = guard.as_ref().map(|(loaded, _)| loaded != fingerprint).unwrap_or(true);
+        if stale {
+            let model = match load_model(dir) {
+                Ok(model) => {
+                    println!("[CLIP] ✅ Loaded CLIP model from {}", dir.display());
+                    Some(model)
+                }
+                Err(e) => {
+                    eprintln!("[CLIP] ❌ Failed to load CLIP model: {}", e);
+                    None
+                }
+            };
+            *guard = Some((fingerprint.to_string(), model));
+        }
+
+        let model = guard
+            .as_mut()
+            .and_then(|(_, model)| model.as_mut())
+            .ok_or_else(|| "CLIP model unavailable".to_string())?;
+
+        let pixels = preprocess(Path::new(path))?;
+        let size = INPUT_SIZE as usize;
+        let input = Tensor::from_array(([1usize, 3, size, size], pixels))
+            .map_err(|e| format!("Failed to build image tensor: {}", e))?;
+        let outputs = model
+            .vision
+            .run(ort::inputs!["pixel_values" => input])
+            .map_err(|e| format!("Vision model failed: {}", e))?;
+        let mut image_embedding = extract_embedding(&outputs, "image_embeds")?;
+        drop(outputs);
+        l2_normalize(&mut image_embedding);
+
+        let logits: Vec<f32> = model
+            .label_embeddings
+            .iter()
+            .map(|(_, text_embedding)| LOGIT_SCALE * crate::onnx::cosine_similarity(&image_embedding, text_embedding))
+            .collect();
+        let probabilities = softmax(&logits);
+
+        let scores = model
+            .label_embeddings
+            .iter()
+            .map(|(label, _)| label.clone())
+            .zip(probabilities)
+            .collect();
+        Ok((scores, image_embedding))
+    }
+
+    fn load_model(dir: &Path) -> Result<ClipModel, String> {
+        let models_root = dir.parent().unwrap_or(dir);
+        if !ensure_runtime(models_root) {
+            return Err("ONNX Runtime not available".to_string());
+        }
+
+        let vision = build_session(&dir.join("vision_model.onnx"))?;
+        let mut text = build_session(&dir.join("text_model.onnx"))?;
+        let tokenizer = Tokenizer::from_file(dir.join("tokenizer.json"))
+            .map_err(|e| format!("Failed to load tokenizer: {}", e))?;
+
+        // Label embeddings only depend on the prompts, so compute them once per model load
+        let mut label_embeddings = Vec::new();
+        for (label, prompt) in COLLECTION_PROMPTS {
+            let mut embedding = embed_prompt(&mut text, &tokenizer, prompt)?;
+            l2_normalize(&mut embedding);
+            label_embeddings.push((label.to_string(), embedding));
+        }
+
+        Ok(ClipModel { vision, label_embeddings })
+    }
+
+    fn build_session(model_path: &Path) -> Result<Session, String> {
+        Session::builder()
+            .and_then(|builder| builder.with_optimization_level(GraphOptimizationLevel::Level3))
+            .and_then(|builder| builder.with_intra_threads(2))
+            .and_then(|builder| builder.commit_from_file(model_path))
+            .map_err(|e| format!("Failed to load {}: {}", model_path.display(), e))
+    }
+
+    fn embed_prompt(text: &mut Session, tokenizer: &Tokenizer, prompt: &str) -> Result<Vec<f32>, String> {
+        let encoding = tokenizer
+            .encode(prompt, true)
+            .map_err(|e| format!("Tokenization failed: {}", e))?;
+        let ids: Vec<i64> = encoding.get_ids().iter().map(|&id| id as i64).collect();
+        let mask: Vec<i64> = encoding.get_attention_mask().iter().map(|&m| m as i64).collect();
+        let len = ids.len();
+
+        let input_ids = Tensor::from_array(([1usize, len], ids))
+            .map_err(|e| format!("Failed to build input_ids: {}", e))?;
+        let wants_mask = text.inputs.iter().any(|input| input.name == "attention_mask");
+        let inputs = if wants_mask {
+            let attention_mask = Tensor::from_array(([1usize, len], mask))
+                .map_err(|e| format!("Failed to build attention_mask: {}", e))?;
+            ort::inputs!["input_ids" => input_ids, "attention_mask" => attention_mask]
+        } else {
+            ort::inputs!["input_ids" => input_ids]
+        };
+
+        let outputs = text
+            .run(inputs)
+            .map_err(|e| format!("Text model failed: {}", e))?;
+        extract_embedding(&outputs, "text_embeds")
+    }
+
+    // Prefer the named projection output, falling back to the first output
+    fn extract_embedding(outputs: &SessionOutputs, preferred: &str) -> Result<Vec<f32>, String> {
+        let value = outputs.get(preferred).unwrap_or(&outputs[0]);
+        let (_, data) = value
+            .try_extract_tensor::<f32>()
+            .map_err(|e| format!("Unexpected model output: {}", e))?;
+        Ok(data.to_vec())
+    }
+
+    // CLIP preprocessing: resize shortest side to 224, center crop, normalize, CHW layout
+    fn preprocess(path: &Path) -> Result<Vec<f32>, String> {
+        let img = image::open(path).map_err(|e| format!("Failed to open image: {}", e))?;
+        let (width, height) = (img.width().max(1), img.height().max(1));
+        let scale = INPUT_SIZE as f32 / width.min(height) as f32;
+        let resized = img.resize_exact(
+            ((width as f32 * scale).round() as u32).max(INPUT_SIZE),
+            ((height as f32 * scale).round() as u32).max(INPUT_SIZE),
+            FilterType::CatmullRom,
+        );
+        let left = (resized.width() - INPUT_SIZE) / 2;
+        let top = (resized.height() - INPUT_SIZE) / 2;
+        let cropped = resized.crop_imm(left, top, INPUT_SIZE, INPUT_SIZE).to_rgb8();
+
+        let plane = (INPUT_SIZE * INPUT_SIZE) as usize;
+        let mut data = vec![0.0f32; plane * 3];
+        for (x, y, pixel) in cropped.enumerate_pixels() {
+            let offset = (y * INPUT_SIZE + x) as usize;
+            for channel in 0..3 {
+                let value = pixel[channel] as f32 / 255.0;
+                data[channel * plane + offset] = (value - MEAN[channel]) / STD[channel];
+            }
+        }
+        Ok(data)
+    }
+
+    fn softmax(logits: &[f32]) -> Vec<f32> {
+        let max = logits.iter().cloned().fold(f32::NEG_INFINITY, f32::max);
+        let exps: Vec<f32> = logits.iter().map(|l| (l - max).exp()).collect();
+        let sum: f32 = exps.iter().sum();
+        exps.iter().map(|e| e / sum).collect()
+    }
+}
@@ -0,0 +1,249 @@
+// Optional local text embeddings for semantic search
+// Expects a sentence-transformers model exported to ONNX under <app data>/models/text-embedding/:
+//   model.onnx      (input_ids, attention_mask[, token_type_ids] -> last_hidden_state or sentence_embedding)
+//   tokenizer.json
+// e.g. all-MiniLM-L6-v2. Vectors are stored in the `embeddings` table tagged with the model fingerprint
+
+use rusqlite::Connection;
+use std::path::{Path, PathBuf};
+
+const MODEL_NAME: &str = "text-embedding";
+
+// Installed model directory + fingerprint, None when no model is present
+pub(crate) fn model_info(data_dir: &Path) -> Option<(PathBuf, String)> {
+    let dir = crate::onnx::model_dir(data_dir, MODEL_NAME);
+    let fingerprint = crate::onnx::model_fingerprint(&dir.join("model.onnx"))?;
+    Some((dir, fingerprint))
+}
+
+// Embed an entry's OCR text and store the vector
+// Returns Ok(false) when there's nothing to do (no model installed or no text)
+pub(crate) fn index_entry(data_dir: &Path, conn: &Connection, path: &str, text: &str) -> Result<bool, String> {
+    let Some((dir, fingerprint)) = model_info(data_dir) else {
+        return Ok(false);
+    };
+    if text.trim().is_empty() {
+        return Ok(false);
+    }
+
+    let vector = backend::embed(&dir, &fingerprint, text)?;
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+        .to_string();
+    conn.execute(
+        "INSERT OR REPLACE INTO embeddings (path, model, vector, created_at) VALUES (?1, ?2, ?3, ?4)",
+        rusqlite::params![path, fingerprint, vector_to_blob(&vector), now],
+    )
+    .map_err(|e| format!("Failed to store embedding: {}", e))?;
+
+    println!("[EMBED] ✅ Embedded {} ({} dims)", path, vector.len());
+    Ok(true)
+}
+
+// Nearest entries to the query by cosine similarity, highest first
+// Brute force over all stored vectors - a few MB even for 10k entries
+pub(crate) fn vector_search(data_dir: &Path, conn: &Connection, query: &str, k: usize) -> Result<Vec<(String, f32)>, String> {
+    let (dir, fingerprint) = model_info(data_dir)
+        .ok_or_else(|| format!("No text embedding model installed (expected models/{}/model.onnx)", MODEL_NAME))?;
+    let query_vector = backend::embed(&dir, &fingerprint, query)?;
+
+    let mut stmt = conn
+        .prepare("SELECT path, vector FROM embeddings WHERE model = ?1")
+        .map_err(|e| format!("Query error: {}", e))?;
+    let rows = stmt
+        .query_map(rusqlite::params![fingerprint], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, Vec<u8>>(1)?))
+        })
+        .map_err(|e| format!("Query map error: {}", e))?;
+
+    let mut scored: Vec<(String, f32)> = Vec::new();
+    for row in rows {
+        let (path, blob) = row.map_err(|e| format!("Row error: {}", e))?;
+        let vector = blob_to_vector(&blob);
+        if vector.len() == query_vector.len() {
+            scored.push((path, crate::onnx::cosine_similarity(&query_vector, &vector)));
+        }
+    }
+
+    scored.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+    scored.truncate(k);
+    Ok(scored)
+}
+
+// Entries with text but no vector from the installed model
+pub(crate) fn missing_entries(data_dir: &Path, conn: &Connection) -> Result<Vec<(String, String)>, String> {
+    let (_, fingerprint) = model_info(data_dir)
+        .ok_or_else(|| format!("No text embedding model installed (expected models/{}/model.onnx)", MODEL_NAME))?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT e.path, e.text FROM entries e
+             LEFT JOIN embeddings v ON v.path = e.path AND v.model = ?1
+             WHERE v.path IS NULL AND TRIM(e.text) != '' AND COALESCE(e.trashed, 0) = 0",
+        )
+        .map_err(|e| format!("Query error: {}", e))?;
+    let rows = stmt
+        .query_map(rusqlite::params![fingerprint], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(|e| format!("Query map error: {}", e))?;
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Row error: {}", e))
+}
+
+// Vectors are stored as little-endian f32 BLOBs
+pub(crate) fn vector_to_blob(vector: &[f32]) -> Vec<u8> {
+    vector.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+pub(crate) fn blob_to_vector(blob: &[u8]) -> Vec<f32> {
+    blob.chunks_exact(4)
+        .map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        .collect()
+}
+
+#[cfg(not(feature = "onnx"))]
+mod backend {
+    use std::path::Path;
+
+    pub(super) fn embed(_dir: &Path, _fingerprint: &str, _text: &str) -> Result<Vec<f32>, String> {
+        Err("Text embedding model installed but this build has no `onnx` feature".to_string())
+    }
+}
+
+#[cfg(feature = "onnx")]
+mod backend {
+    use crate::onnx::{ensure_runtime, l2_normalize};
+    use ort::session::{builder::GraphOptimizationLevel, Session};
+    use ort::value::Tensor;
+    use std::path::Path;
+    use std::sync::Mutex;
+    use tokenizers::{Tokenizer, TruncationParams};
+
+    // MiniLM was trained on 256 word pieces; longer OCR dumps are truncated
+    const MAX_TOKENS: usize = 256;
+
+    struct TextModel {
+        session: Session,
+        tokenizer: Tokenizer,
+    }
+
+    // Fingerprint of the loaded model plus the model itself (None if loading failed)
+    static MODEL: Mutex<Option<(String, Option<TextModel>)>> = Mutex::new(None);
+
+    pub(super) fn embed(dir: &Path, fingerprint: &str, text: &str) -> Result<Vec<f32>, String> {
+        let mut guard = MODEL.lock().unwrap();
+        let stale = guard.as_ref().map(|(loaded, _)| loaded != fingerprint).unwrap_or(true);
+        if stale {
+            let model = match load_model(dir) {
+                Ok(model) => {
+                    println!("[EMBED] ✅ Loaded text embedding model from {}", dir.display());
+                    Some(model)
+                }
+                Err(e) => {
+                    eprintln!("[EMBED] ❌ Failed to load text embedding model: {}", e);
+                    None
+                }
+            };
+            *guard = Some((fingerprint.to_string(), model));
+        }
+
+        let model = guard
+            .as_mut()
+            .and_then(|(_, model)| model.as_mut())
+            .ok_or_else(|| "Text embedding model unavailable".to_string())?;
+
+        let encoding = model
+            .tokenizer
+            .encode(text, true)
+            .map_err(|e| format!("Tokenization failed: {}", e))?;
+        let ids: Vec<i64> = encoding.get_ids().iter().map(|&id| id as i64).collect();
+        let mask: Vec<i64> = encoding.get_attention_mask().iter().map(|&m| m as i64).collect();
+        let len = ids.len();
+
+        let wants = |name: &str| model.session.inputs.iter().any(|input| input.name == name);
+        let wants_token_types = wants("token_type_ids");
+
+        let input_ids = Tensor::from_array(([1usize, len], ids))
+            .map_err(|e| format!("Failed to build input_ids: {}", e))?;
+        let attention_mask = Tensor::from_array(([1usize, len], mask.clone()))
+            .map_err(|e| format!("Failed to build attention_mask: {}", e))?;
+        let inputs = if wants_token_types {
+            let token_type_ids = Tensor::from_array(([1usize, len], vec![0i64; len]))
+                .map_err(|e| format!("Failed to build token_type_ids: {}", e))?;
+            ort::inputs![
+                "input_ids" => input_ids,
+                "attention_mask" => attention_mask,
+                "token_type_ids" => token_type_ids
+            ]
+        } else {
+            ort::inputs!["input_ids" => input_ids, "attention_mask" => attention_mask]
+        };
+
+        let outputs = model
+            .session
+            .run(inputs)
+            .map_err(|e| format!("Embedding model failed: {}", e))?;
+
+        // Pooled exports give [1, dims]; raw exports give token states [1, tokens, dims]
+        let value = outputs.get("sentence_embedding").unwrap_or(&outputs[0]);
+        let (shape, data) = value
+            .try_extract_tensor::<f32>()
+            .map_err(|e| format!("Unexpected model output: {}", e))?;
+        let mut embedding = if shape.len() == 3 {
+            mean_pool(data, shape[1] as usize, shape[2] as usize, &mask)
+        } else {
+            data.to_vec()
+        };
+        l2_normalize(&mut embedding);
+        Ok(embedding)
+    }
+
+    fn load_model(dir: &Path) -> Result<TextModel, String> {
+        let models_root = dir.parent().unwrap_or(dir);
+        if !ensure_runtime(models_root) {
+            return Err("ONNX Runtime not available".to_string());
+        }
+
+        let model_path = dir.join("model.onnx");
+        let session = Session::builder()
+            .and_then(|builder| builder.with_optimization_level(GraphOptimizationLevel::Level3))
+            .and_then(|builder| builder.with_intra_threads(2))
+            .and_then(|builder| builder.commit_from_file(&model_path))
+            .map_err(|e| format!("Failed to load {}: {}", model_path.display(), e))?;
+
+        let mut tokenizer = Tokenizer::from_file(dir.join("tokenizer.json"))
+            .map_err(|e| format!("Failed to load tokenizer: {}", e))?;
+        tokenizer
+            .with_truncation(Some(TruncationParams {
+                max_length: MAX_TOKENS,
+                ..Default::default()
+            }))
+            .map_err(|e| format!("Failed to configure truncation: {}", e))?;
+        // Single inputs only - padding would just add masked tokens
+        tokenizer.with_padding(None);
+
+        Ok(TextModel { session, tokenizer })
+    }
+
+    // Average the token states, ignoring padding (sentence-transformers mean pooling)
+    fn mean_pool(states: &[f32], tokens: usize, dims: usize, mask: &[i64]) -> Vec<f32> {
+        let mut pooled = vec![0.0f32; dims];
+        let mut count = 0.0f32;
+        for token in 0..tokens {
+            if mask.get(token).copied().unwrap_or(0) == 0 {
+                continue;
+            }
+            let offset = token * dims;
+            for (sum, value) in pooled.iter_mut().zip(&states[offset..offset + dims]) {
+                *sum += value;
+            }
+            count += 1.0;
+        }
+        if count > 0.0 {
+            for value in pooled.iter_mut() {
+                *value /= count;
+            }
+        }
+        pooled
+    }
+}
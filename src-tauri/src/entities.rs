@@ -0,0 +1,303 @@
+// Structured entities in OCR text: phone numbers, dates, money, IBANs, tracking and order numbers,
+// hex colours, IPs, file paths, git SHAs and crypto addresses (plus URLs and emails)
+// Kinds double as `has:` filter names, e.g. has:phone, has:iban, has:crypto
+
+use regex::{Captures, Regex};
+use serde::Serialize;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::sync::LazyLock;
+
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct Entity {
+    pub(crate) kind: &'static str,
+    pub(crate) value: String,
+    // Byte offsets into the OCR text
+    pub(crate) start: usize,
+    pub(crate) end: usize,
+}
+
+// A pattern for one kind; `group` is the capture holding the entity (0 = whole match),
+// `validate` rejects look-alikes and returns the normalized value
+struct Extractor {
+    kind: &'static str,
+    pattern: &'static LazyLock<Regex>,
+    group: usize,
+    validate: fn(&Captures, &str) -> Option<String>,
+}
+
+macro_rules! pattern {
+    ($name:ident, $re:expr) => {
+        static $name: LazyLock<Regex> = LazyLock::new(|| Regex::new($re).unwrap());
+    };
+}
+
+pattern!(URL, r"https?://[^\s]+");
+pattern!(EMAIL, r"\b[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}\b");
+pattern!(IBAN, r"\b[A-Z]{2}\d{2}(?: ?[A-Z0-9]{4}){2,7}(?: ?[A-Z0-9]{1,3})?\b");
+pattern!(CRYPTO, r"\b(?:0x[a-fA-F0-9]{40}|bc1[ac-hj-np-z02-9]{11,71}|[13][a-km-zA-HJ-NP-Z1-9]{25,34})\b");
+pattern!(TRACKING, r"(?i)\b(?:1Z[0-9A-Z]{16}|9[2-5]\d{20}|tracking(?:\s*(?:number|no\.?|#|id))?\s*[:#]?\s*([A-Z0-9]{8,30}))\b");
+pattern!(ORDER, r"(?i)\border\b\s*(?:number|no\.?|#|id)?\s*[:#]?\s*([A-Z0-9][A-Z0-9-]{3,24})\b");
+pattern!(IPV4, r"\b\d{1,3}(?:\.\d{1,3}){3}\b");
+pattern!(IPV6, r"(?i)(?:[0-9a-f]{0,4}:){2,7}[0-9a-f]{0,4}");
+pattern!(GIT_SHA, r"\b[0-9a-f]{7,40}\b");
+pattern!(MONEY, r"(?i)(?:([$€£¥₹]|\b(?:USD|EUR|GBP|JPY|CAD|AUD|CHF|INR)\b)\s?(-?\d+(?:[,.\s]\d{3})*(?:[.,]\d{1,2})?)|(-?\d+(?:[,.\s]\d{3})*(?:[.,]\d{1,2})?)\s?([$€£¥₹]|\b(?:USD|EUR|GBP|JPY|CAD|AUD|CHF|INR)\b))");
+pattern!(DATE, r"(?i)\b(?:(\d{4})-(\d{1,2})-(\d{1,2})|(\d{1,2})[/.](\d{1,2})[/.](\d{2}|\d{4})|\d{1,2}(?:st|nd|rd|th)?\s+(?:jan|feb|mar|apr|may|jun|jul|aug|sep|sept|oct|nov|dec)[a-z]*\.?,?\s+\d{4}|(?:jan|feb|mar|apr|may|jun|jul|aug|sep|sept|oct|nov|dec)[a-z]*\.?\s+\d{1,2}(?:st|nd|rd|th)?,?\s+\d{4})\b");
+// (area) 123-4567, +44 20 7946 0958, 555-123-4567
+pattern!(PHONE, r"(?:(?:\+\d{1,3}[\s.-]?)?\(\d{1,4}\)[\s.-]?\d{3,4}|\+\d{1,3}[\s.-]?\d{2,4}[\s.-]?\d{3,4}|\b\d{2,4}[\s.-]?\d{3,4})[\s.-]?\d{3,4}\b");
+pattern!(HEX_COLOR, r"#(?:[0-9a-fA-F]{8}|[0-9a-fA-F]{6}|[0-9a-fA-F]{3})\b");
+pattern!(FILE_PATH, r#"(?m)(?:^|[\s("'`])((?:~|[A-Za-z]:)?(?:[/\\][\w.@-]+){2,}[/\\]?)"#);
+
+// Earlier kinds claim their text first, so e.g. digits inside an IBAN never become a phone number
+static EXTRACTORS: &[Extractor] = &[
+    Extractor { kind: "url", pattern: &URL, group: 0, validate: as_matched },
+    Extractor { kind: "email", pattern: &EMAIL, group: 0, validate: as_matched },
+    Extractor { kind: "iban", pattern: &IBAN, group: 0, validate: valid_iban },
+    Extractor { kind: "crypto", pattern: &CRYPTO, group: 0, validate: as_matched },
+    Extractor { kind: "tracking", pattern: &TRACKING, group: 1, validate: tracking_number },
+    Extractor { kind: "order", pattern: &ORDER, group: 1, validate: order_number },
+    Extractor { kind: "ip", pattern: &IPV4, group: 0, validate: valid_ipv4 },
+    Extractor { kind: "ip", pattern: &IPV6, group: 0, validate: valid_ipv6 },
+    Extractor { kind: "color", pattern: &HEX_COLOR, group: 0, validate: hex_color },
+    Extractor { kind: "sha", pattern: &GIT_SHA, group: 0, validate: git_sha },
+    Extractor { kind: "money", pattern: &MONEY, group: 0, validate: money },
+    Extractor { kind: "date", pattern: &DATE, group: 0, validate: valid_date },
+    Extractor { kind: "phone", pattern: &PHONE, group: 0, validate: phone_number },
+    Extractor { kind: "path", pattern: &FILE_PATH, group: 1, validate: file_path },
+];
+
+pub(crate) fn is_kind(kind: &str) -> bool {
+    EXTRACTORS.iter().any(|extractor| extractor.kind == kind)
+}
+
+pub(crate) fn extract_entities(text: &str) -> Vec<Entity> {
+    let mut entities: Vec<Entity> = Vec::new();
+    for extractor in EXTRACTORS {
+        for captures in extractor.pattern.captures_iter(text) {
+            let Some(span) = captures.get(extractor.group).or_else(|| captures.get(0)) else {
+                continue;
+            };
+            let (start, end) = (span.start(), span.end());
+            if entities.iter().any(|entity| start < entity.end && entity.start < end) {
+                continue;
+            }
+            if let Some(value) = (extractor.validate)(&captures, span.as_str()) {
+                entities.push(Entity { kind: extractor.kind, value, start, end });
+            }
+        }
+    }
+    entities.sort_by_key(|entity| entity.start);
+    entities
+}
+
+fn as_matched(_: &Captures, matched: &str) -> Option<String> {
+    Some(matched.trim_end_matches(['.', ',', ')', ';']).to_string())
+}
+
+// ISO 13616 mod-97 checksum
+fn valid_iban(_: &Captures, matched: &str) -> Option<String> {
+    let compact: String = matched.chars().filter(|c| !c.is_whitespace()).collect();
+    if !(15..=34).contains(&compact.len()) {
+        return None;
+    }
+    let rearranged = compact[4..].chars().chain(compact[..4].chars());
+    let mut remainder = 0u32;
+    for c in rearranged {
+        let digits = c.to_digit(36)?;
+        remainder = if digits >= 10 {
+            (remainder * 100 + digits) % 97
+        } else {
+            (remainder * 10 + digits) % 97
+        };
+    }
+    (remainder == 1).then_some(compact)
+}
+
+fn tracking_number(captures: &Captures, matched: &str) -> Option<String> {
+    let number = captures.get(1).map(|m| m.as_str()).unwrap_or(matched);
+    number.chars().any(|c| c.is_ascii_digit()).then(|| number.to_uppercase())
+}
+
+fn order_number(_: &Captures, matched: &str) -> Option<String> {
+    matched.chars().any(|c| c.is_ascii_digit()).then(|| matched.to_uppercase())
+}
+
+fn valid_ipv4(_: &Captures, matched: &str) -> Option<String> {
+    matched.parse::<Ipv4Addr>().ok().map(|ip| ip.to_string())
+}
+
+fn valid_ipv6(_: &Captures, matched: &str) -> Option<String> {
+    // Clock times like 10:45:30 aren't addresses; real ones have hex groups or ::
+    if !matched.contains("::") && matched.split(':').count() < 8 {
+        return None;
+    }
+    if matched.split(':').filter(|group| !group.is_empty()).count() < 2 {
+        return None;
+    }
+    matched.parse::<Ipv6Addr>().ok().map(|ip| ip.to_string())
+}
+
+// Needs both digits and letters, or it's a plain number or an English word (e.g. "decade")
+fn git_sha(_: &Captures, matched: &str) -> Option<String> {
+    let has_digit = matched.chars().any(|c| c.is_ascii_digit());
+    let has_letter = matched.chars().any(|c| c.is_ascii_alphabetic());
+    (has_digit && has_letter).then(|| matched.to_string())
+}
+
+// Normalized to "<amount> <ISO code>", e.g. "1234.50 EUR"
+fn money(captures: &Captures, _: &str) -> Option<String> {
+    let (currency, amount) = match (captures.get(1), captures.get(2)) {
+        (Some(currency), Some(amount)) => (currency.as_str(), amount.as_str()),
+        _ => (captures.get(4)?.as_str(), captures.get(3)?.as_str()),
+    };
+    let code = currency_code(currency)?;
+    let amount = parse_amount(amount)?;
+    Some(format!("{:.2} {}", amount, code))
+}
+
+pub(crate) fn currency_code(currency: &str) -> Option<&'static str> {
+    match currency.to_uppercase().as_str() {
+        "$" | "USD" => Some("USD"),
+        "€" | "EUR" => Some("EUR"),
+        "£" | "GBP" => Some("GBP"),
+        "¥" | "JPY" => Some("JPY"),
+        "₹" | "INR" => Some("INR"),
+        "CAD" => Some("CAD"),
+        "AUD" => Some("AUD"),
+        "CHF" => Some("CHF"),
+        _ => None,
+    }
+}
+
+// "1,234.56", "1.234,56", "1 234,56" and "12.5" -> 1234.56 / 12.5
+// The last separator is the decimal point when it's followed by 1-2 digits
+pub(crate) fn parse_amount(amount: &str) -> Option<f64> {
+    let amount: String = amount.chars().filter(|c| !c.is_whitespace()).collect();
+    let decimal_at = amount
+        .rfind(['.', ','])
+        .filter(|&i| (1..=2).contains(&(amount.len() - i - 1)));
+    let normalized: String = amount
+        .char_indices()
+        .filter_map(|(i, c)| match c {
+            '.' | ',' if Some(i) == decimal_at => Some('.'),
+            '.' | ',' => None,
+            c => Some(c),
+        })
+        .collect();
+    normalized.parse().ok()
+}
+
+fn valid_date(captures: &Captures, matched: &str) -> Option<String> {
+    let in_range = |month: Option<u32>, day: Option<u32>| {
+        matches!(month, Some(1..=12)) && matches!(day, Some(1..=31))
+    };
+    let number = |i: usize| captures.get(i).and_then(|m| m.as_str().parse::<u32>().ok());
+    if captures.get(1).is_some() {
+        // ISO year-month-day
+        return in_range(number(2), number(3)).then(|| matched.to_string());
+    }
+    if captures.get(4).is_some() {
+        // Day and month order is ambiguous (US vs. everyone else) - accept either
+        let (a, b) = (number(4), number(5));
+        return (in_range(a, b) || in_range(b, a)).then(|| matched.to_string());
+    }
+    Some(matched.to_string())
+}
+
+fn phone_number(_: &Captures, matched: &str) -> Option<String> {
+    let digits: String = matched.chars().filter(|c| c.is_ascii_digit()).collect();
+    if !(7..=15).contains(&digits.len()) {
+        return None;
+    }
+    // Plain digit runs are usually amounts or IDs; phone numbers are grouped or start with +
+    let grouped = matched.starts_with('+') || matched.chars().any(|c| matches!(c, ' ' | '-' | '.' | '('));
+    grouped.then(|| if matched.starts_with('+') { format!("+{}", digits) } else { digits })
+}
+
+fn hex_color(_: &Captures, matched: &str) -> Option<String> {
+    // #123 is more likely an issue or order reference than a colour
+    if matched.len() == 4 && matched[1..].chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    Some(matched.to_lowercase())
+}
+
+fn file_path(_: &Captures, matched: &str) -> Option<String> {
+    // Fractions and dates like 1/2/3 aren't paths
+    matched
+        .chars()
+        .any(char::is_alphabetic)
+        .then(|| matched.trim_end_matches(['.', ',']).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn found(text: &str) -> Vec<(&'static str, String)> {
+        extract_entities(text).into_iter().map(|entity| (entity.kind, entity.value)).collect()
+    }
+
+    fn kinds(text: &str) -> Vec<&'static str> {
+        found(text).into_iter().map(|(kind, _)| kind).collect()
+    }
+
+    #[test]
+    fn iban_checksum() {
+        assert_eq!(found("IBAN: DE89 3704 0044 0532 0130 00"), [("iban", "DE89370400440532013000".to_string())]);
+        assert_eq!(found("GB82WEST12345698765432"), [("iban", "GB82WEST12345698765432".to_string())]);
+        // One digit off fails mod-97
+        assert!(!kinds("DE89 3704 0044 0532 0130 01").contains(&"iban"));
+        assert!(!kinds("GB82WEST12345698765433").contains(&"iban"));
+    }
+
+    #[test]
+    fn spans_are_byte_offsets() {
+        let text = "Café → call +44 20 7946 0958";
+        let entity = &extract_entities(text)[0];
+        assert_eq!(entity.kind, "phone");
+        assert_eq!(&text[entity.start..entity.end], "+44 20 7946 0958");
+        assert_eq!(entity.value, "+442079460958");
+    }
+
+    #[test]
+    fn money_is_normalized() {
+        assert_eq!(found("Total €1.234,50"), [("money", "1234.50 EUR".to_string())]);
+        assert_eq!(found("12.5 USD"), [("money", "12.50 USD".to_string())]);
+        assert_eq!(parse_amount("1 234,56"), Some(1234.56));
+        assert_eq!(parse_amount("1,234"), Some(1234.0));
+        assert_eq!(currency_code("chf"), Some("CHF"));
+        assert_eq!(currency_code("BTC"), None);
+    }
+
+    #[test]
+    fn dates_are_validated() {
+        assert_eq!(kinds("due 2026-10-18"), ["date"]);
+        assert_eq!(kinds("on 31/12/2026"), ["date"]);
+        assert_eq!(kinds("Oct 18th, 2026"), ["date"]);
+        assert!(!kinds("2026-13-40").contains(&"date"));
+    }
+
+    #[test]
+    fn look_alikes_are_rejected() {
+        // Clock times aren't IPv6, words aren't SHAs, #123 is a reference, 1/2/3 isn't a path
+        assert!(kinds("10:45:30").is_empty());
+        assert!(kinds("a decade ago").is_empty());
+        assert!(kinds("see #123").is_empty());
+        assert!(kinds("1/2/3").iter().all(|kind| *kind != "path"));
+        assert!(kinds("999.1.1.1").is_empty());
+        assert!(kinds("5551234567").is_empty());
+    }
+
+    #[test]
+    fn other_kinds() {
+        assert_eq!(found("server 192.168.1.20"), [("ip", "192.168.1.20".to_string())]);
+        assert_eq!(found("fe80::1"), [("ip", "fe80::1".to_string())]);
+        assert_eq!(found("colour #FF8800"), [("color", "#ff8800".to_string())]);
+        assert_eq!(found("commit 3f2a9c1"), [("sha", "3f2a9c1".to_string())]);
+        assert_eq!(found("Order #AB-12345"), [("order", "AB-12345".to_string())]);
+        assert_eq!(found("Tracking number: 1z999aa10123456784"), [("tracking", "1Z999AA10123456784".to_string())]);
+        assert_eq!(found("open ~/Documents/notes.txt."), [("path", "~/Documents/notes.txt".to_string())]);
+        assert_eq!(found("(see https://example.com/a)."), [("url", "https://example.com/a".to_string())]);
+        assert!(is_kind("iban") && !is_kind("text"));
+    }
+}
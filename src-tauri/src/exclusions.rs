@@ -0,0 +1,234 @@
+// Privacy rules that keep screenshots out of the index
+// Path rules (filename globs, folders) are checked before OCR runs; text rules (regexes,
+// secret and entity detection) right after it - either way before anything is stored
+//
+//   glob       *bank*.png, Screenshot*Wallet*; a pattern with '/' matches the whole path
+//   folder     ~/Desktop/private - everything inside it
+//   text       regex over the OCR text, case-insensitive, e.g. chase|wells fargo
+//   sensitive  comma-separated secret or entity kinds (otp, api_key, iban, ...); empty = any secret
+
+use crate::{entities, secrets};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct ExclusionRule {
+    pub(crate) kind: String,
+    #[serde(default)]
+    pub(crate) pattern: String,
+    // "skip" leaves the screenshot out entirely, "image_only" indexes it without its text
+    pub(crate) action: String,
+    #[serde(default = "enabled_by_default")]
+    pub(crate) enabled: bool,
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Exclusion {
+    ImageOnly,
+    Skip,
+}
+
+enum Matcher {
+    Glob { regex: Regex, whole_path: bool },
+    Folder(PathBuf),
+    Text(Regex),
+    Sensitive(Vec<String>),
+}
+
+#[derive(Default)]
+pub(crate) struct Rules {
+    rules: Vec<(Matcher, Exclusion)>,
+}
+
+impl Rules {
+    pub(crate) fn compile(rules: &[ExclusionRule]) -> Result<Rules, String> {
+        let mut compiled = Vec::new();
+        for rule in rules.iter().filter(|rule| rule.enabled) {
+            let exclusion = match rule.action.as_str() {
+                "skip" => Exclusion::Skip,
+                "image_only" => Exclusion::ImageOnly,
+                other => return Err(format!("Unknown exclusion action '{}' (use skip or image_only)", other)),
+            };
+            compiled.push((compile_matcher(rule)?, exclusion));
+        }
+        Ok(Rules { rules: compiled })
+    }
+
+    // Strongest exclusion from the glob and folder rules
+    pub(crate) fn check_path(&self, path: &Path) -> Option<Exclusion> {
+        self.rules
+            .iter()
+            .filter(|(matcher, _)| match matcher {
+                Matcher::Glob { regex, whole_path: true } => regex.is_match(&path.to_string_lossy()),
+                Matcher::Glob { regex, whole_path: false } => path
+                    .file_name()
+                    .is_some_and(|name| regex.is_match(&name.to_string_lossy())),
+                Matcher::Folder(folder) => path.starts_with(folder),
+                _ => false,
+            })
+            .map(|(_, exclusion)| *exclusion)
+            .max()
+    }
+
+    // Strongest exclusion from the text and sensitive rules
+    pub(crate) fn check_text(&self, text: &str) -> Option<Exclusion> {
+        if text.trim().is_empty() {
+            return None;
+        }
+        let mut found_kinds: Option<Vec<&str>> = None;
+        self.rules
+            .iter()
+            .filter(|(matcher, _)| match matcher {
+                Matcher::Text(regex) => regex.is_match(text),
+                Matcher::Sensitive(kinds) => {
+                    let found = found_kinds.get_or_insert_with(|| detected_kinds(text));
+                    if kinds.is_empty() {
+                        found.iter().any(|kind| secrets::is_kind(kind))
+                    } else {
+                        kinds.iter().any(|kind| found.contains(&kind.as_str()))
+                    }
+                }
+                _ => false,
+            })
+            .map(|(_, exclusion)| *exclusion)
+            .max()
+    }
+}
+
+fn compile_matcher(rule: &ExclusionRule) -> Result<Matcher, String> {
+    let pattern = rule.pattern.trim();
+    if pattern.is_empty() && rule.kind != "sensitive" {
+        return Err(format!("Exclusion rule '{}' needs a pattern", rule.kind));
+    }
+    match rule.kind.as_str() {
+        "glob" => Ok(Matcher::Glob { regex: glob_regex(&expand_home(pattern))?, whole_path: pattern.contains('/') }),
+        "folder" => Ok(Matcher::Folder(PathBuf::from(expand_home(pattern)))),
+        "text" => RegexBuilder::new(pattern)
+            .case_insensitive(true)
+            .build()
+            .map(Matcher::Text)
+            .map_err(|e| format!("Invalid exclusion regex '{}': {}", pattern, e)),
+        "sensitive" => {
+            let kinds: Vec<String> = pattern
+                .split(',')
+                .map(|kind| kind.trim().to_lowercase())
+                .filter(|kind| !kind.is_empty())
+                .collect();
+            if let Some(kind) = kinds.iter().find(|kind| !secrets::is_kind(kind) && !entities::is_kind(kind)) {
+                return Err(format!("Unknown secret or entity kind '{}'", kind));
+            }
+            Ok(Matcher::Sensitive(kinds))
+        }
+        other => Err(format!("Unknown exclusion rule kind '{}' (use glob, folder, text or sensitive)", other)),
+    }
+}
+
+fn detected_kinds(text: &str) -> Vec<&'static str> {
+    let mut kinds: Vec<&'static str> = secrets::detect_secrets(text).into_iter().map(|secret| secret.kind).collect();
+    kinds.extend(entities::extract_entities(text).into_iter().map(|entity| entity.kind));
+    kinds
+}
+
+fn expand_home(pattern: &str) -> String {
+    match (pattern.strip_prefix("~/"), std::env::var("HOME")) {
+        (Some(rest), Ok(home)) => format!("{}/{}", home.trim_end_matches('/'), rest),
+        _ => pattern.to_string(),
+    }
+}
+
+// * matches within one path segment, ** across segments, ? one character; case-insensitive
+fn glob_regex(glob: &str) -> Result<Regex, String> {
+    let mut regex = String::from("^");
+    let mut chars = glob.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                regex.push_str(".*");
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push('$');
+    RegexBuilder::new(&regex)
+        .case_insensitive(true)
+        .build()
+        .map_err(|e| format!("Invalid glob '{}': {}", glob, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(kind: &str, pattern: &str, action: &str) -> ExclusionRule {
+        ExclusionRule { kind: kind.to_string(), pattern: pattern.to_string(), action: action.to_string(), enabled: true }
+    }
+
+    #[test]
+    fn glob_wildcards() {
+        let name = glob_regex("*bank*.png").unwrap();
+        assert!(name.is_match("My BANK statement.PNG"));
+        assert!(!name.is_match("bank.jpg"));
+        assert!(!name.is_match("dir/bank.png"));
+        assert!(glob_regex("shot-?.png").unwrap().is_match("shot-1.png"));
+        assert!(!glob_regex("shot-?.png").unwrap().is_match("shot-12.png"));
+        // Regex characters in a glob are literal
+        assert!(glob_regex("a+b (1).png").unwrap().is_match("a+b (1).png"));
+        assert!(!glob_regex("a.png").unwrap().is_match("abpng"));
+    }
+
+    #[test]
+    fn double_star_crosses_folders() {
+        let rules = Rules::compile(&[rule("glob", "/Users/**/private/*.png", "skip")]).unwrap();
+        assert_eq!(rules.check_path(Path::new("/Users/me/a/private/x.png")), Some(Exclusion::Skip));
+        assert_eq!(rules.check_path(Path::new("/Users/me/private/sub/x.png")), None);
+    }
+
+    #[test]
+    fn strongest_exclusion_wins() {
+        let rules = Rules::compile(&[
+            rule("folder", "/shots/work", "image_only"),
+            rule("glob", "*wallet*", "skip"),
+            rule("text", "chase|wells fargo", "image_only"),
+            rule("sensitive", "iban", "skip"),
+        ])
+        .unwrap();
+        assert_eq!(rules.check_path(Path::new("/shots/work/a.png")), Some(Exclusion::ImageOnly));
+        assert_eq!(rules.check_path(Path::new("/shots/work/Wallet.png")), Some(Exclusion::Skip));
+        assert_eq!(rules.check_path(Path::new("/shots/workshop/a.png")), None);
+        assert_eq!(rules.check_text("Welcome to CHASE"), Some(Exclusion::ImageOnly));
+        assert_eq!(rules.check_text("Chase DE89 3704 0044 0532 0130 00"), Some(Exclusion::Skip));
+        assert_eq!(rules.check_text("   "), None);
+    }
+
+    #[test]
+    fn empty_sensitive_pattern_means_any_secret() {
+        let rules = Rules::compile(&[rule("sensitive", "", "skip")]).unwrap();
+        assert_eq!(rules.check_text("password: hunter22"), Some(Exclusion::Skip));
+        // Entities alone aren't secrets
+        assert_eq!(rules.check_text("call 555-123-4567"), None);
+    }
+
+    #[test]
+    fn invalid_rules_are_errors() {
+        for bad in [
+            rule("glob", "", "skip"),
+            rule("text", "(unclosed", "skip"),
+            rule("sensitive", "otp, nope", "skip"),
+            rule("regex", "x", "skip"),
+            rule("glob", "*.png", "delete"),
+        ] {
+            assert!(Rules::compile(&[bad]).is_err());
+        }
+        let mut disabled = rule("regex", "x", "skip");
+        disabled.enabled = false;
+        assert!(Rules::compile(&[disabled]).is_ok());
+    }
+}
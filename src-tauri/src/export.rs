@@ -0,0 +1,328 @@
+// Index export: everything Chronicle knows about each screenshot, outside chronicle.db
+//
+//   jsonl     lossless: a header line, then one line per entry and per saved search
+//   bundle    a folder with that JSONL as chronicle.jsonl and the images as images/<sha256>.<ext>
+//   csv       one row per entry for spreadsheets; saved searches go to <name>.saved-searches.csv
+//   markdown  one note per entry (front matter, image link, OCR text) for Obsidian and friends,
+//             plus "Saved Searches.md"
+//
+// import_index reads jsonl and bundles back
+
+use crate::{csv_field, format_millis, SavedSearch};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+// Bumped when a field changes meaning; new optional fields don't need it
+pub(crate) const FORMAT_VERSION: u32 = 1;
+pub(crate) const BUNDLE_INDEX: &str = "chronicle.jsonl";
+pub(crate) const BUNDLE_IMAGES: &str = "images";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct ExportedEntity {
+    pub(crate) kind: String,
+    pub(crate) value: String,
+    pub(crate) start: i64,
+    pub(crate) end: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct ExportedEntry {
+    pub(crate) path: String,
+    // SHA-256 of the image file, None when it was missing at export time
+    #[serde(default)]
+    pub(crate) content_hash: Option<String>,
+    pub(crate) text: String,
+    // Milliseconds since the epoch, as stored
+    pub(crate) created_at: String,
+    #[serde(default)]
+    pub(crate) tags: Vec<String>,
+    #[serde(default)]
+    pub(crate) custom_tags: Vec<String>,
+    #[serde(default)]
+    pub(crate) urls: Vec<String>,
+    #[serde(default)]
+    pub(crate) emails: Vec<String>,
+    #[serde(default)]
+    pub(crate) pinned: bool,
+    #[serde(default)]
+    pub(crate) sensitive: bool,
+    #[serde(default)]
+    pub(crate) sensitive_kinds: Vec<String>,
+    #[serde(default)]
+    pub(crate) entities: Vec<ExportedEntity>,
+    // Similarity hashes, hex-encoded
+    #[serde(default)]
+    pub(crate) perceptual_hash: Option<String>,
+    #[serde(default)]
+    pub(crate) phash: Option<String>,
+    #[serde(default)]
+    pub(crate) block_hash: Option<String>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub(crate) enum Record {
+    Header { format_version: u32, exported_at: String },
+    Entry(ExportedEntry),
+    SavedSearch(SavedSearch),
+}
+
+// The JSONL export, line by line
+pub(crate) fn records<'a>(
+    exported_at: &str,
+    entries: &'a [ExportedEntry],
+    searches: &'a [SavedSearch],
+) -> impl Iterator<Item = Record> + 'a {
+    let header = Record::Header { format_version: FORMAT_VERSION, exported_at: exported_at.to_string() };
+    std::iter::once(header)
+        .chain(entries.iter().cloned().map(Record::Entry))
+        .chain(searches.iter().cloned().map(Record::SavedSearch))
+}
+
+pub(crate) fn write_jsonl(dest: &Path, exported_at: &str, entries: &[ExportedEntry], searches: &[SavedSearch]) -> Result<(), String> {
+    let mut out = io::BufWriter::new(
+        fs::File::create(dest).map_err(|e| format!("Failed to create {}: {}", dest.display(), e))?,
+    );
+    for record in records(exported_at, entries, searches) {
+        let line = serde_json::to_string(&record)
+            .map_err(|e| format!("Failed to serialize export: {}", e))?;
+        writeln!(out, "{}", line).map_err(|e| format!("Failed to write {}: {}", dest.display(), e))?;
+    }
+    out.flush().map_err(|e| format!("Failed to write {}: {}", dest.display(), e))
+}
+
+// Images are stored by content hash, so the same screenshot is only copied once
+pub(crate) fn write_bundle(dir: &Path, exported_at: &str, entries: &[ExportedEntry], searches: &[SavedSearch]) -> Result<Vec<PathBuf>, String> {
+    let images = dir.join(BUNDLE_IMAGES);
+    fs::create_dir_all(&images)
+        .map_err(|e| format!("Failed to create {}: {}", images.display(), e))?;
+    for entry in entries {
+        let Some(image) = bundle_image(dir, entry) else {
+            eprintln!("[EXPORT] No image for {}, exporting its text only", entry.path);
+            continue;
+        };
+        if !image.exists() {
+            fs::copy(&entry.path, &image)
+                .map_err(|e| format!("Failed to copy {}: {}", entry.path, e))?;
+        }
+    }
+    let index = dir.join(BUNDLE_INDEX);
+    write_jsonl(&index, exported_at, entries, searches)?;
+    Ok(vec![index, images])
+}
+
+// Where a bundle keeps an entry's image; None when it had no file at export time
+// Bundles come from elsewhere: only a SHA-256 and a plain extension make it into the file name
+pub(crate) fn bundle_image(dir: &Path, entry: &ExportedEntry) -> Option<PathBuf> {
+    let hash = entry.content_hash.as_deref().filter(|hash| is_sha256(hash))?;
+    let extension = Path::new(&entry.path)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .filter(|extension| !extension.is_empty() && extension.chars().all(|c| c.is_ascii_alphanumeric()));
+    let file_name = match extension {
+        Some(extension) => format!("{}.{}", hash, extension),
+        None => hash.to_string(),
+    };
+    Some(dir.join(BUNDLE_IMAGES).join(file_name))
+}
+
+// 64 lowercase hex digits, as content_hash writes them
+fn is_sha256(value: &str) -> bool {
+    value.len() == 64 && value.chars().all(|c| matches!(c, '0'..='9' | 'a'..='f'))
+}
+
+// Entries and saved searches from a JSONL export
+pub(crate) fn read_jsonl(path: &Path) -> Result<(Vec<ExportedEntry>, Vec<SavedSearch>), String> {
+    let contents = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let mut entries = Vec::new();
+    let mut searches = Vec::new();
+    for (number, line) in contents.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record: Record = serde_json::from_str(line)
+            .map_err(|e| format!("{} line {}: {}", path.display(), number + 1, e))?;
+        match record {
+            Record::Header { format_version, .. } if format_version > FORMAT_VERSION => {
+                return Err(format!("{} is from a newer Chronicle (format {}) - update to import it", path.display(), format_version));
+            }
+            Record::Header { .. } => {}
+            Record::Entry(entry) => entries.push(entry),
+            Record::SavedSearch(search) => searches.push(search),
+        }
+    }
+    Ok((entries, searches))
+}
+
+pub(crate) fn write_csv(dest: &Path, entries: &[ExportedEntry], searches: &[SavedSearch]) -> Result<Vec<PathBuf>, String> {
+    let mut lines = vec![
+        "path,date,created_at,text,tags,custom_tags,urls,emails,pinned,sensitive,sensitive_kinds,entities,content_hash".to_string(),
+    ];
+    for entry in entries {
+        let entities: Vec<String> = entry.entities.iter().map(|entity| format!("{}:{}", entity.kind, entity.value)).collect();
+        lines.push([
+            csv_field(&entry.path),
+            csv_field(&format_millis(&entry.created_at).unwrap_or_default()),
+            csv_field(&entry.created_at),
+            csv_field(&entry.text),
+            csv_field(&entry.tags.join("; ")),
+            csv_field(&entry.custom_tags.join("; ")),
+            csv_field(&entry.urls.join("; ")),
+            csv_field(&entry.emails.join("; ")),
+            entry.pinned.to_string(),
+            entry.sensitive.to_string(),
+            csv_field(&entry.sensitive_kinds.join("; ")),
+            csv_field(&entities.join("; ")),
+            entry.content_hash.clone().unwrap_or_default(),
+        ].join(","));
+    }
+    write_file(dest, &(lines.join("\r\n") + "\r\n"))?;
+    let mut written = vec![dest.to_path_buf()];
+
+    if !searches.is_empty() {
+        let mut lines = vec!["name,query,collection_filter,created_at,updated_at".to_string()];
+        for search in searches {
+            lines.push([
+                csv_field(&search.name),
+                csv_field(&search.query),
+                csv_field(search.collection_filter.as_deref().unwrap_or("")),
+                csv_field(&search.created_at),
+                csv_field(&search.updated_at),
+            ].join(","));
+        }
+        let searches_path = dest.with_extension("saved-searches.csv");
+        write_file(&searches_path, &(lines.join("\r\n") + "\r\n"))?;
+        written.push(searches_path);
+    }
+    Ok(written)
+}
+
+// One note per entry, named after its image; existing notes with the same name are replaced
+pub(crate) fn write_markdown(dir: &Path, entries: &[ExportedEntry], searches: &[SavedSearch]) -> Result<Vec<PathBuf>, String> {
+    fs::create_dir_all(dir)
+        .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let mut used: HashSet<String> = HashSet::new();
+    let mut written = Vec::new();
+    for entry in entries {
+        let stem = Path::new(&entry.path)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "screenshot".to_string());
+        let mut name = note_name(&stem);
+        let mut counter = 2;
+        while !used.insert(name.to_lowercase()) {
+            name = format!("{}-{}", note_name(&stem), counter);
+            counter += 1;
+        }
+        let path = dir.join(format!("{}.md", name));
+        write_file(&path, &entry_note(entry))?;
+        written.push(path);
+    }
+
+    if !searches.is_empty() {
+        let mut note = String::from("# Saved Searches\n\n");
+        for search in searches {
+            note.push_str(&format!("- **{}**: `{}`", search.name, search.query));
+            if let Some(filter) = search.collection_filter.as_deref().filter(|filter| !filter.is_empty()) {
+                note.push_str(&format!(" in {}", filter));
+            }
+            note.push('\n');
+        }
+        let path = dir.join("Saved Searches.md");
+        write_file(&path, &note)?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+fn entry_note(entry: &ExportedEntry) -> String {
+    let file_name = Path::new(&entry.path)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| entry.path.clone());
+    let mut tags: Vec<String> = Vec::new();
+    for tag in entry.tags.iter().chain(&entry.custom_tags).map(|tag| yaml_string(&obsidian_tag(tag))) {
+        if !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+
+    let mut note = String::from("---\n");
+    note.push_str(&format!("path: {}\n", yaml_string(&entry.path)));
+    if let Some(date) = format_millis(&entry.created_at) {
+        note.push_str(&format!("created: {}\n", yaml_string(&date)));
+    }
+    note.push_str(&format!("tags: [{}]\n", tags.join(", ")));
+    note.push_str(&format!("pinned: {}\n", entry.pinned));
+    if entry.sensitive {
+        note.push_str("sensitive: true\n");
+    }
+    note.push_str("---\n\n");
+
+    note.push_str(&format!("![{}]({})\n\n", file_name.replace(['[', ']'], ""), file_url(&entry.path)));
+    if !entry.text.trim().is_empty() {
+        let fence = "`".repeat(longest_backtick_run(&entry.text).max(2) + 1);
+        note.push_str(&format!("{}text\n{}\n{}\n\n", fence, entry.text.trim_end(), fence));
+    }
+    let links: Vec<&String> = entry.urls.iter().chain(&entry.emails).collect();
+    if !links.is_empty() {
+        note.push_str("## Links\n\n");
+        for link in links {
+            note.push_str(&format!("- {}\n", link));
+        }
+        note.push('\n');
+    }
+    if !entry.entities.is_empty() {
+        note.push_str("## Entities\n\n");
+        for entity in &entry.entities {
+            note.push_str(&format!("- {}: {}\n", entity.kind, entity.value));
+        }
+    }
+    note
+}
+
+// Obsidian tags can't contain spaces or most punctuation
+fn obsidian_tag(tag: &str) -> String {
+    tag.chars()
+        .map(|c| if c.is_alphanumeric() || matches!(c, '_' | '-' | '/') { c } else { '-' })
+        .collect()
+}
+
+fn note_name(stem: &str) -> String {
+    let name: String = stem
+        .chars()
+        .map(|c| if matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' | '#' | '^' | '[' | ']') { '-' } else { c })
+        .collect();
+    if name.trim().is_empty() { "screenshot".to_string() } else { name }
+}
+
+fn yaml_string(value: &str) -> String {
+    serde_json::to_string(value).unwrap_or_else(|_| "\"\"".to_string())
+}
+
+// file:// link to the original image, escaped so Markdown keeps it in one piece
+fn file_url(path: &str) -> String {
+    let path = path.replace('\\', "/");
+    let prefix = if path.starts_with('/') { "file://" } else { "file:///" };
+    let mut url = String::from(prefix);
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'/' | b'-' | b'_' | b'.' | b'~' | b':' => url.push(byte as char),
+            _ => url.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    url
+}
+
+fn longest_backtick_run(text: &str) -> usize {
+    text.split(|c| c != '`').map(str::len).max().unwrap_or(0)
+}
+
+fn write_file(path: &Path, contents: &str) -> Result<(), String> {
+    fs::write(path, contents).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
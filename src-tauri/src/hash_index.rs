@@ -0,0 +1,395 @@
+// In-memory near-duplicate index over perceptual hashes
+// A BK-tree keyed by Hamming distance answers "everything within N bits" without
+// scanning the whole library; clustering runs on top of those neighbor queries
+
+use std::collections::{HashMap, HashSet};
+
+fn hamming(a: &[u8], b: &[u8]) -> u32 {
+    a.iter().zip(b.iter()).map(|(x, y)| (x ^ y).count_ones()).sum()
+}
+
+struct Node {
+    hash: Vec<u8>,
+    // Screenshots with exactly this hash (emptied nodes stay as routing nodes)
+    paths: Vec<String>,
+    children: HashMap<u32, usize>,
+}
+
+#[derive(Default)]
+pub(crate) struct BkTree {
+    nodes: Vec<Node>,
+    // path -> node holding it, for removal and re-insertion on re-save
+    locations: HashMap<String, usize>,
+}
+
+impl BkTree {
+    pub(crate) fn len(&self) -> usize {
+        self.locations.len()
+    }
+
+    pub(crate) fn insert(&mut self, path: &str, hash: Vec<u8>) {
+        self.remove(path);
+
+        if self.nodes.is_empty() {
+            self.push_node(path, hash);
+            return;
+        }
+
+        let mut current = 0;
+        loop {
+            let distance = hamming(&self.nodes[current].hash, &hash);
+            if distance == 0 && self.nodes[current].hash.len() == hash.len() {
+                self.nodes[current].paths.push(path.to_string());
+                self.locations.insert(path.to_string(), current);
+                return;
+            }
+            match self.nodes[current].children.get(&distance) {
+                Some(&child) => current = child,
+                None => {
+                    let index = self.push_node(path, hash);
+                    self.nodes[current].children.insert(distance, index);
+                    return;
+                }
+            }
+        }
+    }
+
+    pub(crate) fn remove(&mut self, path: &str) {
+        if let Some(index) = self.locations.remove(path) {
+            self.nodes[index].paths.retain(|p| p != path);
+        }
+    }
+
+    // Bring the tree in line with the hashes in the DB (other processes write it too)
+    // Returns how many paths were added, moved or dropped
+    pub(crate) fn sync(&mut self, hashes: &HashMap<String, Vec<u8>>) -> usize {
+        let stale: HashSet<String> = self.locations
+            .iter()
+            .filter(|(path, &index)| hashes.get(*path) != Some(&self.nodes[index].hash))
+            .map(|(path, _)| path.clone())
+            .collect();
+        for path in &stale {
+            self.remove(path);
+        }
+        let mut changed = stale.len();
+        for (path, hash) in hashes {
+            if !self.locations.contains_key(path) {
+                self.insert(path, hash.clone());
+                if !stale.contains(path) {
+                    changed += 1;
+                }
+            }
+        }
+        changed
+    }
+
+    // All indexed screenshots within `threshold` bits of `hash`, with their distance
+    pub(crate) fn find(&self, hash: &[u8], threshold: u32) -> Vec<(String, u32)> {
+        let mut results = Vec::new();
+        if self.nodes.is_empty() {
+            return results;
+        }
+
+        let mut stack = vec![0];
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            let distance = hamming(&node.hash, hash);
+            if distance <= threshold {
+                results.extend(node.paths.iter().map(|path| (path.clone(), distance)));
+            }
+            // Triangle inequality: only subtrees at distance d ± threshold can match
+            let low = distance.saturating_sub(threshold);
+            let high = distance + threshold;
+            stack.extend(
+                node.children
+                    .iter()
+                    .filter(|(edge, _)| **edge >= low && **edge <= high)
+                    .map(|(_, child)| *child),
+            );
+        }
+        results
+    }
+
+    fn push_node(&mut self, path: &str, hash: Vec<u8>) -> usize {
+        let index = self.nodes.len();
+        self.nodes.push(Node {
+            hash,
+            paths: vec![path.to_string()],
+            children: HashMap::new(),
+        });
+        self.locations.insert(path.to_string(), index);
+        index
+    }
+}
+
+// How neighbors are turned into groups
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum Linkage {
+    // Connected components: A~B and B~C puts A, B, C together
+    Single,
+    // Every pair inside a group is within the threshold
+    Complete,
+}
+
+impl Linkage {
+    pub(crate) fn parse(value: Option<&str>) -> Result<Self, String> {
+        match value.unwrap_or("single") {
+            "single" => Ok(Linkage::Single),
+            "complete" => Ok(Linkage::Complete),
+            other => Err(format!("Unknown linkage '{}' (expected 'single' or 'complete')", other)),
+        }
+    }
+}
+
+// Group near-duplicates; only groups with 2+ screenshots are returned
+// Result is independent of insertion order: groups and their members are sorted by path
+pub(crate) fn cluster(
+    tree: &BkTree,
+    hashes: &HashMap<String, Vec<u8>>,
+    threshold: u32,
+    linkage: Linkage,
+) -> Vec<Vec<String>> {
+    let mut paths: Vec<&String> = hashes.keys().collect();
+    paths.sort();
+    let position: HashMap<&str, usize> = paths.iter().enumerate().map(|(i, p)| (p.as_str(), i)).collect();
+
+    // Union-find over neighbor pairs from the tree
+    let mut parent: Vec<usize> = (0..paths.len()).collect();
+    fn root(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+    for (i, path) in paths.iter().enumerate() {
+        for (neighbor, _) in tree.find(&hashes[*path], threshold) {
+            if let Some(&j) = position.get(neighbor.as_str()) {
+                let (a, b) = (root(&mut parent, i), root(&mut parent, j));
+                if a != b {
+                    parent[a.max(b)] = a.min(b);
+                }
+            }
+        }
+    }
+
+    let mut components: HashMap<usize, Vec<usize>> = HashMap::new();
+    for i in 0..paths.len() {
+        let r = root(&mut parent, i);
+        components.entry(r).or_default().push(i);
+    }
+
+    let mut groups: Vec<Vec<String>> = components
+        .into_values()
+        .filter(|members| members.len() > 1)
+        .flat_map(|members| match linkage {
+            Linkage::Single => vec![members],
+            Linkage::Complete if members.len() > MAX_COMPLETE_LINK_MEMBERS => {
+                seeded_groups(tree, &members, &paths, &position, hashes, threshold)
+            }
+            Linkage::Complete => complete_link(&members, &paths, hashes, threshold),
+        })
+        .filter(|members| members.len() > 1)
+        .map(|members| members.into_iter().map(|i| paths[i].clone()).collect::<Vec<_>>())
+        .collect();
+
+    for group in groups.iter_mut() {
+        group.sort();
+    }
+    groups.sort();
+    groups
+}
+
+// Largest component clustered exactly: the pairwise distances take n² * 2 bytes (32 MB here)
+const MAX_COMPLETE_LINK_MEMBERS: usize = 4096;
+
+// Complete-link clustering inside one connected component: clusters merge while their farthest
+// members stay within threshold. Nearest-neighbor chain, O(n²)
+fn complete_link(
+    members: &[usize],
+    paths: &[&String],
+    hashes: &HashMap<String, Vec<u8>>,
+    threshold: u32,
+) -> Vec<Vec<usize>> {
+    let n = members.len();
+    let hash = |i: usize| &hashes[paths[members[i]]];
+    // distance[a * n + b]; rows of merged-away clusters go stale and are never read again
+    let mut distance = vec![0u16; n * n];
+    for a in 0..n {
+        for b in (a + 1)..n {
+            let d = hamming(hash(a), hash(b)) as u16;
+            distance[a * n + b] = d;
+            distance[b * n + a] = d;
+        }
+    }
+
+    let mut clusters: Vec<Vec<usize>> = (0..n).map(|i| vec![i]).collect();
+    let mut active = vec![true; n];
+    let mut finished: Vec<Vec<usize>> = Vec::new();
+    let mut chain: Vec<usize> = Vec::new();
+    while let Some(start) = chain.last().copied().or_else(|| active.iter().position(|&a| a)) {
+        if chain.is_empty() {
+            chain.push(start);
+        }
+        let a = start;
+        let previous = chain.len().checked_sub(2).map(|i| chain[i]);
+        // Nearest mergeable cluster; ties go to the previous link so the chain always ends
+        let mut nearest = previous.map(|p| (distance[a * n + p], p));
+        for b in (0..n).filter(|&b| active[b] && b != a) {
+            let d = distance[a * n + b];
+            if d as u32 <= threshold && nearest.is_none_or(|(best, _)| d < best) {
+                nearest = Some((d, b));
+            }
+        }
+        match nearest {
+            // Nothing within threshold, and merging only moves clusters apart
+            None => {
+                chain.pop();
+                active[a] = false;
+                finished.push(std::mem::take(&mut clusters[a]));
+            }
+            Some((_, b)) if Some(b) == previous => {
+                // Reciprocal nearest neighbors: merge b into a
+                chain.truncate(chain.len() - 2);
+                active[b] = false;
+                let merged = std::mem::take(&mut clusters[b]);
+                clusters[a].extend(merged);
+                for k in (0..n).filter(|&k| active[k] && k != a) {
+                    let d = distance[a * n + k].max(distance[b * n + k]);
+                    distance[a * n + k] = d;
+                    distance[k * n + a] = d;
+                }
+            }
+            Some((_, b)) => chain.push(b),
+        }
+    }
+
+    finished
+        .into_iter()
+        .map(|cluster| cluster.into_iter().map(|i| members[i]).collect())
+        .collect()
+}
+
+// Components too big for complete_link: members within threshold/2 of a seed (taken in path order)
+// form a group, so any two members of a group are still within threshold of each other
+fn seeded_groups(
+    tree: &BkTree,
+    members: &[usize],
+    paths: &[&String],
+    position: &HashMap<&str, usize>,
+    hashes: &HashMap<String, Vec<u8>>,
+    threshold: u32,
+) -> Vec<Vec<usize>> {
+    let mut unassigned: HashSet<usize> = members.iter().copied().collect();
+    let mut groups = Vec::new();
+    for &seed in members {
+        if !unassigned.remove(&seed) {
+            continue;
+        }
+        let mut group = vec![seed];
+        for (neighbor, _) in tree.find(&hashes[paths[seed]], threshold / 2) {
+            if let Some(&i) = position.get(neighbor.as_str()) {
+                if unassigned.remove(&i) {
+                    group.push(i);
+                }
+            }
+        }
+        groups.push(group);
+    }
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hashes(entries: &[(&str, u8)]) -> HashMap<String, Vec<u8>> {
+        entries.iter().map(|(path, hash)| (path.to_string(), vec![*hash])).collect()
+    }
+
+    fn tree(hashes: &HashMap<String, Vec<u8>>) -> BkTree {
+        let mut tree = BkTree::default();
+        tree.sync(hashes);
+        tree
+    }
+
+    #[test]
+    fn find_within_threshold() {
+        let hashes = hashes(&[("a", 0b0000_0000), ("b", 0b0000_0001), ("c", 0b0000_0111), ("d", 0b1111_1111)]);
+        let tree = tree(&hashes);
+        let mut found = tree.find(&[0], 1);
+        found.sort();
+        assert_eq!(found, [("a".to_string(), 0), ("b".to_string(), 1)]);
+        assert_eq!(tree.find(&[0], 8).len(), 4);
+        assert!(BkTree::default().find(&[0], 8).is_empty());
+    }
+
+    #[test]
+    fn identical_hashes_share_a_node() {
+        let mut tree = BkTree::default();
+        tree.insert("a", vec![5]);
+        tree.insert("b", vec![5]);
+        assert_eq!(tree.len(), 2);
+        tree.remove("a");
+        assert_eq!(tree.find(&[5], 0), [("b".to_string(), 0)]);
+        // Re-inserting moves a path instead of duplicating it
+        tree.insert("b", vec![6]);
+        assert_eq!(tree.len(), 1);
+        assert!(tree.find(&[5], 0).is_empty());
+    }
+
+    #[test]
+    fn sync_follows_the_database() {
+        let mut tree = tree(&hashes(&[("a", 0), ("b", 1), ("c", 2)]));
+        // b changed, c is gone, d is new
+        assert_eq!(tree.sync(&hashes(&[("a", 0), ("b", 0xF0), ("d", 3)])), 3);
+        assert_eq!(tree.len(), 3);
+        assert!(tree.find(&[2], 0).is_empty());
+        assert_eq!(tree.find(&[0xF0], 0), [("b".to_string(), 0)]);
+        assert_eq!(tree.sync(&hashes(&[("a", 0), ("b", 0xF0), ("d", 3)])), 0);
+    }
+
+    #[test]
+    fn single_linkage_chains() {
+        // a~b and b~c are within 1 bit, a and c are 2 apart
+        let hashes = hashes(&[("a", 0b000), ("b", 0b001), ("c", 0b011), ("far", 0b1111_0000)]);
+        let tree = tree(&hashes);
+        assert_eq!(cluster(&tree, &hashes, 1, Linkage::Single), [vec!["a", "b", "c"]]);
+    }
+
+    #[test]
+    fn complete_linkage_keeps_every_pair_close() {
+        let hashes = hashes(&[("a", 0b000), ("b", 0b001), ("c", 0b011), ("d", 0b111)]);
+        let tree = tree(&hashes);
+        let groups = cluster(&tree, &hashes, 1, Linkage::Complete);
+        assert_eq!(groups, [vec!["a", "b"], vec!["c", "d"]]);
+        for group in &groups {
+            for x in group {
+                for y in group {
+                    assert!(hamming(&hashes[x], &hashes[y]) <= 1);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn clusters_ignore_insertion_order() {
+        let hashes = hashes(&[("a", 0), ("b", 1), ("c", 3), ("d", 7), ("e", 15), ("f", 0x80)]);
+        let mut reversed = BkTree::default();
+        let mut paths: Vec<&String> = hashes.keys().collect();
+        paths.sort();
+        for path in paths.into_iter().rev() {
+            reversed.insert(path, hashes[path].clone());
+        }
+        for linkage in [Linkage::Single, Linkage::Complete] {
+            assert_eq!(cluster(&tree(&hashes), &hashes, 1, linkage), cluster(&reversed, &hashes, 1, linkage));
+        }
+    }
+
+    #[test]
+    fn linkage_names() {
+        assert_eq!(Linkage::parse(None), Ok(Linkage::Single));
+        assert_eq!(Linkage::parse(Some("complete")), Ok(Linkage::Complete));
+        assert!(Linkage::parse(Some("average")).is_err());
+    }
+}
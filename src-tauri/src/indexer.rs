@@ -0,0 +1,464 @@
+// The indexing pipeline without a webview: OCR, saving, tag refinement and the events they report
+// The app runs it with a TauriSink, which forwards events to the frontend; the CLI, tests or a
+// daemon can watch progress through a ChannelSink, or ignore it with NoopSink
+
+use crate::{
+    cache_visual_features, database_in_use, detect_collections, enrich_entry, exclusion_rules, exclusions, extract_urls_and_emails, get_file_created_at,
+    index_hash, open_index, refine_tags, remember_ignore, rename_with_text, ocr_unless_excluded, store_entry, store_tags,
+    text_unless_excluded, unindex_hash, wait_for_file, WordBox, DB_FILE,
+};
+use rusqlite::{Connection, Result as SqlResult};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OcrStatus {
+    pub status: String,
+    pub path: Option<String>,
+    pub error: Option<String>,
+    pub text: Option<String>,
+    pub created_at: Option<String>,
+    pub tags: Option<String>,
+    pub urls: Option<String>,
+    pub emails: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchProgress {
+    pub total: usize,
+    pub completed: usize,
+    pub percent: f64,
+    pub eta_seconds: u64,
+    pub in_progress: bool,
+}
+
+#[derive(Debug, Clone)]
+pub enum IndexEvent {
+    // A screenshot started ("processing") or finished ("idle") - "ocr-status" in the app
+    Status(OcrStatus),
+    // Progress through a batch of existing screenshots - "batch-progress"
+    BatchProgress(BatchProgress),
+    // Refined tags were stored after the slower tagging pass - "tags-updated"
+    TagsUpdated { path: String, tags: Vec<String> },
+    // An entry was written, with its perceptual hash (None if the image couldn't be hashed)
+    Saved { path: String, perceptual_hash: Option<Vec<u8>> },
+}
+
+pub trait EventSink: Send + Sync {
+    fn emit(&self, event: IndexEvent);
+}
+
+pub struct NoopSink;
+
+impl EventSink for NoopSink {
+    fn emit(&self, _event: IndexEvent) {}
+}
+
+// Events go down a channel; once the receiver is dropped they're discarded
+pub struct ChannelSink(pub mpsc::Sender<IndexEvent>);
+
+impl EventSink for ChannelSink {
+    fn emit(&self, event: IndexEvent) {
+        let _ = self.0.send(event);
+    }
+}
+
+// Forwards events to the frontend and keeps the app's near-duplicate index current
+pub(crate) struct TauriSink(pub(crate) AppHandle);
+
+impl EventSink for TauriSink {
+    fn emit(&self, event: IndexEvent) {
+        let app = &self.0;
+        let result = match event {
+            IndexEvent::Status(status) => app.emit("ocr-status", status),
+            IndexEvent::BatchProgress(progress) => app.emit("batch-progress", progress),
+            IndexEvent::TagsUpdated { path, tags } => app.emit("tags-updated", serde_json::json!({
+                "path": path,
+                "tags": tags
+            })),
+            IndexEvent::Saved { path, perceptual_hash } => {
+                match perceptual_hash {
+                    Some(hash) => index_hash(app, &path, &hash),
+                    None => unindex_hash(app, &path),
+                }
+                Ok(())
+            }
+        };
+        if let Err(error) = result {
+            eprintln!("Failed to emit event: {error}");
+        }
+    }
+}
+
+// Where the index lives, how to unlock it, and who hears about progress
+#[derive(Clone)]
+pub struct Indexer {
+    data_dir: PathBuf,
+    key: Option<String>,
+    sink: Arc<dyn EventSink>,
+    // Refine tags, embed and parse receipts on a separate thread so saving returns right away
+    background_enrichment: bool,
+}
+
+impl Indexer {
+    pub fn new(data_dir: PathBuf, key: Option<String>, sink: Arc<dyn EventSink>) -> Indexer {
+        Indexer { data_dir, key, sink, background_enrichment: false }
+    }
+
+    pub fn with_background_enrichment(mut self) -> Indexer {
+        self.background_enrichment = true;
+        self
+    }
+
+    pub(crate) fn data_dir(&self) -> &Path {
+        &self.data_dir
+    }
+
+    pub(crate) fn connect(&self) -> SqlResult<Connection> {
+        open_index(&self.data_dir.join(DB_FILE), self.key.clone())
+    }
+
+    pub(crate) fn emit(&self, event: IndexEvent) {
+        self.sink.emit(event);
+    }
+
+    fn status(&self, status: &str, path: Option<&Path>, error: Option<String>, text: Option<String>) {
+        let created_at = path.and_then(get_file_created_at);
+
+        // Extract tags, URLs, emails if text is available
+        let (tags, urls, emails) = if let Some(text_str) = &text {
+            let detected_tags = detect_collections(text_str);
+            let (extracted_urls, extracted_emails) = extract_urls_and_emails(text_str);
+            (
+                Some(serde_json::to_string(&detected_tags).unwrap_or_else(|_| "[]".to_string())),
+                Some(serde_json::to_string(&extracted_urls).unwrap_or_else(|_| "[]".to_string())),
+                Some(serde_json::to_string(&extracted_emails).unwrap_or_else(|_| "[]".to_string()))
+            )
+        } else {
+            (None, None, None)
+        };
+
+        self.emit(IndexEvent::Status(OcrStatus {
+            status: status.to_string(),
+            path: path.and_then(|value| value.to_str()).map(|value| value.to_string()),
+            error,
+            text,
+            created_at,
+            tags,
+            urls,
+            emails,
+        }));
+    }
+
+    fn batch_progress(&self, total: usize, completed: usize, elapsed: Duration) {
+        let (percent, eta_seconds) = if total == 0 {
+            (100.0, 0)
+        } else if completed == 0 {
+            (0.0, 0)
+        } else {
+            let average = elapsed.as_secs_f64() / completed as f64;
+            let remaining = total.saturating_sub(completed) as f64;
+            ((completed as f64 / total as f64) * 100.0, (average * remaining).round() as u64)
+        };
+        self.emit(IndexEvent::BatchProgress(BatchProgress {
+            total,
+            completed,
+            percent,
+            eta_seconds,
+            in_progress: completed < total,
+        }));
+    }
+
+    // Save with quick tags, then refine them (here or in the background)
+    // `words` are the word boxes of the OCR that produced `text`, kept for layout analysis
+    pub(crate) fn save_entry(&self, conn: &Connection, path: &str, text: &str, created_at: &str, words: Option<&[WordBox]>) -> SqlResult<()> {
+        let perceptual_hash = store_entry(conn, path, text, created_at)?;
+        if let Some(words) = words {
+            cache_visual_features(conn, path, words);
+        }
+        self.emit(IndexEvent::Saved { path: path.to_string(), perceptual_hash });
+
+        if !self.background_enrichment {
+            self.enrich_entry(conn, path, text);
+            return Ok(());
+        }
+        // Initial tags are already saved, so the entry never shows up without tags
+        let indexer = self.clone();
+        let path = path.to_string();
+        let text = text.to_string();
+        std::thread::spawn(move || {
+            // Small delay to ensure DB write is complete
+            std::thread::sleep(Duration::from_millis(100));
+            let _in_use = database_in_use();
+            match indexer.connect() {
+                Ok(conn) => indexer.enrich_entry(&conn, &path, &text),
+                Err(e) => eprintln!("[TAG-PIPELINE] Failed to init DB: {}", e),
+            }
+        });
+        Ok(())
+    }
+
+    pub(crate) fn enrich_entry(&self, conn: &Connection, path: &str, text: &str) {
+        let tags = enrich_entry(&self.data_dir, conn, path, text);
+        self.emit(IndexEvent::TagsUpdated { path: path.to_string(), tags });
+    }
+
+    // Run the tagging pipeline again for an indexed entry (visual classification first, then OCR text)
+    // Every entry ends up with at least one tag
+    pub(crate) fn process_tags(&self, path: &str, text: &str) {
+        let conn = match self.connect() {
+            Ok(c) => c,
+            Err(e) => {
+                eprintln!("[TAG-PIPELINE] Failed to init DB: {}", e);
+                return;
+            }
+        };
+        let tags = refine_tags(&self.data_dir, &conn, path, text);
+        match store_tags(&conn, path, &tags) {
+            Ok(_) => self.emit(IndexEvent::TagsUpdated { path: path.to_string(), tags }),
+            Err(e) => eprintln!("[TAG-PIPELINE] Failed to update tags for {}: {}", path, e),
+        }
+    }
+
+    // OCR one screenshot the rules allow and save it; false when a rule skipped it
+    pub(crate) fn index_file(&self, conn: &Connection, rules: &exclusions::Rules, path: &Path, created_at: &str) -> Result<bool, String> {
+        self.index_with_text(conn, rules, path, created_at, None)
+    }
+
+    // index_file with text from elsewhere (an import); OCR only runs when there's none
+    pub(crate) fn index_with_text(
+        &self,
+        conn: &Connection,
+        rules: &exclusions::Rules,
+        path: &Path,
+        created_at: &str,
+        text: Option<&str>,
+    ) -> Result<bool, String> {
+        let Some((text, words)) = text_unless_excluded(rules, path, text)? else {
+            return Ok(false);
+        };
+        let path = path.to_string_lossy();
+        self.save_entry(conn, &path, text.trim(), created_at, words.as_deref())
+            .map_err(|e| format!("Failed to save {}: {}", path, e))?;
+        Ok(true)
+    }
+
+    // A new screenshot from the watcher: OCR, rename from its text, save
+    pub(crate) fn process_screenshot(
+        &self,
+        path: PathBuf,
+        ignore_map: Arc<Mutex<HashMap<PathBuf, Instant>>>,
+        known_map: Arc<Mutex<HashSet<PathBuf>>>,
+    ) {
+        // Mark original path as known immediately to prevent duplicate processing
+        {
+            let mut guard = known_map.lock().unwrap();
+            guard.insert(path.clone());
+        }
+
+        self.status("processing", Some(&path), None, None);
+        let _in_use = database_in_use();
+
+        if !path.exists() {
+            self.status("idle", Some(&path), None, None);
+            return;
+        }
+
+        if let Err(error) = wait_for_file(&path) {
+            eprintln!("File not ready: {} ({error})", path.display());
+            self.status("idle", Some(&path), Some(error), None);
+            return;
+        }
+
+        let conn = match self.connect() {
+            Ok(conn) => conn,
+            Err(e) => {
+                eprintln!("[DB] ⚠️ Failed to open database: {}", e);
+                self.status("idle", Some(&path), Some(e.to_string()), None);
+                return;
+            }
+        };
+
+        let ocr_result = match ocr_unless_excluded(&exclusion_rules(&conn), &path) {
+            Ok(None) => {
+                self.status("idle", Some(&path), None, None);
+                return;
+            }
+            Ok(Some(ocr)) => Ok(ocr),
+            Err(error) => Err(error),
+        };
+
+        match ocr_result {
+            Ok((text, words)) => {
+                let trimmed = text.trim().to_string();
+
+                // Log detailed results
+                if trimmed.is_empty() {
+                    eprintln!("[OCR] ⚠️ WARNING: OCR returned EMPTY text for {}", path.display());
+                    eprintln!("[OCR] This could indicate:");
+                    eprintln!("[OCR]   1. Image has no readable text");
+                    eprintln!("[OCR]   2. OCR configuration needs adjustment");
+                    eprintln!("[OCR]   3. Image quality is too poor");
+                } else {
+                    let char_count = trimmed.len();
+                    let word_count = trimmed.split_whitespace().count();
+                    println!("[OCR] ✅ Successfully extracted {} characters, {} words from {}",
+                        char_count, word_count, path.display());
+
+                    if char_count < 100 {
+                        println!("[OCR] Full text: {}", trimmed);
+                    } else {
+                        println!("[OCR] Text preview: {}...", trimmed.chars().take(100).collect::<String>());
+                    }
+                }
+
+                // Get creation date from ORIGINAL path (before rename) - this is what we'll match against on startup
+                let created_at = get_file_created_at(&path)
+                    .unwrap_or_else(|| {
+                        SystemTime::now()
+                            .duration_since(UNIX_EPOCH)
+                            .unwrap()
+                            .as_millis()
+                            .to_string()
+                    });
+
+                let final_path = match rename_with_text(&conn, &path, &trimmed, created_at.parse().unwrap_or(0)) {
+                    Ok(None) => path.clone(),
+                    Ok(Some(new_path)) => {
+                        // Mark both original and renamed paths as known to prevent duplicate processing
+                        {
+                            let mut guard = known_map.lock().unwrap();
+                            guard.insert(path.clone()); // Original path
+                            guard.insert(new_path.clone()); // Renamed path
+                            println!("[RENAME] Marked both paths as known: {} -> {}", path.display(), new_path.display());
+                        }
+                        remember_ignore(&ignore_map, &new_path);
+                        remember_ignore(&ignore_map, &path); // Also ignore original path
+                        new_path
+                    }
+                    Err(error) => {
+                        eprintln!("Rename failed for {}: {error}", path.display());
+                        // Still mark original as known even if rename failed
+                        {
+                            let mut guard = known_map.lock().unwrap();
+                            guard.insert(path.clone());
+                        }
+                        path.clone()
+                    }
+                };
+                remember_ignore(&ignore_map, &path);
+
+                // Save to database with final_path (renamed path) but original creation date
+                if let Err(e) = self.save_entry(&conn, &final_path.to_string_lossy(), &trimmed, &created_at, words.as_deref()) {
+                    eprintln!("[DB] ⚠️ Failed to save entry to database: {}", e);
+                }
+
+                // Always emit the text, even if empty (so frontend knows OCR ran)
+                // Emit with final_path (renamed path if successful, original if not)
+                self.status("idle", Some(&final_path), None, Some(trimmed));
+            }
+            Err(error) => {
+                eprintln!("[OCR] ❌ OCR failed for {}: {error}", path.display());
+                eprintln!("[OCR] Error details: {}", error);
+
+                // Still save the entry to database even if OCR failed
+                // This allows the screenshot to appear in the UI, even without text
+                let created_at = get_file_created_at(&path)
+                    .unwrap_or_else(|| {
+                        SystemTime::now()
+                            .duration_since(UNIX_EPOCH)
+                            .unwrap()
+                            .as_secs()
+                            .to_string()
+                    });
+
+                // Save with empty text - user can still see the image
+                if let Err(e) = self.save_entry(&conn, &path.to_string_lossy(), "", &created_at, None) {
+                    eprintln!("[DB] ⚠️ Failed to save entry to database after OCR failure: {}", e);
+                } else {
+                    println!("[DB] ✅ Saved entry (no OCR text) to database: {}", path.display());
+                }
+
+                self.status("idle", Some(&path), Some(error), None);
+            }
+        }
+    }
+
+    // Screenshots that were already in the watch folders, with batch progress (no renaming)
+    pub fn process_batch(&self, paths: &[PathBuf]) {
+        let total = paths.len();
+        self.batch_progress(total, 0, Duration::ZERO);
+        if total == 0 {
+            return;
+        }
+
+        let _in_use = database_in_use();
+        let conn = match self.connect() {
+            Ok(conn) => conn,
+            Err(e) => {
+                eprintln!("[DB] ⚠️ Failed to open database: {}", e);
+                self.batch_progress(total, total, Duration::ZERO);
+                return;
+            }
+        };
+        let rules = exclusion_rules(&conn);
+        let mut completed = 0usize;
+        let mut total_elapsed = Duration::from_secs(0);
+
+        for path in paths {
+            let start = Instant::now();
+            self.status("processing", Some(path), None, None);
+            if let Err(error) = wait_for_file(path) {
+                eprintln!("File not ready: {} ({error})", path.display());
+                self.status("idle", Some(path), Some(error), None);
+            } else {
+                match ocr_unless_excluded(&rules, path) {
+                    Ok(None) => self.status("idle", Some(path), None, None),
+                    Ok(Some((text, words))) => {
+                        let trimmed = text.trim().to_string();
+
+                        // Log detailed results
+                        if trimmed.is_empty() {
+                            eprintln!("[OCR] ⚠️ WARNING: OCR returned EMPTY text for {}", path.display());
+                        } else {
+                            let char_count = trimmed.len();
+                            let word_count = trimmed.split_whitespace().count();
+                            println!("[OCR] ✅ Extracted {} chars, {} words from {}",
+                                char_count, word_count, path.display());
+                        }
+
+                        // Get creation date from original path (before any potential rename)
+                        let created_at = get_file_created_at(path)
+                            .unwrap_or_else(|| {
+                                SystemTime::now()
+                                    .duration_since(UNIX_EPOCH)
+                                    .unwrap()
+                                    .as_secs()
+                                    .to_string()
+                            });
+
+                        // Save to database (using original path since batches don't rename)
+                        if let Err(e) = self.save_entry(&conn, &path.to_string_lossy(), &trimmed, &created_at, words.as_deref()) {
+                            eprintln!("[DB] ⚠️ Failed to save entry to database: {}", e);
+                        }
+
+                        // Always emit the text, even if empty
+                        self.status("idle", Some(path), None, Some(trimmed));
+                    }
+                    Err(error) => {
+                        eprintln!("[OCR] ❌ OCR failed for {}: {error}", path.display());
+                        self.status("idle", Some(path), Some(error), None);
+                    }
+                }
+            }
+
+            completed += 1;
+            total_elapsed += start.elapsed();
+            self.batch_progress(total, completed, total_elapsed);
+        }
+    }
+}
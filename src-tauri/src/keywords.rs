@@ -0,0 +1,264 @@
+// Keyword extraction for screenshot file names
+// RAKE (Rapid Automatic Keyword Extraction) splits the text into candidate phrases at stopwords
+// and punctuation, then scores words by co-occurrence; each word is also weighted by its IDF
+// across the index so words every screenshot shares (app chrome, "today", ...) lose to distinctive ones
+
+use std::collections::{HashMap, HashSet};
+use unicode_segmentation::UnicodeSegmentation;
+
+// Longer runs without a stopword are usually OCR'd UI rows, not phrases
+const MAX_PHRASE_WORDS: usize = 4;
+
+// Document frequencies from the index, for IDF weighting
+pub(crate) struct Corpus<'a> {
+    pub(crate) documents: usize,
+    pub(crate) frequency: &'a dyn Fn(&str) -> usize,
+}
+
+impl Corpus<'_> {
+    fn idf(&self, word: &str) -> f32 {
+        let frequency = (self.frequency)(word).min(self.documents);
+        ((self.documents as f32 + 1.0) / (frequency as f32 + 1.0)).ln() + 1.0
+    }
+}
+
+// Up to `max_words` keywords, best phrases first
+pub(crate) fn extract_keywords(text: &str, corpus: &Corpus, max_words: usize) -> Vec<String> {
+    let stopwords = stopwords_for(text);
+    let phrases = candidate_phrases(text, &stopwords);
+
+    // RAKE word scores: degree (co-occurring words, itself included) over frequency
+    let mut frequency: HashMap<&str, f32> = HashMap::new();
+    let mut degree: HashMap<&str, f32> = HashMap::new();
+    for phrase in &phrases {
+        for word in phrase {
+            *frequency.entry(word.as_str()).or_default() += 1.0;
+            *degree.entry(word.as_str()).or_default() += phrase.len() as f32;
+        }
+    }
+    let word_scores: HashMap<&str, f32> = frequency
+        .iter()
+        .map(|(word, freq)| (*word, degree[word] / freq * corpus.idf(word)))
+        .collect();
+
+    let mut seen = HashSet::new();
+    let mut scored: Vec<(f32, usize, &Vec<String>)> = phrases
+        .iter()
+        .enumerate()
+        .filter(|(_, phrase)| seen.insert(phrase.join(" ")))
+        .map(|(position, phrase)| {
+            let score = phrase.iter().map(|word| word_scores[word.as_str()]).sum();
+            (score, position, phrase)
+        })
+        .collect();
+    // Ties go to the earlier phrase - titles and headings come first
+    scored.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal).then(a.1.cmp(&b.1)));
+
+    let mut keywords: Vec<String> = Vec::new();
+    for (_, _, phrase) in scored {
+        for word in phrase {
+            if keywords.len() >= max_words {
+                return keywords;
+            }
+            if !keywords.contains(word) {
+                keywords.push(word.clone());
+            }
+        }
+    }
+    keywords
+}
+
+// Runs of content words, broken at stopwords, punctuation, numbers and line ends
+fn candidate_phrases(text: &str, stopwords: &HashSet<&str>) -> Vec<Vec<String>> {
+    let mut phrases = Vec::new();
+    for line in text.lines() {
+        let mut current: Vec<String> = Vec::new();
+        let mut adjacent = false;
+        for segment in line.split_word_bounds() {
+            if segment.chars().all(char::is_whitespace) {
+                adjacent = false;
+                continue;
+            }
+            let word = segment.to_lowercase();
+            let follows = std::mem::replace(&mut adjacent, true);
+            if is_content_word(&word) && !stopwords.contains(word.as_str()) {
+                // CJK text has no spaces and segments into single characters - glue them back into runs
+                if let Some(last) = current.last_mut().filter(|last| follows && is_cjk(last) && is_cjk(&word)) {
+                    last.push_str(&word);
+                    continue;
+                }
+                current.push(word);
+                if current.len() < MAX_PHRASE_WORDS {
+                    continue;
+                }
+            }
+            if !current.is_empty() {
+                phrases.push(std::mem::take(&mut current));
+            }
+        }
+        if !current.is_empty() {
+            phrases.push(current);
+        }
+    }
+    phrases
+}
+
+fn is_content_word(word: &str) -> bool {
+    if !word.chars().any(char::is_alphabetic) {
+        return false;
+    }
+    if word.chars().any(|c| !c.is_alphanumeric() && c != '\'' && c != '’') {
+        return false;
+    }
+    // A single CJK character is a word; elsewhere one- and two-letter tokens are mostly OCR noise
+    word.chars().count() >= 3 || word.chars().any(is_ideographic)
+}
+
+fn is_cjk(word: &str) -> bool {
+    word.chars().all(is_ideographic)
+}
+
+fn is_ideographic(c: char) -> bool {
+    matches!(c as u32, 0x2E80..=0x9FFF | 0xAC00..=0xD7AF | 0xF900..=0xFAFF | 0x20000..=0x2FA1F)
+}
+
+// Stopwords of the detected language plus English, which most app chrome is in anyway
+fn stopwords_for(text: &str) -> HashSet<&'static str> {
+    let words: Vec<String> = text.unicode_words().map(|word| word.to_lowercase()).collect();
+    let detected = STOPWORDS
+        .iter()
+        .map(|(language, list)| {
+            let hits = words.iter().filter(|word| list.contains(&word.as_str())).count();
+            (hits, *language, *list)
+        })
+        .filter(|(hits, _, _)| *hits >= 2)
+        .max_by_key(|(hits, _, _)| *hits);
+
+    let mut stopwords: HashSet<&'static str> = ENGLISH.iter().copied().collect();
+    if let Some((_, language, list)) = detected {
+        if language != "en" {
+            stopwords.extend(list.iter().copied());
+        }
+    }
+    stopwords
+}
+
+const STOPWORDS: &[(&str, &[&str])] = &[
+    ("en", ENGLISH),
+    ("es", SPANISH),
+    ("fr", FRENCH),
+    ("de", GERMAN),
+    ("pt", PORTUGUESE),
+    ("it", ITALIAN),
+    ("nl", DUTCH),
+];
+
+const ENGLISH: &[&str] = &[
+    "a", "about", "above", "after", "again", "all", "also", "am", "an", "and", "any", "are", "as", "at",
+    "be", "because", "been", "before", "being", "below", "between", "both", "but", "by", "can", "could",
+    "did", "do", "does", "doing", "don't", "down", "during", "each", "few", "for", "from", "get", "got",
+    "had", "has", "have", "having", "he", "her", "here", "hers", "him", "his", "how", "i", "i'm", "if",
+    "in", "into", "is", "it", "it's", "its", "just", "like", "me", "more", "most", "my", "no", "nor",
+    "not", "now", "of", "off", "on", "once", "only", "or", "other", "our", "ours", "out", "over", "own",
+    "same", "she", "should", "so", "some", "such", "than", "that", "the", "their", "theirs", "them",
+    "then", "there", "these", "they", "this", "those", "through", "to", "too", "under", "until", "up",
+    "very", "was", "we", "were", "what", "when", "where", "which", "while", "who", "whom", "why", "will",
+    "with", "would", "you", "your", "yours", "yeah", "yes", "okay", "really",
+];
+
+const SPANISH: &[&str] = &[
+    "a", "al", "algo", "como", "con", "cuando", "de", "del", "desde", "donde", "el", "ella", "ellos",
+    "en", "entre", "era", "es", "esa", "ese", "eso", "esta", "este", "esto", "están", "fue", "ha", "hay",
+    "la", "las", "le", "les", "lo", "los", "me", "mi", "muy", "más", "nada", "ni", "no", "nos", "o",
+    "para", "pero", "por", "porque", "que", "qué", "se", "sea", "si", "sin", "sobre", "son", "su", "sus",
+    "también", "te", "tu", "tú", "un", "una", "uno", "y", "ya", "yo", "él",
+];
+
+const FRENCH: &[&str] = &[
+    "a", "au", "aussi", "aux", "avec", "c'est", "ce", "cela", "ces", "cette", "dans", "de", "des", "du",
+    "elle", "en", "est", "et", "être", "il", "ils", "je", "la", "le", "les", "leur", "lui", "ma", "mais",
+    "me", "mes", "mon", "ne", "nous", "on", "ou", "où", "par", "pas", "plus", "pour", "qu'il", "que",
+    "qui", "sa", "se", "ses", "son", "sont", "sur", "ta", "te", "tes", "toi", "ton", "tu", "un", "une",
+    "vos", "votre", "vous", "y", "été", "était", "chez", "très",
+];
+
+const GERMAN: &[&str] = &[
+    "aber", "als", "am", "an", "auch", "auf", "aus", "bei", "bin", "bis", "bist", "da", "das", "dass",
+    "dem", "den", "der", "des", "dich", "die", "dir", "doch", "du", "ein", "eine", "einem", "einen",
+    "einer", "es", "für", "hat", "hatte", "ich", "ihr", "im", "in", "ist", "ja", "kann", "mich", "mir",
+    "mit", "nach", "nicht", "noch", "nur", "oder", "schon", "sich", "sie", "sind", "so", "um", "und",
+    "uns", "von", "vor", "war", "was", "weil", "wenn", "wie", "wir", "wird", "zu", "zum", "zur", "über",
+];
+
+const PORTUGUESE: &[&str] = &[
+    "a", "ao", "aos", "as", "com", "como", "da", "das", "de", "do", "dos", "e", "ela", "ele", "eles",
+    "em", "entre", "era", "essa", "esse", "está", "eu", "foi", "isso", "isto", "já", "lhe", "mais", "mas",
+    "me", "meu", "minha", "muito", "na", "nas", "no", "nos", "não", "o", "os", "ou", "para", "pela",
+    "pelo", "por", "quando", "que", "se", "sem", "ser", "seu", "sua", "são", "também", "te", "tem",
+    "um", "uma", "você", "é",
+];
+
+const ITALIAN: &[&str] = &[
+    "a", "ai", "al", "alla", "anche", "che", "ci", "come", "con", "da", "dal", "dalla", "del", "della",
+    "dei", "di", "e", "gli", "ha", "hanno", "ho", "il", "in", "io", "la", "le", "lei", "lo", "lui", "ma",
+    "mi", "nel", "nella", "non", "per", "perché", "più", "questo", "quella", "quello", "se", "si",
+    "sono", "su", "sul", "ti", "tu", "un", "una", "uno", "è",
+];
+
+const DUTCH: &[&str] = &[
+    "aan", "al", "als", "bij", "dan", "dat", "de", "den", "der", "die", "dit", "door", "een", "en", "er",
+    "had", "heb", "hebben", "heeft", "hem", "het", "hij", "hoe", "ik", "in", "is", "je", "jij", "kan",
+    "maar", "me", "met", "mij", "mijn", "naar", "niet", "nog", "nu", "of", "om", "ons", "ook", "op",
+    "over", "te", "tot", "uit", "van", "voor", "was", "wat", "we", "wel", "wij", "zal", "ze", "zei",
+    "zich", "zij", "zijn", "zo",
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keywords(text: &str, max_words: usize) -> Vec<String> {
+        let corpus = Corpus { documents: 0, frequency: &|_| 0 };
+        extract_keywords(text, &corpus, max_words)
+    }
+
+    #[test]
+    fn phrases_break_at_stopwords_numbers_and_lines() {
+        assert_eq!(keywords("Your flight to Berlin is delayed", 5), ["flight", "berlin", "delayed"]);
+        assert_eq!(keywords("Order 12345 shipped", 5), ["order", "shipped"]);
+        assert_eq!(keywords("Boarding\npass", 5), ["boarding", "pass"]);
+    }
+
+    #[test]
+    fn longer_phrases_rank_first() {
+        assert_eq!(keywords("Read this: gate change notice", 3), ["gate", "change", "notice"]);
+        assert_eq!(keywords("flight flight gate change notice", 2), ["flight", "gate"]);
+    }
+
+    #[test]
+    fn common_words_lose_to_distinctive_ones() {
+        let frequency = |word: &str| if word == "inbox" { 100 } else { 1 };
+        let corpus = Corpus { documents: 100, frequency: &frequency };
+        assert_eq!(extract_keywords("Inbox\nReceipt", &corpus, 1), ["receipt"]);
+    }
+
+    #[test]
+    fn detected_language_stopwords() {
+        // pour, votre and avec split the phrases once the text reads as French
+        assert_eq!(
+            keywords("Réservation pour votre voyage avec Air Canada", 5),
+            ["air", "canada", "réservation", "voyage"]
+        );
+    }
+
+    #[test]
+    fn cjk_runs_are_kept() {
+        assert_eq!(keywords("東京駅 ok", 5), ["東京駅"]);
+    }
+
+    #[test]
+    fn noise_is_dropped() {
+        assert!(keywords("ab 12 -- ... x1", 5).is_empty());
+        assert!(keywords("", 5).is_empty());
+    }
+}
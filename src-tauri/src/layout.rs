@@ -0,0 +1,324 @@
+// Layout analysis for screenshots
+// Edge maps, text-line regions grouped from OCR word boxes, chat bubble shapes,
+// left/right message alignment and monospace column detection
+
+use crate::WordBox;
+use image::{imageops, GrayImage, RgbImage};
+use serde::{Deserialize, Serialize};
+
+// Edge analysis runs on a downscaled copy - layout survives, cost stays flat for Retina captures
+const EDGE_ANALYSIS_WIDTH: u32 = 480;
+// Sobel magnitude (|gx| + |gy|) above which a pixel counts as an edge
+const EDGE_THRESHOLD: i32 = 160;
+// A row/column whose longest edge run covers this share of the image is a rule (separator, table line)
+const HORIZONTAL_RULE_COVERAGE: f64 = 0.6;
+const VERTICAL_RULE_COVERAGE: f64 = 0.4;
+// Sparse-text OCR reports icons and texture as low-confidence "words"
+const MIN_WORD_CONFIDENCE: f32 = 30.0;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub(crate) struct LayoutFeatures {
+    // Edge map
+    pub(crate) edge_density: f64, // Share of pixels on strong edges
+    pub(crate) horizontal_edge_ratio: f64, // Horizontal vs vertical edge energy (0.5 = balanced)
+    pub(crate) horizontal_rules: usize, // Long horizontal lines (separators, table rows)
+    pub(crate) vertical_rules: usize, // Long vertical lines (sidebars, table columns)
+
+    // Text lines from OCR boxes
+    pub(crate) text_lines: usize,
+    pub(crate) text_coverage: f64, // Text-line area vs total area
+
+    // Messaging layout
+    pub(crate) left_aligned_lines: usize, // Lines hugging the left edge (incoming messages)
+    pub(crate) right_aligned_lines: usize, // Lines hugging the right edge (outgoing messages)
+    pub(crate) bubble_count: usize, // Lines sitting on a filled, uniformly colored shape
+
+    // Code layout
+    pub(crate) monospace_score: f64, // 0..1, how uniform character widths are
+    pub(crate) column_alignment: f64, // Share of lines starting on a character-grid column
+}
+
+// A connected run of OCR words on the same baseline
+#[derive(Debug, Clone)]
+struct TextLine {
+    left: u32,
+    top: u32,
+    right: u32,
+    bottom: u32,
+}
+
+impl TextLine {
+    fn height(&self) -> u32 {
+        self.bottom.saturating_sub(self.top)
+    }
+
+    fn area(&self) -> u64 {
+        self.right.saturating_sub(self.left) as u64 * self.height() as u64
+    }
+}
+
+pub(crate) fn analyze_layout(img: &RgbImage, words: &[WordBox]) -> LayoutFeatures {
+    let (width, height) = img.dimensions();
+    let mut features = LayoutFeatures::default();
+    if width < 3 || height < 3 {
+        return features;
+    }
+
+    let gray = imageops::grayscale(img);
+    let scaled = if width > EDGE_ANALYSIS_WIDTH {
+        let scaled_height = ((height as f64 * EDGE_ANALYSIS_WIDTH as f64 / width as f64).round() as u32).max(3);
+        imageops::resize(&gray, EDGE_ANALYSIS_WIDTH, scaled_height, imageops::FilterType::Triangle)
+    } else {
+        gray
+    };
+    apply_edge_stats(&scaled, &mut features);
+
+    let words: Vec<&WordBox> = words
+        .iter()
+        .filter(|word| word.confidence >= MIN_WORD_CONFIDENCE)
+        .collect();
+    let lines = group_text_lines(&words);
+    if lines.is_empty() {
+        return features;
+    }
+
+    let image_area = width as f64 * height as f64;
+    features.text_lines = lines.len();
+    features.text_coverage = (lines.iter().map(|l| l.area()).sum::<u64>() as f64 / image_area).min(1.0);
+
+    // Alignment relative to the image: chat apps put incoming bubbles on the left
+    // and outgoing ones on the right, neither spanning the full width
+    let left_band = width as f64 * 0.25;
+    let right_band = width as f64 * 0.75;
+    for line in &lines {
+        let (left, right) = (line.left as f64, line.right as f64);
+        if left < left_band && right < right_band {
+            features.left_aligned_lines += 1;
+        } else if right > right_band && left > left_band {
+            features.right_aligned_lines += 1;
+        }
+    }
+
+    let background = border_background(img);
+    features.bubble_count = lines
+        .iter()
+        .filter(|line| sits_in_bubble(img, line, background))
+        .count();
+
+    let (monospace_score, char_width) = monospace_stats(&words);
+    features.monospace_score = monospace_score;
+    features.column_alignment = column_alignment(&lines, char_width);
+
+    features
+}
+
+fn apply_edge_stats(gray: &GrayImage, features: &mut LayoutFeatures) {
+    let (w, h) = gray.dimensions();
+    let (w, h) = (w as usize, h as usize);
+    let raw = gray.as_raw();
+    let px = |x: usize, y: usize| raw[y * w + x] as i32;
+
+    let mut edges = vec![false; w * h];
+    let mut edge_count = 0usize;
+    let mut horizontal_energy = 0u64;
+    let mut vertical_energy = 0u64;
+
+    for y in 1..h - 1 {
+        for x in 1..w - 1 {
+            // Sobel kernels
+            let gx = (px(x + 1, y - 1) + 2 * px(x + 1, y) + px(x + 1, y + 1))
+                - (px(x - 1, y - 1) + 2 * px(x - 1, y) + px(x - 1, y + 1));
+            let gy = (px(x - 1, y + 1) + 2 * px(x, y + 1) + px(x + 1, y + 1))
+                - (px(x - 1, y - 1) + 2 * px(x, y - 1) + px(x + 1, y - 1));
+            if gx.abs() + gy.abs() >= EDGE_THRESHOLD {
+                edges[y * w + x] = true;
+                edge_count += 1;
+                // A strong vertical gradient means a horizontal edge
+                horizontal_energy += gy.unsigned_abs() as u64;
+                vertical_energy += gx.unsigned_abs() as u64;
+            }
+        }
+    }
+
+    features.edge_density = edge_count as f64 / (w * h) as f64;
+    let total_energy = horizontal_energy + vertical_energy;
+    features.horizontal_edge_ratio = if total_energy > 0 {
+        horizontal_energy as f64 / total_energy as f64
+    } else {
+        0.5
+    };
+
+    let row_is_rule: Vec<bool> = (0..h)
+        .map(|y| longest_run((0..w).map(|x| edges[y * w + x])) as f64 >= w as f64 * HORIZONTAL_RULE_COVERAGE)
+        .collect();
+    let col_is_rule: Vec<bool> = (0..w)
+        .map(|x| longest_run((0..h).map(|y| edges[y * w + x])) as f64 >= h as f64 * VERTICAL_RULE_COVERAGE)
+        .collect();
+
+    // Adjacent rule rows/columns are one line drawn a few pixels thick
+    features.horizontal_rules = count_bands(&row_is_rule);
+    features.vertical_rules = count_bands(&col_is_rule);
+}
+
+fn longest_run(values: impl Iterator<Item = bool>) -> usize {
+    let mut best = 0;
+    let mut current = 0;
+    for value in values {
+        if value {
+            current += 1;
+            best = best.max(current);
+        } else {
+            current = 0;
+        }
+    }
+    best
+}
+
+fn count_bands(flags: &[bool]) -> usize {
+    flags
+        .iter()
+        .enumerate()
+        .filter(|(i, flag)| **flag && (*i == 0 || !flags[i - 1]))
+        .count()
+}
+
+// Group OCR words into lines: words overlap vertically and sit close horizontally
+fn group_text_lines(words: &[&WordBox]) -> Vec<TextLine> {
+    let mut sorted: Vec<&WordBox> = words
+        .iter()
+        .copied()
+        .filter(|word| word.width > 0 && word.height > 0 && !word.text.trim().is_empty())
+        .collect();
+    sorted.sort_by_key(|word| (word.top, word.left));
+
+    let mut lines: Vec<TextLine> = Vec::new();
+    for word in sorted {
+        let (top, bottom) = (word.top, word.top + word.height);
+        let (left, right) = (word.left, word.left + word.width);
+        let center = top + word.height / 2;
+        // Allow a gap of about two character heights between words on one line
+        let max_gap = word.height * 2;
+
+        let existing = lines.iter_mut().find(|line| {
+            center >= line.top
+                && center <= line.bottom
+                && left <= line.right + max_gap
+                && right + max_gap >= line.left
+        });
+        match existing {
+            Some(line) => {
+                line.left = line.left.min(left);
+                line.right = line.right.max(right);
+                line.top = line.top.min(top);
+                line.bottom = line.bottom.max(bottom);
+            }
+            None => lines.push(TextLine { left, top, right, bottom }),
+        }
+    }
+    lines
+}
+
+// Most common (quantized) color along the image border - the page background
+fn border_background(img: &RgbImage) -> [u8; 3] {
+    let (w, h) = img.dimensions();
+    let mut counts: std::collections::HashMap<[u8; 3], usize> = std::collections::HashMap::new();
+    let step = ((w.max(h)) / 200).max(1);
+    let mut sample = |x: u32, y: u32| {
+        let p = img.get_pixel(x, y);
+        *counts.entry([p[0] / 16 * 16, p[1] / 16 * 16, p[2] / 16 * 16]).or_insert(0) += 1;
+    };
+    for x in (0..w).step_by(step as usize) {
+        sample(x, 0);
+        sample(x, h - 1);
+    }
+    for y in (0..h).step_by(step as usize) {
+        sample(0, y);
+        sample(w - 1, y);
+    }
+    counts
+        .into_iter()
+        .max_by_key(|(_, count)| *count)
+        .map(|(color, _)| color)
+        .unwrap_or([255, 255, 255])
+}
+
+fn color_distance(a: [u8; 3], b: [u8; 3]) -> u32 {
+    a.iter().zip(b.iter()).map(|(x, y)| (*x as i32 - *y as i32).unsigned_abs()).sum()
+}
+
+// A bubble is a uniformly filled shape around the text that differs from the page background
+// We sample a ring just outside the line's box: inside a bubble it's all fill color
+fn sits_in_bubble(img: &RgbImage, line: &TextLine, background: [u8; 3]) -> bool {
+    let (w, h) = img.dimensions();
+    let pad = (line.height() / 2).max(4);
+    let left = line.left.saturating_sub(pad);
+    let top = line.top.saturating_sub(pad);
+    let right = (line.right + pad).min(w - 1);
+    let bottom = (line.bottom + pad).min(h - 1);
+    if right <= left || bottom <= top {
+        return false;
+    }
+
+    let mut ring = Vec::new();
+    let step = ((right - left) / 40).max(1) as usize;
+    for x in (left..=right).step_by(step) {
+        ring.push(img.get_pixel(x, top).0);
+        ring.push(img.get_pixel(x, bottom).0);
+    }
+    let step = ((bottom - top) / 10).max(1) as usize;
+    for y in (top..=bottom).step_by(step) {
+        ring.push(img.get_pixel(left, y).0);
+        ring.push(img.get_pixel(right, y).0);
+    }
+    if ring.is_empty() {
+        return false;
+    }
+
+    let n = ring.len() as u32;
+    let mean = [0, 1, 2].map(|c| (ring.iter().map(|p| p[c] as u32).sum::<u32>() / n) as u8);
+    let uniform = ring.iter().filter(|p| color_distance(**p, mean) <= 36).count() as f64 / n as f64;
+
+    uniform >= 0.8 && color_distance(mean, background) > 45
+}
+
+// Monospace text has near-constant per-character width across words
+// Returns (score 0..1, median character width)
+fn monospace_stats(words: &[&WordBox]) -> (f64, f64) {
+    let mut widths: Vec<f64> = words
+        .iter()
+        .filter_map(|word| {
+            let chars = word.text.trim().chars().count();
+            (chars >= 3 && word.width > 0).then(|| word.width as f64 / chars as f64)
+        })
+        .collect();
+    if widths.len() < 5 {
+        return (0.0, 0.0);
+    }
+
+    widths.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    let median = widths[widths.len() / 2];
+    let mean = widths.iter().sum::<f64>() / widths.len() as f64;
+    let variance = widths.iter().map(|w| (w - mean).powi(2)).sum::<f64>() / widths.len() as f64;
+    let cv = if mean > 0.0 { variance.sqrt() / mean } else { 1.0 };
+
+    // OCR box jitter keeps even perfect monospace around cv 0.08;
+    // proportional fonts land well above 0.25
+    let score = (1.0 - (cv - 0.08) / 0.2).clamp(0.0, 1.0);
+    (score, median)
+}
+
+// Share of lines whose indentation is a whole number of characters (code/terminal columns)
+fn column_alignment(lines: &[TextLine], char_width: f64) -> f64 {
+    if lines.len() < 3 || char_width <= 0.0 {
+        return 0.0;
+    }
+    let margin = lines.iter().map(|l| l.left).min().unwrap_or(0) as f64;
+    let aligned = lines
+        .iter()
+        .filter(|line| {
+            let columns = (line.left as f64 - margin) / char_width;
+            (columns - columns.round()).abs() <= 0.25
+        })
+        .count();
+    aligned as f64 / lines.len() as f64
+}
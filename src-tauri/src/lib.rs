@@ -5,7 +5,7 @@ use std::{
     collections::{HashMap, HashSet},
     fs,
     path::{Path, PathBuf},
-    sync::{mpsc, Arc, Mutex},
+    sync::{mpsc, Arc, Mutex, RwLock, RwLockReadGuard},
     thread,
     time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
@@ -15,18 +15,26 @@ use tesseract::Tesseract;
 use image::{ImageBuffer, GenericImageView};
 use regex::Regex;
 use image_hasher::{HashAlg, HasherConfig};
+use indexer::{Indexer, TauriSink};
 
-#[derive(Clone, Serialize)]
-struct OcrStatus {
-    status: String,
-    path: Option<String>,
-    error: Option<String>,
-    text: Option<String>,
-    created_at: Option<String>,
-    tags: Option<String>,
-    urls: Option<String>,
-    emails: Option<String>,
-}
+mod api;
+pub mod cli;
+mod clip;
+mod embeddings;
+mod entities;
+mod exclusions;
+mod export;
+mod hash_index;
+pub mod indexer;
+mod keywords;
+mod layout;
+mod mcp;
+mod onnx;
+mod query;
+mod receipts;
+mod redact;
+mod rename;
+mod secrets;
 
 #[derive(Serialize)]
 struct DeleteResult {
@@ -34,21 +42,6 @@ struct DeleteResult {
     failed: Vec<String>,
 }
 
-#[derive(Clone, Serialize)]
-struct BatchProgress {
-    total: usize,
-    completed: usize,
-    percent: f64,
-    eta_seconds: u64,
-    in_progress: bool,
-}
-
-fn emit_batch_progress(app: &AppHandle, progress: BatchProgress) {
-    if let Err(error) = app.emit("batch-progress", progress) {
-        eprintln!("Failed to emit batch progress: {error}");
-    }
-}
-
 fn get_file_created_at(path: &Path) -> Option<String> {
     fs::metadata(path)
         .ok()
@@ -74,44 +67,6 @@ fn get_file_created_at(path: &Path) -> Option<String> {
         })
 }
 
-fn emit_status(
-    app: &AppHandle,
-    status: &str,
-    path: Option<&Path>,
-    error: Option<String>,
-    text: Option<String>,
-) {
-    let created_at = path.and_then(|p| get_file_created_at(p));
-    
-    // Extract tags, URLs, emails if text is available
-    let (tags, urls, emails) = if let Some(text_str) = &text {
-        let detected_tags = detect_collections(text_str);
-        let (extracted_urls, extracted_emails) = extract_urls_and_emails(text_str);
-        (
-            Some(serde_json::to_string(&detected_tags).unwrap_or_else(|_| "[]".to_string())),
-            Some(serde_json::to_string(&extracted_urls).unwrap_or_else(|_| "[]".to_string())),
-            Some(serde_json::to_string(&extracted_emails).unwrap_or_else(|_| "[]".to_string()))
-        )
-    } else {
-        (None, None, None)
-    };
-    
-    let payload = OcrStatus {
-        status: status.to_string(),
-        path: path.and_then(|value| value.to_str()).map(|value| value.to_string()),
-        error,
-        text,
-        created_at,
-        tags,
-        urls,
-        emails,
-    };
-
-    if let Err(error) = app.emit("ocr-status", payload) {
-        eprintln!("Failed to emit status: {error}");
-    }
-}
-
 fn resolve_watch_dirs() -> Vec<PathBuf> {
     let Ok(home) = std::env::var("HOME") else {
         eprintln!("HOME environment variable not set. File watcher disabled.");
@@ -215,7 +170,8 @@ fn preprocess_image(path: &Path) -> Result<PathBuf, String> {
     Ok(temp_path)
 }
 
-fn run_ocr_with_psm(path: &Path, psm_mode: &str, description: &str) -> Result<String, String> {
+// Text plus the word boxes of the same recognition pass
+fn run_ocr_with_psm(path: &Path, psm_mode: &str, description: &str) -> Result<(String, Vec<WordBox>), String> {
     let path_str = path
         .to_str()
         .ok_or_else(|| "Image path is not valid UTF-8".to_string())?;
@@ -308,11 +264,19 @@ fn run_ocr_with_psm(path: &Path, psm_mode: &str, description: &str) -> Result<St
     };
     
     // Run OCR
-    let result = tesseract
+    let mut tesseract = tesseract
         .set_image(path_str)
         .map_err(|error| format!("Failed to set image: {error}"))?
+        .recognize()
+        .map_err(|error| format!("OCR recognition failed: {error}"))?;
+    let result = tesseract
         .get_text()
         .map_err(|error| format!("OCR extraction failed: {error}"))?;
+    // Reads the recognition that just ran, so layout analysis doesn't need a pass of its own
+    let words = tesseract
+        .get_tsv_text(0)
+        .map(|tsv| parse_word_boxes(&tsv))
+        .unwrap_or_default();
     
     let cleaned = result
         .lines()
@@ -323,8 +287,69 @@ fn run_ocr_with_psm(path: &Path, psm_mode: &str, description: &str) -> Result<St
     
     let char_count = cleaned.len();
     println!("[OCR] PSM {} extracted {} characters", psm_mode, char_count);
-    
-    Ok(cleaned)
+
+    Ok((cleaned, words))
+}
+
+// A single recognized word and its bounding box in image pixels
+#[derive(Debug, Clone)]
+pub(crate) struct WordBox {
+    pub(crate) text: String,
+    pub(crate) left: u32,
+    pub(crate) top: u32,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    pub(crate) confidence: f32,
+}
+
+// Word-level OCR boxes from Tesseract's TSV output
+// PSM 11 (sparse text) finds scattered UI text like chat bubbles and sidebars
+fn ocr_word_boxes(path: &Path) -> Result<Vec<WordBox>, String> {
+    let path_str = path
+        .to_str()
+        .ok_or_else(|| "Image path is not valid UTF-8".to_string())?;
+
+    let mut tesseract = Tesseract::new(None, Some("eng"))
+        .map_err(|error| format!("Tesseract initialization failed: {error}"))?
+        .set_variable("tessedit_pageseg_mode", "11")
+        .map_err(|error| format!("Failed to set PSM: {error}"))?
+        .set_image(path_str)
+        .map_err(|error| format!("Failed to set image: {error}"))?
+        .recognize()
+        .map_err(|error| format!("OCR recognition failed: {error}"))?;
+
+    let tsv = tesseract
+        .get_tsv_text(0)
+        .map_err(|error| format!("Failed to read OCR boxes: {error}"))?;
+    let words = parse_word_boxes(&tsv);
+    println!("[OCR] Extracted {} word boxes", words.len());
+    Ok(words)
+}
+
+// Columns: level page block par line word left top width height conf text
+// Level 5 rows are words; conf is -1 for non-word rows
+fn parse_word_boxes(tsv: &str) -> Vec<WordBox> {
+    tsv.lines()
+        .filter_map(|line| {
+            let cols: Vec<&str> = line.split('\t').collect();
+            if cols.len() < 12 || cols[0] != "5" {
+                return None;
+            }
+            let text = cols[11].trim();
+            let confidence: f32 = cols[10].parse().ok()?;
+            if text.is_empty() || confidence < 0.0 {
+                return None;
+            }
+            Some(WordBox {
+                text: text.to_string(),
+                left: cols[6].parse().ok()?,
+                top: cols[7].parse().ok()?,
+                width: cols[8].parse().ok()?,
+                height: cols[9].parse().ok()?,
+                confidence,
+            })
+        })
+        .collect()
 }
 
 fn fix_ocr_character_mistakes(text: &str) -> String {
@@ -493,10 +518,6 @@ fn detect_collections(text: &str) -> Vec<String> {
                             "monday", "tuesday", "wednesday", "thursday", "friday", "saturday", "sunday"].iter()
                           .any(|date| text_lower.contains(date));
     
-    // Contact names or phone numbers (common in messages)
-    let phone_pattern = Regex::new(r"\+?\d{1,3}[-.\s]?\(?\d{3}\)?[-.\s]?\d{3}[-.\s]?\d{4}").unwrap();
-    let _has_phone = phone_pattern.is_match(text);
-    
     // Conversational indicators (back-and-forth patterns)
     let has_conversation_indicators = text.matches(":").count() > 2 && // Multiple colons suggest timestamps or names
                                     (text.matches("\n").count() > 1 || short_lines > 1);
@@ -693,10 +714,8 @@ fn detect_collections(text: &str) -> Vec<String> {
     tags
 }
 
-// Separate tagging pipeline - runs asynchronously after OCR
-// This allows for better detection, retries, and doesn't block OCR processing
-// GUARANTEES: Every entry will get at least one tag
-fn process_tags_for_entry(app: &AppHandle, path: &str, text: &str) {
+// The tags an entry should have, from its image and text
+fn refine_tags(data_dir: &Path, conn: &Connection, path: &str, text: &str) -> Vec<String> {
     println!("[TAG-PIPELINE] Processing tags for: {}", path);
     
     let text_len = text.trim().len();
@@ -705,12 +724,11 @@ fn process_tags_for_entry(app: &AppHandle, path: &str, text: &str) {
     // This is the most reliable indicator - no text = image/photo
     if text_len == 0 || text_len < 10 {
         println!("[TAG-PIPELINE] ✅ No/minimal text detected ({} chars) - tagging as Images", text_len);
-        update_entry_tags(app, path, &vec!["Images".to_string()]);
-        return;
+        return vec!["Images".to_string()];
     }
     
     // Try visual classification first (more accurate for screenshots)
-    let mut final_tags = classify_with_clip(path);
+    let mut final_tags = classify_with_clip(data_dir, conn, path);
     
     // If visual classification didn't find tags, try OCR-based detection
     if final_tags.is_empty() {
@@ -737,31 +755,40 @@ fn process_tags_for_entry(app: &AppHandle, path: &str, text: &str) {
         println!("[TAG-PIPELINE] ✅ Detected tags: {:?}", final_tags);
     }
     
-    // Always update tags - this ensures every entry has tags
-    update_entry_tags(app, path, &final_tags);
+    final_tags
 }
 
-// Visual feature-based classification for screenshots
-// Analyzes visual characteristics (colors, layout, text regions) to improve accuracy
-fn classify_with_clip(path: &str) -> Vec<String> {
-    use std::path::Path;
-    
-    let image_path = Path::new(path);
-    
-    // Load and analyze the image
-    let img = match image::open(image_path) {
-        Ok(img) => img,
-        Err(e) => {
-            eprintln!("[VISUAL-CLASSIFY] Failed to open image: {}", e);
-            return vec![];
+// Visual classification for screenshots
+// Confident zero-shot labels from the CLIP model (when one is installed), merged with tags from
+// visual characteristics (colors, layout, text regions)
+fn classify_with_clip(data_dir: &Path, conn: &Connection, path: &str) -> Vec<String> {
+    // Model-backed labels (cached per entry) come first when the model is confident
+    let mut clip_tags = Vec::new();
+    if let Some(scores) = clip::collection_scores(data_dir, conn, path) {
+        clip_tags = clip::confident_tags(&scores);
+        if !clip_tags.is_empty() {
+            println!("[VISUAL-CLASSIFY] ✅ CLIP classified as: {:?} (score: {:.2})", 
+                     clip_tags, scores[0].1);
+        } else {
+            println!("[VISUAL-CLASSIFY] CLIP not confident (top: {:?}), using visual features only", 
+                     scores.first());
         }
-    };
-    
-    let (width, height) = img.dimensions();
-    let rgb_img = img.to_rgb8();
+    }
     
-    // Analyze visual features
-    let features = analyze_visual_features(&rgb_img, width, height);
+    // Reuse the persisted feature vector (saved at indexing time from the main OCR pass)
+    let features = match load_visual_features(conn, path) {
+        Some(features) => features,
+        None => match compute_visual_features(path, None) {
+            Ok(features) => {
+                save_visual_features(conn, path, &features);
+                features
+            }
+            Err(e) => {
+                eprintln!("[VISUAL-CLASSIFY] {}", e);
+                return clip_tags;
+            }
+        },
+    };
     
     // Use visual features to classify
     let tags = classify_from_features(&features);
@@ -771,10 +798,75 @@ fn classify_with_clip(path: &str) -> Vec<String> {
                  tags, features.confidence);
     }
     
-    tags
+    // Merge the heuristic tags in after the CLIP labels
+    for tag in tags {
+        if !clip_tags.contains(&tag) {
+            clip_tags.push(tag);
+        }
+    }
+    clip_tags
+}
+
+// Colors and layout of a screenshot; `words` are its OCR word boxes, read here when not given
+fn compute_visual_features(path: &str, words: Option<&[WordBox]>) -> Result<VisualFeatures, String> {
+    let image_path = Path::new(path);
+    let img = image::open(image_path)
+        .map_err(|e| format!("Failed to open image: {}", e))?;
+    let (width, height) = img.dimensions();
+    let rgb_img = img.to_rgb8();
+    
+    // Word boxes drive the text-line analysis; without them we fall back to color heuristics
+    let read;
+    let words = match words {
+        Some(words) => words,
+        None => {
+            read = ocr_word_boxes(image_path).unwrap_or_else(|e| {
+                eprintln!("[LAYOUT] Word boxes unavailable for {}: {}", path, e);
+                Vec::new()
+            });
+            &read
+        }
+    };
+    Ok(analyze_visual_features(&rgb_img, width, height, words))
+}
+
+// Store the features of a just-indexed screenshot from the word boxes its OCR produced
+fn cache_visual_features(conn: &Connection, path: &str, words: &[WordBox]) {
+    match compute_visual_features(path, Some(words)) {
+        Ok(features) => save_visual_features(conn, path, &features),
+        Err(e) => eprintln!("[LAYOUT] {}: {}", path, e),
+    }
+}
+
+// Persisted feature vector for an entry (None if never computed or from an older format)
+fn load_visual_features(conn: &Connection, path: &str) -> Option<VisualFeatures> {
+    let json: Option<String> = conn
+        .query_row(
+            "SELECT visual_features FROM entries WHERE path = ?1",
+            rusqlite::params![path],
+            |row| row.get(0),
+        )
+        .ok()?;
+    serde_json::from_str(&json?).ok()
+}
+
+fn save_visual_features(conn: &Connection, path: &str, features: &VisualFeatures) {
+    let json = match serde_json::to_string(features) {
+        Ok(json) => json,
+        Err(e) => {
+            eprintln!("[LAYOUT] Failed to serialize features for {}: {}", path, e);
+            return;
+        }
+    };
+    if let Err(e) = conn.execute(
+        "UPDATE entries SET visual_features = ?1 WHERE path = ?2",
+        rusqlite::params![json, path],
+    ) {
+        eprintln!("[LAYOUT] Failed to persist features for {}: {}", path, e);
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, serde::Deserialize)]
 struct VisualFeatures {
     // Color analysis
     #[allow(dead_code)]
@@ -784,19 +876,23 @@ struct VisualFeatures {
     
     // Layout analysis
     text_density: f64, // Estimated text regions vs total area
-    has_grid_layout: bool, // Regular grid pattern (like messages)
-    has_linear_layout: bool, // Linear vertical layout (like code)
+    has_grid_layout: bool, // Table/grid of ruled lines (spreadsheets, settings panes)
+    has_linear_layout: bool, // Lines sharing a left margin (like code/terminals)
+    has_message_bubbles: bool, // Bubbles or alternating left/right lines (like chats)
     
     // Content analysis
     has_ui_elements: bool, // Buttons, icons, etc.
     has_code_blocks: bool, // Monospace-like regions
     has_images: bool, // Photo-like regions
     
+    // Raw layout measurements the flags above are derived from
+    layout: layout::LayoutFeatures,
+    
     // Overall confidence
     confidence: f64,
 }
 
-fn analyze_visual_features(img: &image::RgbImage, _width: u32, _height: u32) -> VisualFeatures {
+fn analyze_visual_features(img: &image::RgbImage, _width: u32, _height: u32, words: &[WordBox]) -> VisualFeatures {
     let pixels: Vec<_> = img.pixels().collect();
     let total_pixels = pixels.len();
     
@@ -841,15 +937,30 @@ fn analyze_visual_features(img: &image::RgbImage, _width: u32, _height: u32) ->
     let dark_ratio = dark_pixels as f64 / sample_size as f64;
     let is_dark_mode = dark_ratio > 0.6 || avg_brightness < 100;
     
-    // Simple layout detection (check for regular patterns)
-    // This is a simplified version - full implementation would use edge detection
-    let has_grid_layout = variance > 0.3 && color_counts.len() > 20;
-    let has_linear_layout = variance < 0.2 && color_counts.len() < 10;
+    // Layout analysis: edge map + text lines from the OCR word boxes
+    let layout = layout::analyze_layout(img, words);
+    let has_text_lines = layout.text_lines > 0;
+    
+    // Ruled grids: several long horizontal and vertical lines (tables, settings panes)
+    let has_grid_layout = layout.horizontal_rules >= 3 && layout.vertical_rules >= 2;
+    
+    // Chats: text sitting in bubbles, or lines alternating between the left and right edges
+    let has_message_bubbles = layout.bubble_count >= 2
+        || (layout.left_aligned_lines >= 2 && layout.right_aligned_lines >= 2);
+    
+    // Linear layout: most lines share a margin or character-grid indentation
+    let has_linear_layout = if has_text_lines {
+        layout.text_lines >= 3 && layout.column_alignment >= 0.6 && !has_message_bubbles
+    } else {
+        variance < 0.2 && color_counts.len() < 10
+    };
     
-    // Estimate text density based on color patterns
-    // High contrast regions (many distinct colors) suggest text
-    // Low contrast (few colors) suggests solid backgrounds or images
-    let text_density = if variance > 0.4 { 
+    // Text density from the measured text-line area when OCR found lines,
+    // otherwise estimate from color patterns
+    // (line boxes include inter-line gaps poorly, so scale coverage up to match the old range)
+    let text_density = if has_text_lines {
+        (layout.text_coverage * 1.5).min(1.0)
+    } else if variance > 0.4 { 
         0.7 // High variance = likely text/UI
     } else if variance > 0.25 { 
         0.5 // Medium variance = mixed content
@@ -859,23 +970,33 @@ fn analyze_visual_features(img: &image::RgbImage, _width: u32, _height: u32) ->
         0.1 // Very low variance = mostly solid/image
     };
     
-    // UI elements detection (many distinct color regions = UI components)
-    let has_ui_elements = color_counts.len() > 25 && variance > 0.25;
+    // UI elements detection (ruled separators or many distinct color regions = UI components)
+    let has_ui_elements = layout.horizontal_rules + layout.vertical_rules >= 2
+        || (color_counts.len() > 25 && variance > 0.25);
     
-    // Code blocks (monospace-like: low color variance, but structured)
-    // Code typically has consistent background with text
-    let has_code_blocks = variance < 0.2 && color_counts.len() < 15 && text_density > 0.3;
+    // Code blocks: monospace glyph widths on a character grid
+    // Without word boxes fall back to the flat-background color heuristic
+    let has_code_blocks = if has_text_lines {
+        layout.text_lines >= 3 && layout.monospace_score >= 0.6 && layout.column_alignment >= 0.5
+    } else {
+        variance < 0.2 && color_counts.len() < 15 && text_density > 0.3
+    };
     
     // Images/photos (high color variance, many colors, low text structure)
     let has_images = variance > 0.45 && color_counts.len() > 40 && text_density < 0.25;
     
     // Calculate confidence based on how clear the features are
     let confidence = if has_code_blocks { 0.8 }
-        else if has_grid_layout && text_density > 0.4 { 0.75 }
+        else if has_message_bubbles { 0.75 }
         else if has_images { 0.7 }
         else if has_ui_elements { 0.65 }
         else { 0.5 };
     
+    println!("[LAYOUT] lines: {}, coverage: {:.2}, bubbles: {}, left/right: {}/{}, monospace: {:.2}, columns: {:.2}, edges: {:.3}",
+             layout.text_lines, layout.text_coverage, layout.bubble_count,
+             layout.left_aligned_lines, layout.right_aligned_lines,
+             layout.monospace_score, layout.column_alignment, layout.edge_density);
+    
     VisualFeatures {
         dominant_colors,
         color_variance: variance,
@@ -883,9 +1004,11 @@ fn analyze_visual_features(img: &image::RgbImage, _width: u32, _height: u32) ->
         text_density,
         has_grid_layout,
         has_linear_layout,
+        has_message_bubbles,
         has_ui_elements,
         has_code_blocks,
         has_images,
+        layout,
         confidence,
     }
 }
@@ -905,9 +1028,8 @@ fn classify_from_features(features: &VisualFeatures) -> Vec<String> {
         return tags;
     }
     
-    // Messages detection (grid layout + high text density)
-    // Messages typically have many small text regions (bubbles)
-    if features.has_grid_layout && features.text_density > 0.5 && features.confidence >= 0.7 {
+    // Messages detection (bubbles / left-right alternating lines)
+    if features.has_message_bubbles && features.confidence >= 0.7 {
         tags.push("Messages".to_string());
         return tags;
     }
@@ -1013,15 +1135,7 @@ fn detect_collections_enhanced(text: &str) -> Vec<String> {
 }
 
 // Update tags for an entry in the database
-fn update_entry_tags(app: &AppHandle, path: &str, tags: &[String]) {
-    let conn = match init_database(app) {
-        Ok(c) => c,
-        Err(e) => {
-            eprintln!("[TAG-PIPELINE] Failed to init DB: {}", e);
-            return;
-        }
-    };
-    
+fn store_tags(conn: &Connection, path: &str, tags: &[String]) -> SqlResult<usize> {
     let tags_json = serde_json::to_string(tags).unwrap_or_else(|_| "[]".to_string());
     let now = SystemTime::now()
         .duration_since(UNIX_EPOCH)
@@ -1029,20 +1143,10 @@ fn update_entry_tags(app: &AppHandle, path: &str, tags: &[String]) {
         .as_secs();
     let now_str = now.to_string();
     
-    if let Err(e) = conn.execute(
+    conn.execute(
         "UPDATE entries SET tags = ?1, updated_at = ?2 WHERE path = ?3",
         rusqlite::params![tags_json, now_str, path]
-    ) {
-        eprintln!("[TAG-PIPELINE] Failed to update tags for {}: {}", path, e);
-    } else {
-        // Emit event to frontend that tags were updated
-        if let Err(e) = app.emit("tags-updated", serde_json::json!({
-            "path": path,
-            "tags": tags
-        })) {
-            eprintln!("[TAG-PIPELINE] Failed to emit tags-updated event: {}", e);
-        }
-    }
+    )
 }
 
 fn extract_urls_and_emails(text: &str) -> (Vec<String>, Vec<String>) {
@@ -1073,6 +1177,38 @@ fn compute_perceptual_hash(path: &Path) -> Result<Vec<u8>, String> {
     Ok(hash.as_bytes().to_vec())
 }
 
+// SHA-256 of the file's bytes: the same screenshot on another machine, whatever its path
+fn content_hash(path: &Path) -> Result<String, String> {
+    use sha2::{Digest, Sha256};
+    let bytes = fs::read(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    Ok(format!("{:x}", Sha256::digest(&bytes)))
+}
+
+// Additional hashes for "find screenshots like this" ranking: (pHash, block mean)
+// Each algorithm is sensitive to different edits, so combining them ranks variants more robustly
+fn compute_similarity_hashes(path: &Path) -> Result<(Vec<u8>, Vec<u8>), String> {
+    let img = image::open(path)
+        .map_err(|e| format!("Failed to open image: {}", e))?;
+    
+    // pHash: DCT low frequencies vs their median
+    let phash = HasherConfig::new()
+        .hash_alg(HashAlg::Median)
+        .hash_size(8, 8)
+        .preproc_dct()
+        .to_hasher()
+        .hash_image(&img);
+    
+    // Block mean (blockhash.io)
+    let block_hash = HasherConfig::new()
+        .hash_alg(HashAlg::Blockhash)
+        .hash_size(16, 16)
+        .to_hasher()
+        .hash_image(&img);
+    
+    Ok((phash.as_bytes().to_vec(), block_hash.as_bytes().to_vec()))
+}
+
 fn hamming_distance(hash1: &[u8], hash2: &[u8]) -> u32 {
     hash1.iter()
         .zip(hash2.iter())
@@ -1398,7 +1534,8 @@ fn check_word_preservation(original: &str, cleaned: &str, important_words: &[&st
     }
 }
 
-fn run_ocr(path: &Path) -> Result<String, String> {
+// Text from every engine, with Tesseract's word boxes for layout analysis
+fn run_ocr(path: &Path) -> Result<(String, Vec<WordBox>), String> {
     let path_str = path
         .to_str()
         .ok_or_else(|| "Image path is not valid UTF-8".to_string())?;
@@ -1423,6 +1560,7 @@ fn run_ocr(path: &Path) -> Result<String, String> {
     
     let mut vision_result: Option<String> = None;
     let mut tesseract_result: Option<String> = None;
+    let mut words = Vec::new();
     
     // TRY VISION FRAMEWORK (macOS only)
     #[cfg(target_os = "macos")]
@@ -1454,9 +1592,10 @@ fn run_ocr(path: &Path) -> Result<String, String> {
     }
     
     match run_ocr_with_modes(path, &tesseract_paths) {
-        Ok(text) if !text.trim().is_empty() => {
+        Ok((text, boxes)) if !text.trim().is_empty() => {
             println!("[OCR] ✅ Tesseract success: {} chars", text.len());
             tesseract_result = Some(text);
+            words = boxes;
         }
         Ok(_) => {
             println!("[OCR] ⚠️ Tesseract returned empty text");
@@ -1473,7 +1612,7 @@ fn run_ocr(path: &Path) -> Result<String, String> {
     // This allows screenshots without text to still be saved and displayed
     if raw_combined.is_empty() {
         println!("[OCR] ⚠️ Both OCR engines failed or returned empty results - saving with empty text");
-        return Ok(String::new());
+        return Ok((String::new(), words));
     }
     
     println!("[OCR] Raw combined text: {} chars", raw_combined.len());
@@ -1492,7 +1631,7 @@ fn run_ocr(path: &Path) -> Result<String, String> {
     // Safety check: if cleaning removed everything but we had content, use raw
     if cleaned.is_empty() && raw_combined.len() > 10 {
         println!("[OCR] ⚠️ Cleaning removed everything! Using raw text instead");
-        return Ok(raw_combined);
+        return Ok((raw_combined, words));
     }
     
     if cleaned.len() < 100 {
@@ -1502,10 +1641,10 @@ fn run_ocr(path: &Path) -> Result<String, String> {
     }
     
     println!("[OCR] ===== OCR Complete =====");
-    Ok(cleaned)
+    Ok((cleaned, words))
 }
 
-fn run_ocr_with_modes(original_path: &Path, image_paths: &[PathBuf]) -> Result<String, String> {
+fn run_ocr_with_modes(original_path: &Path, image_paths: &[PathBuf]) -> Result<(String, Vec<WordBox>), String> {
     // Try multiple PSM modes optimized for messaging apps and screenshots
     // PSM 4 is particularly good for chat/messaging apps (single column, vertical text flow)
     // PSM 11 is good for sparse text (like chat bubbles with gaps)
@@ -1521,6 +1660,7 @@ fn run_ocr_with_modes(original_path: &Path, image_paths: &[PathBuf]) -> Result<S
     
     let mut last_error = None;
     let mut best_result = String::new();
+    let mut best_words = Vec::new();
     let mut best_length = 0;
     let mut best_source = "";
     
@@ -1532,7 +1672,7 @@ fn run_ocr_with_modes(original_path: &Path, image_paths: &[PathBuf]) -> Result<S
         // Try each PSM mode
         for (psm, desc) in &psm_modes {
             match run_ocr_with_psm(image_path, psm, desc) {
-                Ok(text) => {
+                Ok((text, words)) => {
                     let text_len = text.trim().len();
                     println!("[OCR] {} image + PSM {} success: {} characters", image_type, psm, text_len);
                     
@@ -1554,6 +1694,7 @@ fn run_ocr_with_modes(original_path: &Path, image_paths: &[PathBuf]) -> Result<S
                     // Use the result with the most text
                     if text_len > best_length {
                         best_result = text;
+                        best_words = words;
                         best_length = text_len;
                         best_source = image_type;
                     }
@@ -1578,7 +1719,7 @@ fn run_ocr_with_modes(original_path: &Path, image_paths: &[PathBuf]) -> Result<S
                             println!("[OCR] Cleaned preview: {}", cleaned.chars().take(100).collect::<String>());
                         }
                         
-                        return Ok(cleaned);
+                        return Ok((cleaned, best_words));
                     }
                 }
                 Err(e) => {
@@ -1610,7 +1751,7 @@ fn run_ocr_with_modes(original_path: &Path, image_paths: &[PathBuf]) -> Result<S
                 cleaned_len, best_result.len() - cleaned_len);
         }
         
-        return Ok(cleaned);
+        return Ok((cleaned, best_words));
     }
     
     // If all failed, return detailed error
@@ -1623,55 +1764,39 @@ fn run_ocr_with_modes(original_path: &Path, image_paths: &[PathBuf]) -> Result<S
     Err(error_msg)
 }
 
-fn summarize_text(text: &str) -> String {
-    let mut best_line = "";
-    let mut best_score = 0usize;
-
-    for line in text.lines() {
-        let trimmed = line.trim();
-        if trimmed.is_empty() {
-            continue;
-        }
-        let score = trimmed.chars().filter(|ch| ch.is_ascii_alphanumeric()).count();
-        if score > best_score {
-            best_score = score;
-            best_line = trimmed;
-        }
-    }
-
-    let source = if best_line.is_empty() { text } else { best_line };
-    let mut words = Vec::new();
-    for word in source.split_whitespace() {
-        let cleaned = word
-            .trim_matches(|ch: char| !ch.is_ascii_alphanumeric())
-            .to_ascii_lowercase();
-        if cleaned.len() < 3 {
-            continue;
-        }
-        words.push(cleaned);
-        if words.len() >= 5 {
-            break;
-        }
-    }
-
-    words.join(" ")
+// A few keywords for the file name, weighted against the rest of the index
+fn summarize_text(conn: &Connection, text: &str) -> String {
+    let documents: i64 = conn
+        .query_row("SELECT COUNT(*) FROM entries_fts", [], |row| row.get(0))
+        .unwrap_or(0);
+    // Ask the full-text index itself so words are tokenized (and accents folded) the same way
+    let frequency = |word: &str| -> usize {
+        conn.prepare_cached("SELECT COUNT(*) FROM entries_fts WHERE entries_fts MATCH ?1")
+            .and_then(|mut stmt| stmt.query_row(rusqlite::params![format!("\"{}\"", word.replace('"', ""))], |row| row.get::<_, i64>(0)))
+            .map(|count| count as usize)
+            .unwrap_or(0)
+    };
+    let corpus = keywords::Corpus { documents: documents as usize, frequency: &frequency };
+    keywords::extract_keywords(text, &corpus, 5).join(" ")
 }
 
 fn slugify_text(text: &str) -> String {
     let mut out = String::new();
     let mut last_dash = false;
+    let mut length = 0;
 
     for ch in text.chars() {
-        if ch.is_ascii_alphanumeric() {
-            out.push(ch.to_ascii_lowercase());
+        if ch.is_alphanumeric() {
+            out.extend(ch.to_lowercase());
             last_dash = false;
-        } else if ch.is_ascii_whitespace() || ch == '-' || ch == '_' {
-            if !last_dash && !out.is_empty() {
-                out.push('-');
-                last_dash = true;
-            }
+            length += 1;
+        } else if (ch.is_whitespace() || ch == '-' || ch == '_') && !last_dash && !out.is_empty() {
+            out.push('-');
+            last_dash = true;
+            length += 1;
         }
-        if out.len() >= 60 {
+        // Characters, not bytes, so non-Latin names aren't cut short (or mid-character)
+        if length >= 60 {
             break;
         }
     }
@@ -1687,22 +1812,27 @@ fn slugify_text(text: &str) -> String {
     }
 }
 
-fn rename_with_text(path: &Path, text: &str) -> Result<PathBuf, String> {
-    let parent = path
-        .parent()
-        .ok_or_else(|| "Screenshot path missing parent directory".to_string())?;
-    let summary = summarize_text(text);
-    let slug = slugify_text(&summary);
-    let stamp = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .map_err(|error| format!("{error}"))?
-        .as_secs();
-    let filename = format!("{slug}-{stamp}.png");
-    let new_path = parent.join(filename);
+// Rename a new screenshot using the configured template
+// Ok(None) when renaming is turned off for its watch folder or the name wouldn't change
+fn rename_with_text(conn: &Connection, path: &Path, text: &str, created_at_ms: i64) -> Result<Option<PathBuf>, String> {
+    if !rename_enabled(conn, path) {
+        println!("[RENAME] Renaming disabled for {}", path.display());
+        return Ok(None);
+    }
+    
+    let template = rename_template(conn);
+    let tag = initial_tags(text).into_iter().next().unwrap_or_else(|| "Images".to_string());
+    let new_path = rename_target(conn, &template, path, text, &tag, created_at_ms, &HashSet::new())?;
+    if new_path == path {
+        return Ok(None);
+    }
 
     fs::rename(path, &new_path)
         .map_err(|error| format!("Failed to rename file: {error}"))?;
-    Ok(new_path)
+    if let Err(e) = record_file_operation(conn, path, &new_path, FILE_OP_RENAME) {
+        eprintln!("[FILEOPS] {}", e);
+    }
+    Ok(Some(new_path))
 }
 
 fn remember_ignore(ignore_map: &Arc<Mutex<HashMap<PathBuf, Instant>>>, path: &Path) {
@@ -1718,16 +1848,49 @@ fn is_ignored(ignore_map: &Arc<Mutex<HashMap<PathBuf, Instant>>>, path: &Path) -
 }
 
 // Database functions
-fn get_db_path(app: &AppHandle) -> PathBuf {
+const DB_FILE: &str = "chronicle.db";
+
+// chronicle.db, models/ and the trash all live in the app data dir; the CLI resolves the same one
+fn data_dir(app: &AppHandle) -> PathBuf {
     let app_data_dir = app.path().app_data_dir().expect("Failed to get app data directory");
     fs::create_dir_all(&app_data_dir).expect("Failed to create app data directory");
-    app_data_dir.join("chronicle.db")
+    app_data_dir
+}
+
+fn get_db_path(app: &AppHandle) -> PathBuf {
+    data_dir(app).join(DB_FILE)
 }
 
 fn init_database(app: &AppHandle) -> SqlResult<Connection> {
-    let db_path = get_db_path(app);
-    let conn = Connection::open(&db_path)?;
-    
+    open_index(&get_db_path(app), database_key(app))
+}
+
+// The indexing pipeline, reporting to the frontend
+fn indexer(app: &AppHandle) -> Indexer {
+    Indexer::new(data_dir(app), database_key(app), Arc::new(TauriSink(app.clone()))).with_background_enrichment()
+}
+
+// Bump when migrate() changes, so existing databases run it once more
+const SCHEMA_VERSION: i64 = 1;
+
+// Open (and migrate) the index without a running app
+// Migrations and backfills run on the first open after an upgrade, not on every connection
+fn open_index(db_path: &Path, key: Option<String>) -> SqlResult<Connection> {
+    let conn = open_database(db_path, key)?;
+    // Zero freed pages on every write, so text replaced by re-OCR, edits or redaction doesn't linger
+    conn.pragma_update(None, "secure_delete", true)?;
+    let version: i64 = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    if version < SCHEMA_VERSION {
+        migrate(&conn)?;
+        conn.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+        println!("[DB] Database initialized at: {} (schema {})", db_path.display(), SCHEMA_VERSION);
+    }
+    Ok(conn)
+}
+
+// Create and upgrade every table, then backfill what older versions didn't compute
+// Each step is idempotent, so two processes migrating at once is harmless
+fn migrate(conn: &Connection) -> SqlResult<()> {
     conn.execute(
         "CREATE TABLE IF NOT EXISTS entries (
             id INTEGER PRIMARY KEY AUTOINCREMENT,
@@ -1752,6 +1915,17 @@ fn init_database(app: &AppHandle) -> SqlResult<Connection> {
         ("perceptual_hash", "BLOB"),
         ("pinned", "INTEGER DEFAULT 0"),
         ("custom_tags", "TEXT"),
+        ("clip_scores", "TEXT"),
+        ("visual_features", "TEXT"),
+        ("clip_embedding", "BLOB"),
+        ("phash", "BLOB"),
+        ("block_hash", "BLOB"),
+        ("trashed", "INTEGER DEFAULT 0"),
+        ("trashed_at", "TEXT"),
+        ("trash_path", "TEXT"),
+        ("sensitive", "INTEGER DEFAULT 0"),
+        ("sensitive_kinds", "TEXT"),
+        ("content_hash", "TEXT"),
     ];
     
     for (col_name, col_type) in columns_to_add {
@@ -1796,6 +1970,12 @@ fn init_database(app: &AppHandle) -> SqlResult<Connection> {
         [],
     )?;
     
+    // Imports merge on the file's content hash
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_content_hash ON entries(content_hash)",
+        [],
+    )?;
+    
     // Create saved_searches table for Smart Albums feature
     conn.execute(
         "CREATE TABLE IF NOT EXISTS saved_searches (
@@ -1808,15 +1988,197 @@ fn init_database(app: &AppHandle) -> SqlResult<Connection> {
         )",
         [],
     )?;
-    
+
+    // Retention rules evaluated by the maintenance scheduler
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS retention_rules (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            query TEXT NOT NULL,
+            action TEXT NOT NULL,
+            enabled INTEGER NOT NULL DEFAULT 1,
+            previewed_at TEXT,
+            approved_at TEXT,
+            last_run_at TEXT,
+            created_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    // Key/value app settings (JSON values)
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS settings (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    // Journal of renames and moves, so they can be undone
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS file_operations (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            old_path TEXT NOT NULL,
+            new_path TEXT NOT NULL,
+            reason TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            undone_at TEXT
+        )",
+        [],
+    )?;
+
+    // Full-text index over OCR text (BM25 ranking for keyword + hybrid search)
+    init_fts(conn)?;
+
+    // Structured entities (phones, money, IBANs, ...) found in OCR text
+    init_entities(conn)?;
+
+    // Secrets (OTP codes, API keys, ...) in entries indexed before detection existed
+    scan_unscanned_secrets(conn)?;
+
+    // Parsed receipts for entries tagged Receipts (items as JSON)
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS receipts (
+            path TEXT PRIMARY KEY,
+            merchant TEXT,
+            date TEXT,
+            currency TEXT,
+            subtotal REAL,
+            tax REAL,
+            total REAL,
+            items TEXT NOT NULL DEFAULT '[]',
+            parsed_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    // Text embeddings for semantic search, one row per entry and model
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS embeddings (
+            path TEXT PRIMARY KEY,
+            model TEXT NOT NULL,
+            vector BLOB NOT NULL,
+            created_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+
     // CRITICAL: Fix any entries without tags (should never happen, but safety check)
-    fix_entries_without_tags(&conn);
+    fix_entries_without_tags(conn);
     
     // Convert all "Documents" tags to "Images" (all screenshots are images)
-    convert_documents_to_images(&conn);
-    
-    println!("[DB] Database initialized at: {}", db_path.display());
-    Ok(conn)
+    convert_documents_to_images(conn);
+    Ok(())
+}
+
+// FTS5 table mirroring entries.text, kept in sync by triggers
+// Its rowids are the entries' ids, so triggers find the row to drop without scanning the table
+// (entries are upserted, never INSERT OR REPLACEd, so every change goes through a trigger)
+// Not an external-content table: the bundled SQLite 3.45 corrupts those under secure-delete
+fn init_fts(conn: &Connection) -> SqlResult<()> {
+    let existed: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'entries_fts'",
+        [],
+        |row| row.get(0),
+    )?;
+    // Older indexes matched rows on the unindexed path column, a full scan on every change
+    let outdated: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'trigger' AND name = 'entries_fts_delete' AND sql LIKE '%WHERE path = old.path%'",
+        [],
+        |row| row.get(0),
+    )?;
+    if outdated > 0 {
+        conn.execute_batch(
+            "DROP TRIGGER IF EXISTS entries_fts_insert;
+            DROP TRIGGER IF EXISTS entries_fts_update;
+            DROP TRIGGER IF EXISTS entries_fts_delete;
+            DROP TABLE IF EXISTS entries_fts;",
+        )?;
+    }
+
+    conn.execute_batch(
+        "CREATE VIRTUAL TABLE IF NOT EXISTS entries_fts USING fts5(
+            path UNINDEXED,
+            text,
+            tokenize = 'unicode61 remove_diacritics 2'
+        );
+        CREATE TRIGGER IF NOT EXISTS entries_fts_insert AFTER INSERT ON entries BEGIN
+            INSERT INTO entries_fts (rowid, path, text) VALUES (new.id, new.path, new.text);
+        END;
+        CREATE TRIGGER IF NOT EXISTS entries_fts_update AFTER UPDATE OF path, text ON entries BEGIN
+            DELETE FROM entries_fts WHERE rowid = old.id;
+            INSERT INTO entries_fts (rowid, path, text) VALUES (new.id, new.path, new.text);
+        END;
+        CREATE TRIGGER IF NOT EXISTS entries_fts_delete AFTER DELETE ON entries BEGIN
+            DELETE FROM entries_fts WHERE rowid = old.id;
+        END;",
+    )?;
+
+    // Backfill existing entries the first time the index is created (or rebuilt)
+    if existed == 0 || outdated > 0 {
+        let indexed = conn.execute("INSERT INTO entries_fts (rowid, path, text) SELECT id, path, text FROM entries", [])?;
+        println!("[DB] Built full-text index for {} entries", indexed);
+    }
+
+    // Scrub deleted text out of the index segments instead of only marking it deleted
+    let secure_delete: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM entries_fts_config WHERE k = 'secure-delete' AND v = 1",
+        [],
+        |row| row.get(0),
+    )?;
+    if secure_delete == 0 {
+        conn.execute("INSERT INTO entries_fts (entries_fts, rank) VALUES ('secure-delete', 1)", [])?;
+    }
+    Ok(())
+}
+
+fn init_entities(conn: &Connection) -> SqlResult<()> {
+    let existed: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'entities'",
+        [],
+        |row| row.get(0),
+    )?;
+
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS entities (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            path TEXT NOT NULL,
+            kind TEXT NOT NULL,
+            value TEXT NOT NULL,
+            span_start INTEGER NOT NULL,
+            span_end INTEGER NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS entities_path ON entities (path);
+        CREATE INDEX IF NOT EXISTS entities_kind ON entities (kind, value);",
+    )?;
+
+    // Extract from existing entries the first time the table is created
+    if existed == 0 {
+        let rows: Vec<(String, String)> = {
+            let mut stmt = conn.prepare("SELECT path, text FROM entries")?;
+            let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            rows.collect::<SqlResult<Vec<_>>>()?
+        };
+        let mut found = 0;
+        for (path, text) in &rows {
+            found += save_entities(conn, path, text)?;
+        }
+        println!("[DB] Extracted {} entities from {} entries", found, rows.len());
+    }
+    Ok(())
+}
+
+// Replace an entry's entities with those found in its text
+fn save_entities(conn: &Connection, path: &str, text: &str) -> SqlResult<usize> {
+    conn.execute("DELETE FROM entities WHERE path = ?1", rusqlite::params![path])?;
+    let found = entities::extract_entities(text);
+    let mut stmt = conn.prepare_cached(
+        "INSERT INTO entities (path, kind, value, span_start, span_end) VALUES (?1, ?2, ?3, ?4, ?5)"
+    )?;
+    for entity in &found {
+        stmt.execute(rusqlite::params![path, entity.kind, entity.value, entity.start as i64, entity.end as i64])?;
+    }
+    Ok(found.len())
 }
 
 // Fix entries without tags - ensures database integrity
@@ -1934,18 +2296,10 @@ fn convert_documents_to_images(conn: &Connection) {
     }
 }
 
-fn save_entry_to_db(app: &AppHandle, path: &str, text: &str, created_at: &str) -> SqlResult<()> {
-    let conn = init_database(app)?;
-    let now = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_secs();
-    let now_str = now.to_string();
-    
-    // Assign tags IMMEDIATELY (synchronously) to ensure every entry has tags
-    // This prevents entries from appearing without tags in the UI
+// Quick text-only tags, refined asynchronously after saving
+fn initial_tags(text: &str) -> Vec<String> {
     let text_len = text.trim().len();
-    let initial_tags = if text_len == 0 || text_len < 10 {
+    let tags = if text_len == 0 || text_len < 10 {
         vec!["Images".to_string()] // No/minimal text = Images
     } else {
         // Try quick detection, but don't block - will refine asynchronously
@@ -1953,42 +2307,91 @@ fn save_entry_to_db(app: &AppHandle, path: &str, text: &str, created_at: &str) -
     };
     
     // If quick detection failed, assign fallback - always use "Images"
-    let tags_to_save = if initial_tags.is_empty() {
+    if tags.is_empty() {
         vec!["Images".to_string()]
     } else {
-        initial_tags
-    };
-    
-    let tags_json = serde_json::to_string(&tags_to_save).unwrap_or_else(|_| "[]".to_string());
-    
-    // Extract URLs and emails
+        tags
+    }
+}
+
+// Delete the trashed copy of the entry at `path`, if it's in the trash
+fn purge_replaced_trash(conn: &Connection, path: &str) {
+    let trash_path: Option<String> = conn.query_row(
+        "SELECT trash_path FROM entries WHERE path = ?1 AND trashed = 1",
+        rusqlite::params![path],
+        |row| row.get(0),
+    ).ok().flatten();
+    let Some(trash_path) = trash_path else {
+        return;
+    };
+    let removed = if is_sensitive_entry(conn, path) {
+        shred_file(Path::new(&trash_path))
+    } else {
+        fs::remove_file(&trash_path)
+    };
+    match removed {
+        Ok(_) => println!("[TRASH] Purged {} - {} was replaced by a new file", trash_path, path),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => eprintln!("[TRASH] Failed to purge {}: {}", trash_path, e),
+    }
+}
+
+// Save an entry with its quick text-only tags, URLs, hashes, secrets and entities
+// Returns the perceptual hash for the near-duplicate index
+fn store_entry(conn: &Connection, path: &str, text: &str, created_at: &str) -> SqlResult<Option<Vec<u8>>> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let now_str = now.to_string();
+    
+    // Assign tags IMMEDIATELY (synchronously) to ensure every entry has tags
+    // This prevents entries from appearing without tags in the UI
+    let tags_to_save = initial_tags(text);
+    
+    let tags_json = serde_json::to_string(&tags_to_save).unwrap_or_else(|_| "[]".to_string());
+    
+    // Extract URLs and emails
     let (urls, emails) = extract_urls_and_emails(text);
     let urls_json = serde_json::to_string(&urls).unwrap_or_else(|_| "[]".to_string());
     let emails_json = serde_json::to_string(&emails).unwrap_or_else(|_| "[]".to_string());
     
     // Compute perceptual hash for similarity detection
     let perceptual_hash = compute_perceptual_hash(Path::new(path)).ok();
+    let (phash, block_hash) = match compute_similarity_hashes(Path::new(path)) {
+        Ok((phash, block_hash)) => (Some(phash), Some(block_hash)),
+        Err(_) => (None, None),
+    };
+    let file_hash = content_hash(Path::new(path)).ok();
+    
+    // One-time codes, tokens, passwords, recovery phrases
+    let sensitive_kinds = secret_kinds(text);
+    let sensitive_json = serde_json::to_string(&sensitive_kinds).unwrap_or_else(|_| "[]".to_string());
     
+    // A new file at a trashed entry's path: the entry comes back for it, so its trashed copy is purged
+    // (restoring it would overwrite the new file)
+    purge_replaced_trash(conn, path);
+    
+    // Upsert so re-indexing a path keeps its pin and custom tags
+    // The image caches describe the old file, so they only survive when the content is unchanged
     conn.execute(
-        "INSERT OR REPLACE INTO entries (path, text, created_at, processed_at, updated_at, tags, urls, emails, perceptual_hash)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
-        rusqlite::params![path, text, created_at, now_str, now_str, tags_json, urls_json, emails_json, perceptual_hash],
+        "INSERT INTO entries (path, text, created_at, processed_at, updated_at, tags, urls, emails, perceptual_hash, phash, block_hash, sensitive, sensitive_kinds, content_hash)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)
+         ON CONFLICT(path) DO UPDATE SET
+            text = excluded.text, created_at = excluded.created_at, processed_at = excluded.processed_at,
+            updated_at = excluded.updated_at, tags = excluded.tags, urls = excluded.urls, emails = excluded.emails,
+            perceptual_hash = excluded.perceptual_hash, phash = excluded.phash, block_hash = excluded.block_hash,
+            sensitive = excluded.sensitive, sensitive_kinds = excluded.sensitive_kinds, content_hash = excluded.content_hash,
+            trashed = 0, trashed_at = NULL, trash_path = NULL,
+            visual_features = CASE WHEN content_hash IS excluded.content_hash THEN visual_features END,
+            clip_scores = CASE WHEN content_hash IS excluded.content_hash THEN clip_scores END,
+            clip_embedding = CASE WHEN content_hash IS excluded.content_hash THEN clip_embedding END",
+        rusqlite::params![path, text, created_at, now_str, now_str, tags_json, urls_json, emails_json, perceptual_hash, phash, block_hash, !sensitive_kinds.is_empty(), sensitive_json, file_hash],
     )?;
     
-    // Refine tags asynchronously (visual classification, enhanced detection)
-    // Initial tags are already saved above, so entry won't appear without tags
-    let app_for_tagging = app.clone();
-    let path_for_tagging = path.to_string();
-    let text_for_tagging = text.to_string();
-    tauri::async_runtime::spawn_blocking(move || {
-        // Small delay to ensure DB write is complete
-        std::thread::sleep(std::time::Duration::from_millis(100));
-        // Refine tags with visual classification and enhanced detection
-        // This will update tags if better classification is found
-        process_tags_for_entry(&app_for_tagging, &path_for_tagging, &text_for_tagging);
-    });
+    let entity_count = save_entities(conn, path, text)?;
     
-    println!("[DB] ✅ Saved entry: {} ({} chars) - Tags will be processed asynchronously", path, text.len());
+    println!("[DB] ✅ Saved entry: {} ({} chars)", path, text.len());
     
     if !urls.is_empty() {
         println!("[DB]   Found {} URLs: {:?}", urls.len(), urls);
@@ -1996,8 +2399,34 @@ fn save_entry_to_db(app: &AppHandle, path: &str, text: &str, created_at: &str) -
     if !emails.is_empty() {
         println!("[DB]   Found {} emails: {:?}", emails.len(), emails);
     }
+    if entity_count > 0 {
+        println!("[DB]   Found {} entities", entity_count);
+    }
+    if !sensitive_kinds.is_empty() {
+        println!("[DB]   ⚠️ Flagged sensitive: {:?}", sensitive_kinds);
+    }
     
-    Ok(())
+    Ok(perceptual_hash)
+}
+
+// Slower follow-up work once an entry is saved: refined tags, the text embedding and the receipt
+// Returns the refined tags, already stored
+fn enrich_entry(data_dir: &Path, conn: &Connection, path: &str, text: &str) -> Vec<String> {
+    // Refine tags with visual classification and enhanced detection
+    let tags = refine_tags(data_dir, conn, path, text);
+    if let Err(e) = store_tags(conn, path, &tags) {
+        eprintln!("[TAG-PIPELINE] Failed to update tags for {}: {}", path, e);
+    }
+    
+    // Embed the text for semantic search (no-op without an installed model)
+    if let Err(e) = embeddings::index_entry(data_dir, conn, path, text) {
+        eprintln!("[EMBED] Failed to embed {}: {}", path, e);
+    }
+    // Needs the refined tags, so runs after tagging
+    if let Err(e) = index_receipt(conn, path, text) {
+        eprintln!("[RECEIPTS] Failed to parse {}: {}", path, e);
+    }
+    tags
 }
 
 #[derive(Serialize)]
@@ -2010,6 +2439,7 @@ struct DbEntry {
     emails: Option<String>,
     pinned: bool,
     custom_tags: Option<String>,
+    sensitive: bool,
 }
 
 #[derive(Clone, Serialize, serde::Deserialize)]
@@ -2023,8 +2453,12 @@ struct SavedSearch {
 }
 
 fn load_all_entries_from_db(app: &AppHandle) -> SqlResult<Vec<DbEntry>> {
-    let conn = init_database(app)?;
-    let mut stmt = conn.prepare("SELECT path, text, created_at, tags, urls, emails, COALESCE(pinned, 0), custom_tags FROM entries ORDER BY pinned DESC, created_at DESC")?;
+    load_entries(&init_database(app)?)
+}
+
+// Every entry outside the trash, pinned first, newest first
+fn load_entries(conn: &Connection) -> SqlResult<Vec<DbEntry>> {
+    let mut stmt = conn.prepare("SELECT path, text, created_at, tags, urls, emails, COALESCE(pinned, 0), custom_tags, COALESCE(sensitive, 0) FROM entries WHERE COALESCE(trashed, 0) = 0 ORDER BY pinned DESC, created_at DESC")?;
     let rows = stmt.query_map([], |row| {
         Ok(DbEntry {
             path: row.get(0)?,
@@ -2035,6 +2469,7 @@ fn load_all_entries_from_db(app: &AppHandle) -> SqlResult<Vec<DbEntry>> {
             emails: row.get(5).ok(),
             pinned: row.get::<_, i64>(6).unwrap_or(0) == 1,
             custom_tags: row.get(7).ok(),
+            sensitive: row.get::<_, i64>(8).unwrap_or(0) == 1,
         })
     })?;
     
@@ -2049,146 +2484,24 @@ fn load_all_entries_from_db(app: &AppHandle) -> SqlResult<Vec<DbEntry>> {
 
 fn delete_entry_from_db(app: &AppHandle, path: &str) -> SqlResult<()> {
     let conn = init_database(app)?;
-    conn.execute("DELETE FROM entries WHERE path = ?1", rusqlite::params![path])?;
-    println!("[DB] ✅ Deleted entry: {}", path);
-    Ok(())
-}
-
-fn process_screenshot(
-    app: AppHandle,
-    path: PathBuf,
-    ignore_map: Arc<Mutex<HashMap<PathBuf, Instant>>>,
-    known_map: Arc<Mutex<HashSet<PathBuf>>>,
-) {
-    // Mark original path as known immediately to prevent duplicate processing
-    {
-        let mut guard = known_map.lock().unwrap();
-        guard.insert(path.clone());
-    }
-    
-    emit_status(&app, "processing", Some(&path), None, None);
-
-    if !path.exists() {
-        emit_status(&app, "idle", Some(&path), None, None);
-        return;
+    // Sensitive entries also lose their old names from the journal
+    let sensitive = is_sensitive_entry(&conn, path);
+    if sensitive {
+        purge_file_history(&conn, path)?;
     }
-
-    if let Err(error) = wait_for_file(&path) {
-        eprintln!("File not ready: {} ({error})", path.display());
-        emit_status(&app, "idle", Some(&path), Some(error), None);
-        return;
-    }
-
-    match run_ocr(&path) {
-        Ok(text) => {
-            let trimmed = text.trim().to_string();
-            
-            // Log detailed results
-            if trimmed.is_empty() {
-                eprintln!("[OCR] ⚠️ WARNING: OCR returned EMPTY text for {}", path.display());
-                eprintln!("[OCR] This could indicate:");
-                eprintln!("[OCR]   1. Image has no readable text");
-                eprintln!("[OCR]   2. OCR configuration needs adjustment");
-                eprintln!("[OCR]   3. Image quality is too poor");
-            } else {
-                let char_count = trimmed.len();
-                let word_count = trimmed.split_whitespace().count();
-                println!("[OCR] ✅ Successfully extracted {} characters, {} words from {}", 
-                    char_count, word_count, path.display());
-                
-                // Check for specific words that user is looking for
-                let important_words = vec!["fights", "building", "lmao", "lmfao"];
-                let text_lower = trimmed.to_lowercase();
-                for word in &important_words {
-                    if text_lower.contains(word) {
-                        println!("[OCR] ✅ Found '{}' in extracted text", word);
-                        // Show context around the word
-                        if let Some(pos) = text_lower.find(word) {
-                            let start = pos.saturating_sub(20);
-                            let end = (pos + word.len() + 20).min(trimmed.len());
-                            println!("[OCR] Context: ...{}...", &trimmed[start..end]);
-                        }
-                    } else {
-                        println!("[OCR] ⚠️ '{}' NOT found in extracted text", word);
-                    }
-                }
-                
-                if char_count < 100 {
-                    println!("[OCR] Full text: {}", trimmed);
-                } else {
-                    println!("[OCR] Text preview: {}...", trimmed.chars().take(100).collect::<String>());
-                }
-            }
-            
-            let final_path = match rename_with_text(&path, &trimmed) {
-                Ok(new_path) => {
-                    // Mark both original and renamed paths as known to prevent duplicate processing
-                    {
-                        let mut guard = known_map.lock().unwrap();
-                        guard.insert(path.clone()); // Original path
-                        guard.insert(new_path.clone()); // Renamed path
-                        println!("[RENAME] Marked both paths as known: {} -> {}", path.display(), new_path.display());
-                    }
-                    remember_ignore(&ignore_map, &new_path);
-                    remember_ignore(&ignore_map, &path); // Also ignore original path
-                    new_path
-                }
-                Err(error) => {
-                    eprintln!("Rename failed for {}: {error}", path.display());
-                    // Still mark original as known even if rename failed
-                    {
-                        let mut guard = known_map.lock().unwrap();
-                        guard.insert(path.clone());
-                    }
-                    path.clone()
-                }
-            };
-            remember_ignore(&ignore_map, &path);
-            
-            // Get creation date from ORIGINAL path (before rename) - this is what we'll match against on startup
-            let created_at = get_file_created_at(&path)
-                .unwrap_or_else(|| {
-                    SystemTime::now()
-                        .duration_since(UNIX_EPOCH)
-                        .unwrap()
-                        .as_secs()
-                        .to_string()
-                });
-            
-            // Save to database with final_path (renamed path) but original creation date
-            if let Err(e) = save_entry_to_db(&app, &final_path.to_string_lossy(), &trimmed, &created_at) {
-                eprintln!("[DB] ⚠️ Failed to save entry to database: {}", e);
-            }
-            
-            // Always emit the text, even if empty (so frontend knows OCR ran)
-            // Emit with final_path (renamed path if successful, original if not)
-            emit_status(&app, "idle", Some(&final_path), None, Some(trimmed));
-        }
-        Err(error) => {
-            eprintln!("[OCR] ❌ OCR failed for {}: {error}", path.display());
-            eprintln!("[OCR] Error details: {}", error);
-            
-            // Still save the entry to database even if OCR failed
-            // This allows the screenshot to appear in the UI, even without text
-            let created_at = get_file_created_at(&path)
-                .unwrap_or_else(|| {
-                    SystemTime::now()
-                        .duration_since(UNIX_EPOCH)
-                        .unwrap()
-                        .as_secs()
-                        .to_string()
-                });
-            
-            // Save with empty text - user can still see the image
-            if let Err(e) = save_entry_to_db(&app, &path.to_string_lossy(), "", &created_at) {
-                eprintln!("[DB] ⚠️ Failed to save entry to database after OCR failure: {}", e);
-            } else {
-                println!("[DB] ✅ Saved entry (no OCR text) to database: {}", path.display());
-            }
-            
-            emit_status(&app, "idle", Some(&path), Some(error), None);
-        }
+    conn.execute("DELETE FROM entries WHERE path = ?1", rusqlite::params![path])?;
+    conn.execute("DELETE FROM embeddings WHERE path = ?1", rusqlite::params![path])?;
+    conn.execute("DELETE FROM entities WHERE path = ?1", rusqlite::params![path])?;
+    conn.execute("DELETE FROM receipts WHERE path = ?1", rusqlite::params![path])?;
+    if sensitive {
+        // Merge the full-text segments that still hold its words, then rewrite the file without
+        // the free pages and slack that held earlier versions of its text
+        conn.execute("INSERT INTO entries_fts (entries_fts) VALUES ('optimize')", [])?;
+        conn.execute_batch("VACUUM")?;
     }
+    unindex_hash(app, path);
+    println!("[DB] ✅ Deleted entry: {}{}", path, if sensitive { " (secure)" } else { "" });
+    Ok(())
 }
 
 fn handle_event(
@@ -2227,6 +2540,13 @@ fn handle_event(
             continue;
         }
 
+        // Excluded by name or folder: don't even wait for the file
+        if load_exclusion_rules(app).check_path(&path) == Some(exclusions::Exclusion::Skip) {
+            known_map.lock().unwrap().insert(path.clone());
+            println!("[EXCLUDE] Not indexing {}", path.display());
+            continue;
+        }
+
         if !is_ignored(ignore_map, &path) {
             let now = Instant::now();
             {
@@ -2234,7 +2554,7 @@ fn handle_event(
                 guard.insert(path.clone(), now);
             }
 
-            let app_handle = app.clone();
+            let indexer = indexer(app);
             let debounce_map = Arc::clone(debounce_map);
             let ignore_map = Arc::clone(ignore_map);
             let known_map = Arc::clone(known_map);
@@ -2246,7 +2566,7 @@ fn handle_event(
                 };
 
                 if should_process {
-                    process_screenshot(app_handle, path, ignore_map, known_map);
+                    indexer.process_screenshot(path, ignore_map, known_map);
                 }
             });
         }
@@ -2272,120 +2592,16 @@ fn load_existing_screenshots(watch_dirs: &[PathBuf]) -> Vec<PathBuf> {
 }
 
 fn process_existing_screenshots(app: AppHandle, paths: Vec<PathBuf>) {
-    tauri::async_runtime::spawn_blocking(move || {
-        let total = paths.len();
-        if total == 0 {
-            emit_batch_progress(
-                &app,
-                BatchProgress {
-                    total: 0,
-                    completed: 0,
-                    percent: 100.0,
-                    eta_seconds: 0,
-                    in_progress: false,
-                },
-            );
-            return;
-        }
-
-        emit_batch_progress(
-            &app,
-            BatchProgress {
-                total,
-                completed: 0,
-                percent: 0.0,
-                eta_seconds: 0,
-                in_progress: true,
-            },
-        );
-
-        let mut completed = 0usize;
-        let mut total_elapsed = Duration::from_secs(0);
-
-        for path in paths {
-            let start = Instant::now();
-            emit_status(&app, "processing", Some(&path), None, None);
-            if let Err(error) = wait_for_file(&path) {
-                eprintln!("File not ready: {} ({error})", path.display());
-                emit_status(&app, "idle", Some(&path), Some(error), None);
-                completed += 1;
-                total_elapsed += start.elapsed();
-                let average = total_elapsed.as_secs_f64() / completed as f64;
-                let remaining = total.saturating_sub(completed) as f64;
-                emit_batch_progress(
-                    &app,
-                    BatchProgress {
-                        total,
-                        completed,
-                        percent: (completed as f64 / total as f64) * 100.0,
-                        eta_seconds: (average * remaining).round() as u64,
-                        in_progress: completed < total,
-                    },
-                );
-                continue;
-            }
-
-            match run_ocr(&path) {
-                Ok(text) => {
-                    let trimmed = text.trim().to_string();
-                    
-                    // Log detailed results
-                    if trimmed.is_empty() {
-                        eprintln!("[OCR] ⚠️ WARNING: OCR returned EMPTY text for {}", path.display());
-                    } else {
-                        let char_count = trimmed.len();
-                        let word_count = trimmed.split_whitespace().count();
-                        println!("[OCR] ✅ Extracted {} chars, {} words from {}", 
-                            char_count, word_count, path.display());
-                    }
-                    
-                    // Get creation date from original path (before any potential rename)
-                    let created_at = get_file_created_at(&path)
-                        .unwrap_or_else(|| {
-                            SystemTime::now()
-                                .duration_since(UNIX_EPOCH)
-                                .unwrap()
-                                .as_secs()
-                                .to_string()
-                        });
-                    
-                    // Save to database (using original path since process_existing_screenshots doesn't rename)
-                    if let Err(e) = save_entry_to_db(&app, &path.to_string_lossy(), &trimmed, &created_at) {
-                        eprintln!("[DB] ⚠️ Failed to save entry to database: {}", e);
-                    }
-                    
-                    // Always emit the text, even if empty
-                    emit_status(&app, "idle", Some(&path), None, Some(trimmed));
-                }
-                Err(error) => {
-                    eprintln!("[OCR] ❌ OCR failed for {}: {error}", path.display());
-                    emit_status(&app, "idle", Some(&path), Some(error), None);
-                }
-            }
-
-            completed += 1;
-            total_elapsed += start.elapsed();
-            let average = total_elapsed.as_secs_f64() / completed as f64;
-            let remaining = total.saturating_sub(completed) as f64;
-            emit_batch_progress(
-                &app,
-                BatchProgress {
-                    total,
-                    completed,
-                    percent: (completed as f64 / total as f64) * 100.0,
-                    eta_seconds: (average * remaining).round() as u64,
-                    in_progress: completed < total,
-                },
-            );
-        }
-    });
+    let indexer = indexer(&app);
+    tauri::async_runtime::spawn_blocking(move || indexer.process_batch(&paths));
 }
 
 fn start_watcher(app: AppHandle) {
     tauri::async_runtime::spawn_blocking(move || {
         let (tx, rx) = mpsc::channel();
         let debounce_map = Arc::new(Mutex::new(HashMap::new()));
-        let ignore_map = Arc::new(Mutex::new(HashMap::new()));
+        // Shared with commands that move files back into watch dirs (e.g. restore from trash)
+        let ignore_map = Arc::clone(&app.state::<WatcherIgnoreState>().0);
         let known_map = Arc::new(Mutex::new(HashSet::new()));
 
         let mut watcher = match notify::recommended_watcher(move |res| {
@@ -2476,16 +2692,7 @@ fn start_watcher(app: AppHandle) {
         } else {
             println!("[WATCHER] All {} existing screenshots already indexed, skipping processing", existing.len());
             // Emit batch progress to indicate we're done
-            emit_batch_progress(
-                &app,
-                BatchProgress {
-                    total: 0,
-                    completed: 0,
-                    percent: 100.0,
-                    eta_seconds: 0,
-                    in_progress: false,
-                },
-            );
+            indexer(&app).process_batch(&[]);
         }
 
         for dir in watch_dirs {
@@ -2515,6 +2722,8 @@ fn delete_files(app: AppHandle, paths: Vec<String>) -> Result<DeleteResult, Stri
     if watch_dirs.is_empty() {
         return Err("Watch directories not available".to_string());
     }
+    // Archived screenshots live in the library, so it's an allowed location too
+    let allowed_dirs = managed_dirs(&app);
 
     let mut deleted = Vec::new();
     let mut failed = Vec::new();
@@ -2532,11 +2741,9 @@ fn delete_files(app: AppHandle, paths: Vec<String>) -> Result<DeleteResult, Stri
         }
         
         let Ok(canonical_path) = fs::canonicalize(&path) else {
-            // Can't canonicalize but file exists - try direct deletion
-            match fs::remove_file(&path) {
-                Ok(_) => {
-                    deleted.push(path_str);
-                }
+            // Can't canonicalize but file exists - try moving it directly
+            match delete_or_trash(&app, &path_str, &path) {
+                Ok(()) => deleted.push(path_str),
                 Err(e) => {
                     eprintln!("[DELETE] Failed to delete {} (canonicalize failed): {:?}", path_str, e);
                     failed.push(path_str);
@@ -2545,25 +2752,15 @@ fn delete_files(app: AppHandle, paths: Vec<String>) -> Result<DeleteResult, Stri
             continue;
         };
 
-        let allowed = watch_dirs.iter().any(|dir| {
-            fs::canonicalize(dir)
-                .map(|canonical_dir| canonical_path.starts_with(&canonical_dir))
-                .unwrap_or(false)
-        });
-
-        if !allowed {
+        if !is_within_dirs(&canonical_path, &allowed_dirs) {
             eprintln!("[DELETE] Path not in allowed directories: {}", path_str);
             failed.push(path_str);
             continue;
         }
 
-        match fs::remove_file(&canonical_path) {
-            Ok(_) => {
-                // Return the original path_str, not canonicalized, so it matches frontend entries
-                // Also remove from database
-                let _ = delete_entry_from_db(&app, &path_str);
-                deleted.push(path_str);
-            }
+        // Return the original path_str, not canonicalized, so it matches frontend entries
+        match delete_or_trash(&app, &path_str, &canonical_path) {
+            Ok(()) => deleted.push(path_str),
             Err(e) => {
                 eprintln!("[DELETE] Failed to delete {}: {:?}", path_str, e);
                 failed.push(path_str);
@@ -2657,52 +2854,313 @@ fn get_file_metadata(paths: Vec<String>) -> Result<Vec<FileMetadata>, String> {
     Ok(metadata)
 }
 
+// ============== NEAR-DUPLICATE INDEX ==============
+
+// BK-tree over perceptual hashes, built from the DB on first use and
+// then kept current as entries are saved and deleted
+// The CLI, imports and the local API write chronicle.db too, so each use syncs it with the DB's hashes
+#[derive(Default)]
+struct HashIndexState(Mutex<Option<hash_index::BkTree>>);
+
+// Max Hamming distance (of 256 bits) for two screenshots to count as near-duplicates
+const DEFAULT_DUPLICATE_THRESHOLD: u32 = 10;
+
+fn index_hash(app: &AppHandle, path: &str, hash: &[u8]) {
+    if let Some(state) = app.try_state::<HashIndexState>() {
+        if let Some(tree) = state.0.lock().unwrap().as_mut() {
+            tree.insert(path, hash.to_vec());
+        }
+    }
+}
+
+fn unindex_hash(app: &AppHandle, path: &str) {
+    if let Some(state) = app.try_state::<HashIndexState>() {
+        if let Some(tree) = state.0.lock().unwrap().as_mut() {
+            tree.remove(path);
+        }
+    }
+}
+
+fn load_perceptual_hashes(conn: &Connection) -> Result<HashMap<String, Vec<u8>>, String> {
+    let mut stmt = conn.prepare("SELECT path, perceptual_hash FROM entries WHERE perceptual_hash IS NOT NULL AND COALESCE(trashed, 0) = 0")
+        .map_err(|e| format!("Query error: {}", e))?;
+    
+    let hashes = stmt.query_map([], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, Vec<u8>>(1)?))
+    })
+    .map_err(|e| format!("Query map error: {}", e))?
+    .filter_map(|r| r.ok())
+    .collect();
+    Ok(hashes)
+}
+
+// Groups of near-duplicate screenshots
+// linkage "single" (default) groups connected components; "complete" requires every pair in a group to match
 #[tauri::command]
-fn find_similar_screenshots(app: AppHandle, threshold: Option<u32>) -> Result<Vec<Vec<String>>, String> {
+fn find_similar_screenshots(app: AppHandle, threshold: Option<u32>, linkage: Option<String>) -> Result<Vec<Vec<String>>, String> {
+    let linkage = hash_index::Linkage::parse(linkage.as_deref())?;
     let conn = init_database(&app)
         .map_err(|e| format!("DB error: {}", e))?;
     
-    let mut stmt = conn.prepare("SELECT path, perceptual_hash FROM entries WHERE perceptual_hash IS NOT NULL")
-        .map_err(|e| format!("Query error: {}", e))?;
+    let threshold = threshold.unwrap_or(DEFAULT_DUPLICATE_THRESHOLD);
+    let state = app.state::<HashIndexState>();
+    let mut guard = state.0.lock().unwrap();
+    let hashes = load_perceptual_hashes(&conn)?;
+    let tree = match guard.as_mut() {
+        Some(tree) => {
+            let changed = tree.sync(&hashes);
+            if changed > 0 {
+                println!("[SIMILARITY] Synced hash index: {} entries changed since last use", changed);
+            }
+            tree
+        }
+        None => {
+            let mut tree = hash_index::BkTree::default();
+            tree.sync(&hashes);
+            println!("[SIMILARITY] Built hash index with {} entries", tree.len());
+            guard.insert(tree)
+        }
+    };
     
-    let entries: Vec<(String, Vec<u8>)> = stmt.query_map([], |row| {
-        Ok((row.get(0)?, row.get(1)?))
+    let groups = hash_index::cluster(tree, &hashes, threshold, linkage);
+    
+    println!("[SIMILARITY] Found {} groups of similar screenshots ({:?} linkage)", groups.len(), linkage);
+    Ok(groups)
+}
+
+// Weights for find_similar_to: (dHash, pHash, block mean); renormalized over the hashes both entries have
+const SIMILARITY_HASH_WEIGHTS: [f32; 3] = [0.3, 0.4, 0.3];
+// Share of the score taken by CLIP image embeddings when both entries have one
+const SIMILARITY_EMBEDDING_WEIGHT: f32 = 0.4;
+
+#[derive(Serialize)]
+struct SimilarEntry {
+    #[serde(flatten)]
+    entry: DbEntry,
+    score: f32,
+}
+
+struct SimilarityRow {
+    path: String,
+    hashes: [Option<Vec<u8>>; 3],
+}
+
+// 1.0 for identical hashes, 0.0 when every bit differs
+fn hash_similarity(a: &[u8], b: &[u8]) -> Option<f32> {
+    if a.is_empty() || a.len() != b.len() {
+        return None;
+    }
+    Some(1.0 - hamming_distance(a, b) as f32 / (a.len() * 8) as f32)
+}
+
+#[tauri::command]
+fn find_similar_to(app: AppHandle, path: String, k: Option<usize>) -> Result<Vec<SimilarEntry>, String> {
+    let conn = init_database(&app)
+        .map_err(|e| format!("DB error: {}", e))?;
+    let (results, computed_hash) = similar_entries(&conn, &path, k.unwrap_or(20).max(1))?;
+    if let Some(hash) = computed_hash {
+        index_hash(&app, &path, &hash);
+    }
+    Ok(results)
+}
+
+// The k entries most like `path`, plus its perceptual hash when it had to be computed
+fn similar_entries(conn: &Connection, path: &str, k: usize) -> Result<(Vec<SimilarEntry>, Option<Vec<u8>>), String> {
+    let mut stmt = conn.prepare("SELECT path, perceptual_hash, phash, block_hash FROM entries WHERE COALESCE(trashed, 0) = 0")
+        .map_err(|e| format!("Query error: {}", e))?;
+    let rows: Vec<SimilarityRow> = stmt.query_map([], |row| {
+        Ok(SimilarityRow {
+            path: row.get(0)?,
+            hashes: [row.get(1)?, row.get(2)?, row.get(3)?],
+        })
     })
     .map_err(|e| format!("Query map error: {}", e))?
     .filter_map(|r| r.ok())
     .collect();
-    
-    let threshold = threshold.unwrap_or(10); // Default threshold
-    let mut groups: Vec<Vec<String>> = Vec::new();
-    let mut assigned = vec![false; entries.len()];
-    
-    for i in 0..entries.len() {
-        if assigned[i] {
-            continue;
+
+    // Hashes for the chosen screenshot - compute from the file if the entry predates them
+    let mut target = rows.iter()
+        .find(|row| row.path == path)
+        .map(|row| row.hashes.clone())
+        .unwrap_or([None, None, None]);
+    let mut computed_hash = None;
+    if target.iter().any(|hash| hash.is_none()) {
+        let file = Path::new(path);
+        let (phash, block_hash) = compute_similarity_hashes(file)?;
+        target = [Some(compute_perceptual_hash(file)?), Some(phash), Some(block_hash)];
+        if let Err(e) = conn.execute(
+            "UPDATE entries SET perceptual_hash = ?1, phash = ?2, block_hash = ?3 WHERE path = ?4",
+            rusqlite::params![target[0], target[1], target[2], path],
+        ) {
+            eprintln!("[SIMILARITY] Failed to store hashes for {}: {}", path, e);
+        } else {
+            computed_hash = target[0].clone();
         }
-        
-        let mut group = vec![i];
-        assigned[i] = true;
-        
-        for j in (i + 1)..entries.len() {
-            if assigned[j] {
-                continue;
+    }
+
+    let embeddings = clip::image_embeddings(conn);
+    let target_embedding = embeddings.get(path);
+
+    let mut scored: Vec<(String, f32)> = rows.into_iter()
+        .filter(|row| row.path != path)
+        .filter_map(|row| {
+            // Weighted mean over the hash algorithms both entries have
+            let (weighted, total_weight) = row.hashes.iter()
+                .zip(target.iter())
+                .zip(SIMILARITY_HASH_WEIGHTS)
+                .filter_map(|((hash, target_hash), weight)| {
+                    hash_similarity(hash.as_deref()?, target_hash.as_deref()?).map(|sim| (sim * weight, weight))
+                })
+                .fold((0.0f32, 0.0f32), |(sum, total), (value, weight)| (sum + value, total + weight));
+            if total_weight == 0.0 {
+                return None;
             }
-            
-            let distance = hamming_distance(&entries[i].1, &entries[j].1);
-            if distance <= threshold {
-                group.push(j);
-                assigned[j] = true;
+            let hash_score = weighted / total_weight;
+
+            let score = match (target_embedding, embeddings.get(&row.path)) {
+                (Some(a), Some(b)) if a.len() == b.len() => {
+                    let embedding_score = onnx::cosine_similarity(a, b).max(0.0);
+                    (1.0 - SIMILARITY_EMBEDDING_WEIGHT) * hash_score + SIMILARITY_EMBEDDING_WEIGHT * embedding_score
+                }
+                _ => hash_score,
+            };
+            Some((row.path, score))
+        })
+        .collect();
+
+    scored.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+    scored.truncate(k);
+
+    let results: Vec<SimilarEntry> = scored.into_iter()
+        .filter_map(|(path, score)| load_entry_by_path(conn, &path).map(|entry| SimilarEntry { entry, score }))
+        .collect();
+
+    println!("[SIMILARITY] ✅ Found {} screenshots similar to {}", results.len(), path);
+    Ok((results, computed_hash))
+}
+
+// ============== DUPLICATE RESOLUTION ==============
+
+#[derive(Serialize)]
+struct DuplicateResolution {
+    keep: Vec<String>,
+    delete: Vec<String>,
+    merged_custom_tags: Vec<String>, // Custom tags the kept screenshot ends up with
+    dry_run: bool,
+    result: Option<DeleteResult>, // None for dry runs
+}
+
+struct DuplicateCandidate {
+    path: String,
+    created_at: u64,
+    text_len: usize,
+    pinned: bool,
+    custom_tags: Vec<String>,
+    pixels: u64,
+}
+
+fn load_duplicate_candidate(conn: &Connection, path: &str) -> Option<DuplicateCandidate> {
+    let (text, created_at, pinned, custom_tags): (String, String, i64, Option<String>) = conn.query_row(
+        "SELECT text, created_at, COALESCE(pinned, 0), custom_tags FROM entries WHERE path = ?1",
+        rusqlite::params![path],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+    ).ok()?;
+
+    let pixels = image::image_dimensions(path)
+        .map(|(w, h)| w as u64 * h as u64)
+        .unwrap_or(0);
+
+    Some(DuplicateCandidate {
+        path: path.to_string(),
+        created_at: created_at.parse().unwrap_or(0),
+        text_len: text.trim().chars().count(),
+        pinned: pinned == 1,
+        custom_tags: custom_tags.and_then(|s| serde_json::from_str(&s).ok()).unwrap_or_default(),
+        pixels,
+    })
+}
+
+// Pick which screenshot of a duplicate group survives
+// policy: "newest" | "largest" (resolution) | "longest_text" (OCR) | "pinned" (pinned first, then newest)
+// Pinned screenshots are never deleted, whatever the policy
+// merge_tags (default true) copies custom tags from the deleted screenshots onto the keeper
+#[tauri::command]
+fn resolve_duplicates(
+    app: AppHandle,
+    group: Vec<String>,
+    policy: String,
+    merge_tags: Option<bool>,
+    dry_run: Option<bool>,
+) -> Result<DuplicateResolution, String> {
+    if group.len() < 2 {
+        return Err("A duplicate group needs at least two screenshots".to_string());
+    }
+    let dry_run = dry_run.unwrap_or(false);
+
+    let conn = init_database(&app)
+        .map_err(|e| format!("Database error: {}", e))?;
+    let candidates: Vec<DuplicateCandidate> = group.iter()
+        .filter_map(|path| load_duplicate_candidate(&conn, path))
+        .collect();
+    if candidates.len() < 2 {
+        return Err("Fewer than two screenshots of this group are indexed".to_string());
+    }
+
+    // Later created_at breaks ties so the result doesn't depend on group order
+    let keeper = match policy.as_str() {
+        "newest" => candidates.iter().max_by_key(|c| c.created_at),
+        "largest" => candidates.iter().max_by_key(|c| (c.pixels, c.created_at)),
+        "longest_text" => candidates.iter().max_by_key(|c| (c.text_len, c.created_at)),
+        "pinned" => candidates.iter().max_by_key(|c| (c.pinned, c.created_at)),
+        other => return Err(format!(
+            "Unknown policy '{}' (expected newest, largest, longest_text or pinned)", other
+        )),
+    }
+    .ok_or_else(|| "No screenshot to keep".to_string())?;
+
+    let (keep, delete): (Vec<&DuplicateCandidate>, Vec<&DuplicateCandidate>) = candidates.iter()
+        .partition(|c| c.path == keeper.path || c.pinned);
+
+    let mut merged_custom_tags = keeper.custom_tags.clone();
+    if merge_tags.unwrap_or(true) {
+        for candidate in &delete {
+            for tag in &candidate.custom_tags {
+                if !merged_custom_tags.contains(tag) {
+                    merged_custom_tags.push(tag.clone());
+                }
             }
         }
-        
-        if group.len() > 1 {
-            groups.push(group.into_iter().map(|idx| entries[idx].0.clone()).collect());
-        }
     }
-    
-    println!("[SIMILARITY] Found {} groups of similar screenshots", groups.len());
-    Ok(groups)
+
+    let keep: Vec<String> = keep.iter().map(|c| c.path.clone()).collect();
+    let delete: Vec<String> = delete.iter().map(|c| c.path.clone()).collect();
+
+    if dry_run {
+        println!("[DUPLICATES] Dry run ({}): keep {:?}, delete {} screenshots", policy, keep, delete.len());
+        return Ok(DuplicateResolution { keep, delete, merged_custom_tags, dry_run, result: None });
+    }
+
+    // Merge tags before deleting so they aren't lost if deletion partially fails
+    if merged_custom_tags != keeper.custom_tags {
+        let tags_json = serde_json::to_string(&merged_custom_tags)
+            .map_err(|e| format!("Failed to serialize tags: {}", e))?;
+        conn.execute(
+            "UPDATE entries SET custom_tags = ?1 WHERE path = ?2",
+            rusqlite::params![tags_json, keeper.path],
+        ).map_err(|e| format!("Failed to merge custom tags: {}", e))?;
+        println!("[DUPLICATES] Merged custom tags into {}: {:?}", keeper.path, merged_custom_tags);
+    }
+
+    // Same path as a manual delete, so the watch-dir safety check applies
+    let result = if delete.is_empty() {
+        DeleteResult { deleted: vec![], failed: vec![] }
+    } else {
+        delete_files(app, delete.clone())?
+    };
+
+    println!("[DUPLICATES] ✅ Resolved group with policy '{}': kept {}, deleted {}, failed {}",
+             policy, keep.len(), result.deleted.len(), result.failed.len());
+    Ok(DuplicateResolution { keep, delete, merged_custom_tags, dry_run, result: Some(result) })
 }
 
 #[tauri::command]
@@ -2749,6 +3207,10 @@ fn open_quick_search(app: AppHandle) -> Result<(), String> {
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
+        .manage(HashIndexState::default())
+        .manage(WatcherIgnoreState::default())
+        .manage(DatabaseKeyState::default())
+        .manage(ApiServerState::default())
         .plugin(tauri_plugin_opener::init())
         .plugin(
             tauri_plugin_global_shortcut::Builder::new()
@@ -2775,6 +3237,8 @@ pub fn run() {
             copy_image_to_clipboard,
             load_all_entries,
             find_similar_screenshots,
+            find_similar_to,
+            resolve_duplicates,
             open_quick_search,
             compute_missing_hashes,
             reprocess_all_with_visual,
@@ -2790,12 +3254,72 @@ pub fn run() {
             // Custom Tags
             add_custom_tag,
             remove_custom_tag,
-            get_all_custom_tags
+            get_all_custom_tags,
+            // Semantic Search
+            semantic_search,
+            compute_missing_embeddings,
+            // Settings
+            get_setting,
+            set_setting,
+            // Trash
+            get_trashed_entries,
+            restore_from_trash,
+            empty_trash,
+            // Retention
+            create_retention_rule,
+            list_retention_rules,
+            update_retention_rule,
+            delete_retention_rule,
+            preview_retention_rule,
+            approve_retention_rule,
+            run_retention_rule,
+            // Archive
+            archive_entries,
+            // Renaming
+            preview_rename,
+            rename_entries,
+            get_rename_folders,
+            set_rename_folder,
+            // File operations
+            list_file_operations,
+            undo_operation,
+            revert_all_renames,
+            // Entities
+            get_entities,
+            // Receipts
+            get_receipt,
+            export_receipts,
+            // Export
+            export_index,
+            // Import
+            import_index,
+            // Secrets
+            get_secrets,
+            set_sensitive,
+            // Redaction
+            redact_entry,
+            // Encryption
+            get_database_status,
+            unlock_database,
+            encrypt_database,
+            decrypt_database,
+            // Exclusions
+            get_exclusion_rules,
+            set_exclusion_rules,
+            // Local API
+            get_api_settings,
+            set_api_settings,
+            regenerate_api_token
         ])
         .setup(|app| {
             // Verify Tesseract on startup
             verify_tesseract();
-            start_watcher(app.app_handle().clone());
+            // An encrypted index has to be unlocked before anything can read it
+            if database_locked(app.app_handle()) && !unlock_with_keyfile(app.app_handle()) {
+                println!("[DB] 🔒 Database is encrypted - waiting for unlock");
+            } else {
+                start_background_work(app.app_handle());
+            }
             
             // Register the global shortcuts (both Cmd and Ctrl for macOS compatibility)
             let app_handle = app.app_handle().clone();
@@ -2812,52 +3336,55 @@ pub fn run() {
                 Err(e) => eprintln!("[SHORTCUT] ⚠️ Failed to register Ctrl+Shift+F: {} (may not be needed)", e),
             }
             
-            // Automatically process tags for entries without tags on startup (in background)
-            let app_handle_for_reprocess = app.app_handle().clone();
-            tauri::async_runtime::spawn_blocking(move || {
-                // Small delay to let the app finish initializing
-                std::thread::sleep(std::time::Duration::from_secs(2));
-                
-                println!("[STARTUP] 🔄 Processing tags for entries without tags...");
-                match reprocess_entries_without_tags_enhanced(app_handle_for_reprocess.clone()) {
-                    Ok(count) => {
-                        if count > 0 {
-                            println!("[STARTUP] ✅ Processed tags for {} entries", count);
-                        } else {
-                            println!("[STARTUP] ✅ All entries already have tags");
-                        }
-                    }
-                    Err(e) => {
-                        eprintln!("[STARTUP] ⚠️ Failed to process tags: {}", e);
-                    }
-                }
-                
-                // Also compute missing perceptual hashes in background
-                println!("[STARTUP] 🔄 Computing missing perceptual hashes...");
-                match compute_missing_hashes(app_handle_for_reprocess) {
-                    Ok(count) => {
-                        if count > 0 {
-                            println!("[STARTUP] ✅ Computed {} missing perceptual hashes", count);
-                        } else {
-                            println!("[STARTUP] ✅ All entries already have perceptual hashes");
-                        }
-                    }
-                    Err(e) => {
-                        eprintln!("[STARTUP] ⚠️ Failed to compute missing hashes: {}", e);
-                    }
-                }
-            });
-            
             Ok(())
         })
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
 
+// Automatically process tags for entries without tags on startup (in background)
+fn start_startup_reprocessing(app: AppHandle) {
+    tauri::async_runtime::spawn_blocking(move || {
+        // Small delay to let the app finish initializing
+        std::thread::sleep(std::time::Duration::from_secs(2));
+        let _in_use = database_in_use();
+
+        println!("[STARTUP] 🔄 Processing tags for entries without tags...");
+        match reprocess_entries_without_tags_enhanced(app.clone()) {
+            Ok(count) => {
+                if count > 0 {
+                    println!("[STARTUP] ✅ Processed tags for {} entries", count);
+                } else {
+                    println!("[STARTUP] ✅ All entries already have tags");
+                }
+            }
+            Err(e) => {
+                eprintln!("[STARTUP] ⚠️ Failed to process tags: {}", e);
+            }
+        }
+
+        // Also compute missing perceptual hashes in background
+        println!("[STARTUP] 🔄 Computing missing perceptual hashes...");
+        match compute_missing_hashes(app) {
+            Ok(count) => {
+                if count > 0 {
+                    println!("[STARTUP] ✅ Computed {} missing perceptual hashes", count);
+                } else {
+                    println!("[STARTUP] ✅ All entries already have perceptual hashes");
+                }
+            }
+            Err(e) => {
+                eprintln!("[STARTUP] ⚠️ Failed to compute missing hashes: {}", e);
+            }
+        }
+    });
+}
+
 // Enhanced reprocessing using the separate tagging pipeline (with visual classification)
 fn reprocess_entries_without_tags_enhanced(app: AppHandle) -> Result<usize, String> {
     let conn = init_database(&app)
         .map_err(|e| format!("DB error: {}", e))?;
+    let indexer = indexer(&app);
     
     // Get entries without tags or with empty tags
     let mut stmt = conn.prepare("SELECT path, text FROM entries WHERE tags IS NULL OR tags = '' OR tags = '[]'")
@@ -2875,7 +3402,7 @@ fn reprocess_entries_without_tags_enhanced(app: AppHandle) -> Result<usize, Stri
         // Use the same pipeline as new entries: visual classification first, then OCR
         // This ensures old images get the benefit of visual analysis
         // This function guarantees every entry gets at least one tag
-        process_tags_for_entry(&app, &path, &text);
+        indexer.process_tags(&path, &text);
         updated += 1;
     }
     
@@ -2914,7 +3441,10 @@ fn save_search(app: AppHandle, name: String, query: String, collection_filter: O
 fn load_saved_searches(app: AppHandle) -> Result<Vec<SavedSearch>, String> {
     let conn = init_database(&app)
         .map_err(|e| format!("Database error: {}", e))?;
-    
+    saved_searches(&conn)
+}
+
+fn saved_searches(conn: &Connection) -> Result<Vec<SavedSearch>, String> {
     let mut stmt = conn.prepare("SELECT id, name, query, collection_filter, created_at, updated_at FROM saved_searches ORDER BY name ASC")
         .map_err(|e| format!("Failed to prepare query: {}", e))?;
     
@@ -3015,7 +3545,7 @@ fn get_pinned_entries(app: AppHandle) -> Result<Vec<DbEntry>, String> {
     let conn = init_database(&app)
         .map_err(|e| format!("Database error: {}", e))?;
     
-    let mut stmt = conn.prepare("SELECT path, text, created_at, tags, urls, emails, pinned, custom_tags FROM entries WHERE pinned = 1 ORDER BY created_at DESC")
+    let mut stmt = conn.prepare("SELECT path, text, created_at, tags, urls, emails, pinned, custom_tags, COALESCE(sensitive, 0) FROM entries WHERE pinned = 1 AND COALESCE(trashed, 0) = 0 ORDER BY created_at DESC")
         .map_err(|e| format!("Failed to prepare query: {}", e))?;
     
     let rows = stmt.query_map([], |row| {
@@ -3028,6 +3558,7 @@ fn get_pinned_entries(app: AppHandle) -> Result<Vec<DbEntry>, String> {
             emails: row.get(5).ok(),
             pinned: row.get::<_, i64>(6).unwrap_or(0) == 1,
             custom_tags: row.get(7).ok(),
+            sensitive: row.get::<_, i64>(8).unwrap_or(0) == 1,
         })
     }).map_err(|e| format!("Failed to query: {}", e))?;
     
@@ -3048,22 +3579,31 @@ fn get_pinned_entries(app: AppHandle) -> Result<Vec<DbEntry>, String> {
 fn add_custom_tag(app: AppHandle, path: String, tag: String) -> Result<Vec<String>, String> {
     let conn = init_database(&app)
         .map_err(|e| format!("Database error: {}", e))?;
-    
-    // Get current custom_tags
+    add_entry_tag(&conn, &path, &tag)
+}
+
+#[tauri::command]
+fn remove_custom_tag(app: AppHandle, path: String, tag: String) -> Result<Vec<String>, String> {
+    let conn = init_database(&app)
+        .map_err(|e| format!("Database error: {}", e))?;
+    remove_entry_tag(&conn, &path, &tag)
+}
+
+fn load_custom_tags(conn: &Connection, path: &str) -> Result<Vec<String>, String> {
     let current: Option<String> = conn.query_row(
         "SELECT custom_tags FROM entries WHERE path = ?1",
         rusqlite::params![path],
         |row| row.get(0),
     ).map_err(|e| format!("Entry not found: {}", e))?;
-    
-    // Parse existing tags or create empty vec
-    let mut tags: Vec<String> = current
-        .and_then(|s| serde_json::from_str(&s).ok())
-        .unwrap_or_default();
+    Ok(parse_json_list(current))
+}
+
+fn add_entry_tag(conn: &Connection, path: &str, tag: &str) -> Result<Vec<String>, String> {
+    let mut tags = load_custom_tags(conn, path)?;
     
     // Add tag if not already present
-    if !tags.contains(&tag) {
-        tags.push(tag.clone());
+    if !tags.iter().any(|t| t == tag) {
+        tags.push(tag.to_string());
         
         let tags_json = serde_json::to_string(&tags)
             .map_err(|e| format!("Failed to serialize tags: {}", e))?;
@@ -3079,25 +3619,9 @@ fn add_custom_tag(app: AppHandle, path: String, tag: String) -> Result<Vec<Strin
     Ok(tags)
 }
 
-#[tauri::command]
-fn remove_custom_tag(app: AppHandle, path: String, tag: String) -> Result<Vec<String>, String> {
-    let conn = init_database(&app)
-        .map_err(|e| format!("Database error: {}", e))?;
-    
-    // Get current custom_tags
-    let current: Option<String> = conn.query_row(
-        "SELECT custom_tags FROM entries WHERE path = ?1",
-        rusqlite::params![path],
-        |row| row.get(0),
-    ).map_err(|e| format!("Entry not found: {}", e))?;
-    
-    // Parse existing tags
-    let mut tags: Vec<String> = current
-        .and_then(|s| serde_json::from_str(&s).ok())
-        .unwrap_or_default();
-    
-    // Remove tag
-    tags.retain(|t| t != &tag);
+fn remove_entry_tag(conn: &Connection, path: &str, tag: &str) -> Result<Vec<String>, String> {
+    let mut tags = load_custom_tags(conn, path)?;
+    tags.retain(|t| t != tag);
     
     let tags_json = serde_json::to_string(&tags)
         .map_err(|e| format!("Failed to serialize tags: {}", e))?;
@@ -3112,12 +3636,38 @@ fn remove_custom_tag(app: AppHandle, path: String, tag: String) -> Result<Vec<St
     Ok(tags)
 }
 
+#[derive(Serialize)]
+struct TagCount {
+    tag: String,
+    count: usize,
+}
+
+// Auto and custom tags together, most used first
+fn tag_counts(conn: &Connection) -> Result<Vec<TagCount>, String> {
+    let mut stmt = conn.prepare("SELECT tags, custom_tags FROM entries WHERE COALESCE(trashed, 0) = 0")
+        .map_err(|e| format!("Query error: {}", e))?;
+    let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(|e| format!("Query map error: {}", e))?;
+
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for row in rows {
+        let (tags, custom_tags) = row.map_err(|e| format!("Row error: {}", e))?;
+        let entry_tags: HashSet<String> = parse_json_list(tags).into_iter().chain(parse_json_list(custom_tags)).collect();
+        for tag in entry_tags {
+            *counts.entry(tag).or_default() += 1;
+        }
+    }
+    let mut counts: Vec<TagCount> = counts.into_iter().map(|(tag, count)| TagCount { tag, count }).collect();
+    counts.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.tag.cmp(&b.tag)));
+    Ok(counts)
+}
+
 #[tauri::command]
 fn get_all_custom_tags(app: AppHandle) -> Result<Vec<String>, String> {
     let conn = init_database(&app)
         .map_err(|e| format!("Database error: {}", e))?;
     
-    let mut stmt = conn.prepare("SELECT custom_tags FROM entries WHERE custom_tags IS NOT NULL AND custom_tags != '[]'")
+    let mut stmt = conn.prepare("SELECT custom_tags FROM entries WHERE custom_tags IS NOT NULL AND custom_tags != '[]' AND COALESCE(trashed, 0) = 0")
         .map_err(|e| format!("Failed to prepare query: {}", e))?;
     
     let rows = stmt.query_map([], |row| {
@@ -3147,9 +3697,10 @@ fn get_all_custom_tags(app: AppHandle) -> Result<Vec<String>, String> {
 fn reprocess_all_with_visual(app: AppHandle) -> Result<usize, String> {
     let conn = init_database(&app)
         .map_err(|e| format!("DB error: {}", e))?;
+    let indexer = indexer(&app);
     
     // Get ALL entries to reprocess with visual classification
-    let mut stmt = conn.prepare("SELECT path, text FROM entries")
+    let mut stmt = conn.prepare("SELECT path, text FROM entries WHERE COALESCE(trashed, 0) = 0")
         .map_err(|e| format!("Query error: {}", e))?;
     
     let rows = stmt.query_map([], |row| {
@@ -3163,7 +3714,7 @@ fn reprocess_all_with_visual(app: AppHandle) -> Result<usize, String> {
         
         // Reprocess with visual classification (will update tags if better match found)
         // This function guarantees every entry gets at least one tag
-        process_tags_for_entry(&app, &path, &text);
+        indexer.process_tags(&path, &text);
         processed += 1;
         
         if processed % 10 == 0 {
@@ -3180,8 +3731,8 @@ fn compute_missing_hashes(app: AppHandle) -> Result<usize, String> {
     let conn = init_database(&app)
         .map_err(|e| format!("DB error: {}", e))?;
     
-    // Get all entries without perceptual hashes
-    let mut stmt = conn.prepare("SELECT path FROM entries WHERE perceptual_hash IS NULL")
+    // Get all entries missing any of the perceptual hashes
+    let mut stmt = conn.prepare("SELECT path FROM entries WHERE (perceptual_hash IS NULL OR phash IS NULL OR block_hash IS NULL) AND COALESCE(trashed, 0) = 0")
         .map_err(|e| format!("Query error: {}", e))?;
     
     let rows = stmt.query_map([], |row| {
@@ -3193,16 +3744,19 @@ fn compute_missing_hashes(app: AppHandle) -> Result<usize, String> {
         let path_str = row.map_err(|e| format!("Row error: {}", e))?;
         let path = Path::new(&path_str);
         
-        // Compute perceptual hash
-        match compute_perceptual_hash(path) {
-            Ok(hash_bytes) => {
-                // Update the entry with the hash
+        // Compute perceptual hashes
+        match compute_perceptual_hash(path).and_then(|hash| {
+            compute_similarity_hashes(path).map(|(phash, block_hash)| (hash, phash, block_hash))
+        }) {
+            Ok((hash_bytes, phash, block_hash)) => {
+                // Update the entry with the hashes
                 if let Err(e) = conn.execute(
-                    "UPDATE entries SET perceptual_hash = ?1 WHERE path = ?2",
-                    rusqlite::params![hash_bytes, path_str]
+                    "UPDATE entries SET perceptual_hash = ?1, phash = ?2, block_hash = ?3 WHERE path = ?4",
+                    rusqlite::params![hash_bytes, phash, block_hash, path_str]
                 ) {
                     eprintln!("[HASH] Failed to update {}: {}", path_str, e);
                 } else {
+                    index_hash(&app, &path_str, &hash_bytes);
                     computed += 1;
                     if computed % 10 == 0 {
                         println!("[HASH] Computed {} hashes...", computed);
@@ -3218,3 +3772,2886 @@ fn compute_missing_hashes(app: AppHandle) -> Result<usize, String> {
     println!("[HASH] ✅ Computed {} perceptual hashes", computed);
     Ok(computed)
 }
+
+// ============== SEMANTIC SEARCH ==============
+
+// Weight of the vector score in hybrid mode (the rest goes to BM25)
+const HYBRID_VECTOR_WEIGHT: f32 = 0.6;
+
+#[derive(Serialize)]
+struct SearchHit {
+    #[serde(flatten)]
+    entry: DbEntry,
+    score: f32,
+}
+
+// BM25 keyword matches from the FTS index, best first
+// Scores are flipped so higher is better (SQLite's bm25() is lower-is-better)
+fn bm25_search(conn: &Connection, query: &str, limit: usize) -> Result<Vec<(String, f32)>, String> {
+    // Quote every term so user input can't be parsed as FTS syntax; any term may match
+    let fts_query = query
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|term| !term.is_empty())
+        .map(|term| format!("\"{}\"", term))
+        .collect::<Vec<_>>()
+        .join(" OR ");
+    if fts_query.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut stmt = conn
+        .prepare("SELECT path, -bm25(entries_fts) FROM entries_fts WHERE entries_fts MATCH ?1 ORDER BY bm25(entries_fts) LIMIT ?2")
+        .map_err(|e| format!("Query error: {}", e))?;
+    let rows = stmt
+        .query_map(rusqlite::params![fts_query, limit as i64], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, f64>(1)? as f32))
+        })
+        .map_err(|e| format!("Query map error: {}", e))?;
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Row error: {}", e))
+}
+
+fn load_entry_by_path(conn: &Connection, path: &str) -> Option<DbEntry> {
+    conn.query_row(
+        "SELECT path, text, created_at, tags, urls, emails, pinned, custom_tags, COALESCE(sensitive, 0) FROM entries WHERE path = ?1 AND COALESCE(trashed, 0) = 0",
+        rusqlite::params![path],
+        |row| {
+            Ok(DbEntry {
+                path: row.get(0)?,
+                text: row.get(1)?,
+                at: row.get(2)?,
+                tags: row.get(3).ok(),
+                urls: row.get(4).ok(),
+                emails: row.get(5).ok(),
+                pinned: row.get::<_, i64>(6).unwrap_or(0) == 1,
+                custom_tags: row.get(7).ok(),
+                sensitive: row.get::<_, i64>(8).unwrap_or(0) == 1,
+            })
+        },
+    )
+    .ok()
+}
+
+// Scale scores into 0..1 by the best score in the list
+fn normalize_scores(scores: &[(String, f32)]) -> HashMap<String, f32> {
+    let max = scores.iter().map(|(_, s)| *s).fold(0.0f32, f32::max);
+    scores
+        .iter()
+        .map(|(path, score)| (path.clone(), if max > 0.0 { score.max(0.0) / max } else { 0.0 }))
+        .collect()
+}
+
+#[tauri::command]
+fn semantic_search(app: AppHandle, query: String, k: Option<usize>, hybrid: Option<bool>) -> Result<Vec<SearchHit>, String> {
+    let conn = init_database(&app)
+        .map_err(|e| format!("Database error: {}", e))?;
+    ranked_search(&data_dir(&app), &conn, &query, k.unwrap_or(20).max(1), hybrid.unwrap_or(false), false)
+}
+
+// Both rankers score the OCR text, so with withhold_sensitive set, sensitive entries are left out
+// entirely rather than letting other programs (MCP, the local API) confirm a guess by rank
+fn ranked_search(data_dir: &Path, conn: &Connection, query: &str, k: usize, hybrid: bool, withhold_sensitive: bool) -> Result<Vec<SearchHit>, String> {
+    let pool = k * 4;
+    let ranked: Vec<(String, f32)> = if hybrid {
+        // Pull a wider candidate pool from both rankers, then merge normalized scores
+        let keyword = bm25_search(conn, query, pool)?;
+        let vector = match embeddings::vector_search(data_dir, conn, query, pool) {
+            Ok(results) => results,
+            Err(e) => {
+                println!("[SEARCH] Vector search unavailable ({}), using BM25 only", e);
+                Vec::new()
+            }
+        };
+
+        let keyword_scores = normalize_scores(&keyword);
+        let vector_scores = normalize_scores(&vector);
+        let vector_weight = if vector.is_empty() { 0.0 } else { HYBRID_VECTOR_WEIGHT };
+
+        let candidates: HashSet<&String> = keyword_scores.keys().chain(vector_scores.keys()).collect();
+        let mut merged: Vec<(String, f32)> = candidates
+            .into_iter()
+            .map(|path| {
+                let keyword_score = keyword_scores.get(path).copied().unwrap_or(0.0);
+                let vector_score = vector_scores.get(path).copied().unwrap_or(0.0);
+                (path.clone(), vector_weight * vector_score + (1.0 - vector_weight) * keyword_score)
+            })
+            .collect();
+        merged.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+        merged
+    } else {
+        embeddings::vector_search(data_dir, conn, query, if withhold_sensitive { pool } else { k })?
+    };
+
+    let hits: Vec<SearchHit> = ranked
+        .into_iter()
+        .filter_map(|(path, score)| load_entry_by_path(conn, &path).map(|entry| SearchHit { entry, score }))
+        .filter(|hit| !(withhold_sensitive && hit.entry.sensitive))
+        .take(k)
+        .collect();
+
+    println!("[SEARCH] ✅ {} search for {:?} returned {} results",
+             if hybrid { "Hybrid" } else { "Semantic" }, query, hits.len());
+    Ok(hits)
+}
+
+#[tauri::command]
+fn compute_missing_embeddings(app: AppHandle) -> Result<usize, String> {
+    let conn = init_database(&app)
+        .map_err(|e| format!("DB error: {}", e))?;
+
+    let data_dir = data_dir(&app);
+    let missing = embeddings::missing_entries(&data_dir, &conn)?;
+    let mut computed = 0;
+    for (path, text) in missing {
+        match embeddings::index_entry(&data_dir, &conn, &path, &text) {
+            Ok(true) => {
+                computed += 1;
+                if computed % 10 == 0 {
+                    println!("[EMBED] Computed {} embeddings...", computed);
+                }
+            }
+            Ok(false) => {}
+            Err(e) => eprintln!("[EMBED] Failed to embed {}: {}", path, e),
+        }
+    }
+
+    println!("[EMBED] ✅ Computed {} embeddings", computed);
+    Ok(computed)
+}
+
+// ============== SETTINGS ==============
+
+fn read_setting<T: serde::de::DeserializeOwned>(conn: &Connection, key: &str) -> Option<T> {
+    let json: String = conn.query_row(
+        "SELECT value FROM settings WHERE key = ?1",
+        rusqlite::params![key],
+        |row| row.get(0),
+    ).ok()?;
+    serde_json::from_str(&json).ok()
+}
+
+fn write_setting<T: Serialize>(conn: &Connection, key: &str, value: &T) -> Result<(), String> {
+    let json = serde_json::to_string(value)
+        .map_err(|e| format!("Failed to serialize setting: {}", e))?;
+    conn.execute(
+        "INSERT OR REPLACE INTO settings (key, value) VALUES (?1, ?2)",
+        rusqlite::params![key, json],
+    ).map_err(|e| format!("Failed to save setting: {}", e))?;
+    Ok(())
+}
+
+#[tauri::command]
+fn get_setting(app: AppHandle, key: String) -> Result<Option<serde_json::Value>, String> {
+    let conn = init_database(&app)
+        .map_err(|e| format!("Database error: {}", e))?;
+    Ok(read_setting(&conn, &key))
+}
+
+#[tauri::command]
+fn set_setting(app: AppHandle, key: String, value: serde_json::Value) -> Result<(), String> {
+    let conn = init_database(&app)
+        .map_err(|e| format!("Database error: {}", e))?;
+    if key == RENAME_TEMPLATE_KEY {
+        let template = value.as_str()
+            .ok_or_else(|| "Rename template must be a string".to_string())?;
+        rename::Template::parse(template)?;
+    }
+    if key == EXCLUSION_RULES_KEY {
+        let rules: Vec<exclusions::ExclusionRule> = serde_json::from_value(value.clone())
+            .map_err(|e| format!("Invalid exclusion rules: {}", e))?;
+        exclusions::Rules::compile(&rules)?;
+    }
+    if key == OTP_EXPIRY_ACTION_KEY && !matches!(value.as_str(), Some("off" | "trash" | "redact")) {
+        return Err("OTP expiry action must be \"off\", \"trash\" or \"redact\"".to_string());
+    }
+    if key == LIBRARY_DIR_KEY {
+        let requested = validate_library_dir(&value)?;
+        move_library(&app, &conn, requested)?;
+    }
+    write_setting(&conn, &key, &value)?;
+    if key == LIBRARY_DIR_KEY {
+        allow_library_assets(&app);
+    }
+    println!("[SETTINGS] ✅ Updated {}", key);
+    Ok(())
+}
+
+// ============== TRASH ==============
+
+// Days a screenshot stays in the trash before it's deleted for good (0 = never)
+const TRASH_RETENTION_DAYS_KEY: &str = "trash_retention_days";
+const DEFAULT_TRASH_RETENTION_DAYS: u64 = 30;
+
+// Watcher ignore list, shared so commands can move files into watch dirs without re-indexing them
+#[derive(Default)]
+struct WatcherIgnoreState(Arc<Mutex<HashMap<PathBuf, Instant>>>);
+
+#[derive(Serialize)]
+struct RestoreResult {
+    restored: Vec<String>,
+    failed: Vec<String>,
+}
+
+fn trash_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app.path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to get app data directory: {}", e))?
+        .join("trash");
+    fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create trash directory: {}", e))?;
+    Ok(dir)
+}
+
+// Move a file, falling back to copy + delete across filesystems
+fn move_file(from: &Path, to: &Path) -> Result<(), String> {
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    fs::copy(from, to)
+        .map_err(|e| format!("Failed to copy {} to {}: {}", from.display(), to.display(), e))?;
+    fs::remove_file(from)
+        .map_err(|e| format!("Failed to remove {}: {}", from.display(), e))
+}
+
+fn now_millis_string() -> String {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis()
+        .to_string()
+}
+
+// Local "YYYY-MM-DD HH:MM" for a created_at value
+fn format_millis(millis: &str) -> Option<String> {
+    use chrono::{Local, TimeZone};
+    let millis: i64 = millis.parse().ok()?;
+    Local.timestamp_millis_opt(millis).single().map(|date| date.format("%Y-%m-%d %H:%M").to_string())
+}
+
+fn move_to_trash(app: &AppHandle, path: &Path) -> Result<PathBuf, String> {
+    let file_name = path.file_name()
+        .and_then(|n| n.to_str())
+        .ok_or_else(|| format!("Invalid file name: {}", path.display()))?;
+    // Timestamp prefix keeps same-named screenshots from different folders apart
+    let trash_path = trash_dir(app)?.join(format!("{}-{}", now_millis_string(), file_name));
+    move_file(path, &trash_path)?;
+    println!("[TRASH] Moved {} to trash", path.display());
+    Ok(trash_path)
+}
+
+// Deleted screenshots go to the trash first so they can be restored, and the DB row is kept
+// (marked trashed) with its text and tags. Sensitive ones are shredded and dropped right away,
+// as purging the trash would, so their pixels and text don't wait out the retention period
+fn delete_or_trash(app: &AppHandle, path: &str, file: &Path) -> Result<(), String> {
+    let sensitive = init_database(app)
+        .map(|conn| is_sensitive_entry(&conn, path))
+        .unwrap_or(false);
+    if !sensitive {
+        let trash_path = move_to_trash(app, file)?;
+        mark_trashed(app, path, &trash_path);
+        return Ok(());
+    }
+    shred_file(file).map_err(|e| format!("Failed to delete {}: {}", file.display(), e))?;
+    println!("[TRASH] Shredded {} (sensitive)", file.display());
+    delete_entry_from_db(app, path)
+        .map_err(|e| format!("Failed to delete entry: {}", e))
+}
+
+fn mark_trashed(app: &AppHandle, path: &str, trash_path: &Path) {
+    let result = init_database(app).and_then(|conn| {
+        conn.execute(
+            "UPDATE entries SET trashed = 1, trashed_at = ?1, trash_path = ?2 WHERE path = ?3",
+            rusqlite::params![now_millis_string(), trash_path.to_string_lossy(), path],
+        )
+    });
+    match result {
+        Ok(0) => eprintln!("[TRASH] ⚠️ {} was not indexed; it can't be restored from the app", path),
+        Ok(_) => {}
+        Err(e) => eprintln!("[TRASH] Failed to mark {} as trashed: {}", path, e),
+    }
+    unindex_hash(app, path);
+}
+
+// Permanently remove a trashed entry: its file in the trash and its DB row
+fn purge_trashed_entry(app: &AppHandle, path: &str, trash_path: Option<&str>) -> Result<(), String> {
+    let sensitive = init_database(app)
+        .map(|conn| is_sensitive_entry(&conn, path))
+        .unwrap_or(false);
+    if let Some(trash_path) = trash_path {
+        let removed = if sensitive {
+            shred_file(Path::new(trash_path))
+        } else {
+            fs::remove_file(trash_path)
+        };
+        match removed {
+            Ok(_) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(format!("Failed to delete {}: {}", trash_path, e)),
+        }
+    }
+    delete_entry_from_db(app, path)
+        .map_err(|e| format!("Failed to delete entry: {}", e))
+}
+
+// (path, trash_path) for trashed entries, optionally only those trashed before a cutoff (ms)
+fn load_trashed(conn: &Connection, trashed_before: Option<u64>) -> Result<Vec<(String, Option<String>)>, String> {
+    let mut stmt = conn.prepare(
+        "SELECT path, trash_path FROM entries
+         WHERE trashed = 1 AND (?1 IS NULL OR CAST(trashed_at AS INTEGER) < ?1)"
+    ).map_err(|e| format!("Query error: {}", e))?;
+    let rows = stmt.query_map(rusqlite::params![trashed_before.map(|t| t as i64)], |row| {
+        Ok((row.get(0)?, row.get(1)?))
+    }).map_err(|e| format!("Query map error: {}", e))?;
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Row error: {}", e))
+}
+
+#[tauri::command]
+fn get_trashed_entries(app: AppHandle) -> Result<Vec<DbEntry>, String> {
+    let conn = init_database(&app)
+        .map_err(|e| format!("Database error: {}", e))?;
+    
+    let mut stmt = conn.prepare("SELECT path, text, created_at, tags, urls, emails, pinned, custom_tags, COALESCE(sensitive, 0) FROM entries WHERE trashed = 1 ORDER BY trashed_at DESC")
+        .map_err(|e| format!("Failed to prepare query: {}", e))?;
+    
+    let entries = stmt.query_map([], |row| {
+        Ok(DbEntry {
+            path: row.get(0)?,
+            text: row.get(1)?,
+            at: row.get(2)?,
+            tags: row.get(3).ok(),
+            urls: row.get(4).ok(),
+            emails: row.get(5).ok(),
+            pinned: row.get::<_, i64>(6).unwrap_or(0) == 1,
+            custom_tags: row.get(7).ok(),
+            sensitive: row.get::<_, i64>(8).unwrap_or(0) == 1,
+        })
+    })
+    .map_err(|e| format!("Failed to query: {}", e))?
+    .filter_map(|r| r.ok())
+    .collect::<Vec<_>>();
+    
+    println!("[TRASH] ✅ Loaded {} trashed entries", entries.len());
+    Ok(entries)
+}
+
+#[tauri::command]
+fn restore_from_trash(app: AppHandle, paths: Vec<String>) -> Result<RestoreResult, String> {
+    let conn = init_database(&app)
+        .map_err(|e| format!("Database error: {}", e))?;
+    let ignore_map = Arc::clone(&app.state::<WatcherIgnoreState>().0);
+    
+    let mut restored = Vec::new();
+    let mut failed = Vec::new();
+    
+    for path_str in paths {
+        let trash_path: Option<String> = conn.query_row(
+            "SELECT trash_path FROM entries WHERE path = ?1 AND trashed = 1",
+            rusqlite::params![path_str],
+            |row| row.get(0),
+        ).ok().flatten();
+        
+        let Some(trash_path) = trash_path else {
+            eprintln!("[TRASH] {} is not in the trash", path_str);
+            failed.push(path_str);
+            continue;
+        };
+        
+        let original = PathBuf::from(&path_str);
+        if original.exists() {
+            eprintln!("[TRASH] Can't restore {}: a file already exists there", path_str);
+            failed.push(path_str);
+            continue;
+        }
+        if let Some(parent) = original.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        
+        // The entry keeps its text and tags - don't let the watcher OCR it again
+        remember_ignore(&ignore_map, &original);
+        if let Err(e) = move_file(Path::new(&trash_path), &original) {
+            eprintln!("[TRASH] Failed to restore {}: {}", path_str, e);
+            failed.push(path_str);
+            continue;
+        }
+        
+        if let Err(e) = conn.execute(
+            "UPDATE entries SET trashed = 0, trashed_at = NULL, trash_path = NULL WHERE path = ?1",
+            rusqlite::params![path_str],
+        ) {
+            eprintln!("[TRASH] Failed to update {}: {}", path_str, e);
+        }
+        let hash: Option<Vec<u8>> = conn.query_row(
+            "SELECT perceptual_hash FROM entries WHERE path = ?1",
+            rusqlite::params![path_str],
+            |row| row.get(0),
+        ).ok().flatten();
+        if let Some(hash) = hash {
+            index_hash(&app, &path_str, &hash);
+        }
+        
+        println!("[TRASH] ✅ Restored {}", path_str);
+        restored.push(path_str);
+    }
+    
+    Ok(RestoreResult { restored, failed })
+}
+
+#[tauri::command]
+fn empty_trash(app: AppHandle) -> Result<usize, String> {
+    let conn = init_database(&app)
+        .map_err(|e| format!("Database error: {}", e))?;
+    
+    let mut purged = 0;
+    for (path, trash_path) in load_trashed(&conn, None)? {
+        match purge_trashed_entry(&app, &path, trash_path.as_deref()) {
+            Ok(_) => purged += 1,
+            Err(e) => eprintln!("[TRASH] {}", e),
+        }
+    }
+    
+    println!("[TRASH] ✅ Emptied trash ({} screenshots)", purged);
+    Ok(purged)
+}
+
+fn purge_expired_trash(app: &AppHandle) -> Result<usize, String> {
+    let conn = init_database(app)
+        .map_err(|e| format!("Database error: {}", e))?;
+    let days: u64 = read_setting(&conn, TRASH_RETENTION_DAYS_KEY).unwrap_or(DEFAULT_TRASH_RETENTION_DAYS);
+    if days == 0 {
+        return Ok(0);
+    }
+    
+    let now_ms = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as u64;
+    let cutoff = now_ms.saturating_sub(days * 24 * 60 * 60 * 1000);
+    
+    let mut purged = 0;
+    for (path, trash_path) in load_trashed(&conn, Some(cutoff))? {
+        match purge_trashed_entry(app, &path, trash_path.as_deref()) {
+            Ok(_) => purged += 1,
+            Err(e) => eprintln!("[TRASH] {}", e),
+        }
+    }
+    
+    if purged > 0 {
+        println!("[TRASH] ✅ Purged {} screenshots trashed more than {} days ago", purged, days);
+    }
+    Ok(purged)
+}
+
+// ============== QUERY FILTERS ==============
+
+fn parse_json_list(json: Option<String>) -> Vec<String> {
+    json.and_then(|s| serde_json::from_str(&s).ok()).unwrap_or_default()
+}
+
+// Entity kinds (phone, iban, ...) per entry, for has: filters
+fn load_entity_kinds(conn: &Connection) -> Result<HashMap<String, HashSet<String>>, String> {
+    let mut stmt = conn.prepare("SELECT DISTINCT path, kind FROM entities")
+        .map_err(|e| format!("Query error: {}", e))?;
+    let rows = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))
+        .map_err(|e| format!("Query map error: {}", e))?;
+    let mut kinds: HashMap<String, HashSet<String>> = HashMap::new();
+    for row in rows {
+        let (path, kind) = row.map_err(|e| format!("Row error: {}", e))?;
+        kinds.entry(path).or_default().insert(kind);
+    }
+    Ok(kinds)
+}
+
+#[derive(Serialize)]
+struct EntityRecord {
+    kind: String,
+    value: String,
+    start: i64,
+    end: i64,
+}
+
+// Entities found in one screenshot's text, in reading order
+#[tauri::command]
+fn get_entities(app: AppHandle, path: String) -> Result<Vec<EntityRecord>, String> {
+    let conn = init_database(&app)
+        .map_err(|e| format!("Database error: {}", e))?;
+    load_entities(&conn, &path)
+}
+
+#[derive(Serialize)]
+struct EntryDetails {
+    #[serde(flatten)]
+    entry: DbEntry,
+    entities: Vec<EntityRecord>,
+}
+
+// One entry with its entities, for the CLI and the local API
+fn load_entry_details(conn: &Connection, path: &str) -> Result<EntryDetails, String> {
+    let entry = load_entry_by_path(conn, path).ok_or_else(|| format!("{} isn't indexed", path))?;
+    let entities = load_entities(conn, path)?;
+    Ok(EntryDetails { entry, entities })
+}
+
+fn load_entities(conn: &Connection, path: &str) -> Result<Vec<EntityRecord>, String> {
+    let mut stmt = conn.prepare(
+        "SELECT kind, value, span_start, span_end FROM entities WHERE path = ?1 ORDER BY span_start"
+    ).map_err(|e| format!("Query error: {}", e))?;
+    let rows = stmt.query_map(rusqlite::params![path], |row| {
+        Ok(EntityRecord { kind: row.get(0)?, value: row.get(1)?, start: row.get(2)?, end: row.get(3)? })
+    }).map_err(|e| format!("Query map error: {}", e))?;
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Row error: {}", e))
+}
+
+// Paths of (non-trashed) entries matching a query-language filter, newest first
+fn query_entry_paths(conn: &Connection, query: &query::Query) -> Result<Vec<String>, String> {
+    match_entry_paths(conn, query, false)
+}
+
+// Same, for other programs (MCP, the local API): the text and entities of sensitive entries
+// never match, so a search can't confirm a guessed code or key
+fn query_shared_entry_paths(conn: &Connection, query: &query::Query) -> Result<Vec<String>, String> {
+    match_entry_paths(conn, query, true)
+}
+
+fn match_entry_paths(conn: &Connection, query: &query::Query, withhold_sensitive: bool) -> Result<Vec<String>, String> {
+    let mut stmt = conn.prepare(
+        "SELECT path, text, created_at, tags, custom_tags, COALESCE(pinned, 0), urls, emails, COALESCE(sensitive, 0)
+         FROM entries WHERE COALESCE(trashed, 0) = 0 ORDER BY created_at DESC"
+    ).map_err(|e| format!("Query error: {}", e))?;
+    
+    let rows = stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, String>(2)?,
+            row.get::<_, Option<String>>(3)?,
+            row.get::<_, Option<String>>(4)?,
+            row.get::<_, i64>(5)?,
+            row.get::<_, Option<String>>(6)?,
+            row.get::<_, Option<String>>(7)?,
+            row.get::<_, i64>(8)?,
+        ))
+    }).map_err(|e| format!("Query map error: {}", e))?;
+    
+    let mut entity_kinds = load_entity_kinds(conn)?;
+    let now_ms = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as u64;
+    let mut paths = Vec::new();
+    for row in rows {
+        let (path, mut text, created_at, tags, custom_tags, pinned, urls, emails, sensitive) =
+            row.map_err(|e| format!("Row error: {}", e))?;
+        let withheld = withhold_sensitive && sensitive == 1;
+        if withheld {
+            text.clear();
+        }
+        let tags = parse_json_list(tags);
+        let custom_tags = parse_json_list(custom_tags);
+        let mut has = entity_kinds.remove(&path).unwrap_or_default();
+        if !parse_json_list(urls).is_empty() {
+            has.insert("url".to_string());
+        }
+        if !parse_json_list(emails).is_empty() {
+            has.insert("email".to_string());
+        }
+        if withheld {
+            has.clear();
+        }
+        
+        let facts = query::EntryFacts {
+            path: &path,
+            text: &text,
+            tags: &tags,
+            custom_tags: &custom_tags,
+            pinned: pinned == 1,
+            sensitive: sensitive == 1,
+            created_at_ms: created_at.parse().unwrap_or(0),
+            has: &has,
+        };
+        if query.matches(&facts, now_ms) {
+            paths.push(path);
+        }
+    }
+    Ok(paths)
+}
+
+// ============== RETENTION ==============
+
+// What a retention rule does with the screenshots its query matches
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum RetentionAction {
+    // Move every match to the trash
+    Delete,
+    // Among the matches, keep only the newest screenshot of each near-duplicate group
+    KeepNewestDuplicate,
+    // Move every match into the library
+    Archive,
+}
+
+impl RetentionAction {
+    fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "delete" => Ok(RetentionAction::Delete),
+            "keep_newest_duplicate" => Ok(RetentionAction::KeepNewestDuplicate),
+            "archive" => Ok(RetentionAction::Archive),
+            other => Err(format!("Unknown retention action '{}' (expected delete, keep_newest_duplicate or archive)", other)),
+        }
+    }
+}
+
+#[derive(Clone, Serialize)]
+struct RetentionRule {
+    id: i64,
+    name: String,
+    query: String,
+    action: String,
+    enabled: bool,
+    previewed_at: Option<String>,
+    approved_at: Option<String>, // Rules only run after their preview was approved
+    last_run_at: Option<String>,
+    created_at: String,
+}
+
+#[derive(Serialize)]
+struct RetentionPreview {
+    rule_id: i64,
+    matched: usize,
+    affected: Vec<String>, // Screenshots the rule would trash (or archive)
+}
+
+#[derive(Serialize)]
+struct RetentionRun {
+    processed: Vec<String>, // Trashed or archived
+    failed: Vec<String>,
+}
+
+fn load_retention_rules(conn: &Connection, id: Option<i64>) -> Result<Vec<RetentionRule>, String> {
+    let mut stmt = conn.prepare(
+        "SELECT id, name, query, action, enabled, previewed_at, approved_at, last_run_at, created_at
+         FROM retention_rules WHERE ?1 IS NULL OR id = ?1 ORDER BY id"
+    ).map_err(|e| format!("Failed to prepare query: {}", e))?;
+    
+    let rows = stmt.query_map(rusqlite::params![id], |row| {
+        Ok(RetentionRule {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            query: row.get(2)?,
+            action: row.get(3)?,
+            enabled: row.get::<_, i64>(4)? == 1,
+            previewed_at: row.get(5)?,
+            approved_at: row.get(6)?,
+            last_run_at: row.get(7)?,
+            created_at: row.get(8)?,
+        })
+    }).map_err(|e| format!("Failed to query: {}", e))?;
+    
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Row error: {}", e))
+}
+
+fn load_retention_rule(conn: &Connection, id: i64) -> Result<RetentionRule, String> {
+    load_retention_rules(conn, Some(id))?
+        .pop()
+        .ok_or_else(|| format!("Retention rule {} not found", id))
+}
+
+// Validate a rule's query + action; an empty query would match the whole library
+fn validate_retention_rule(query_str: &str, action: &str) -> Result<(query::Query, RetentionAction), String> {
+    let query = query::Query::parse(query_str)?;
+    if query.is_empty() {
+        return Err("A retention rule needs a query".to_string());
+    }
+    Ok((query, RetentionAction::parse(action)?))
+}
+
+// Screenshots a rule would trash (or archive) right now
+fn plan_retention_rule(app: &AppHandle, conn: &Connection, rule: &RetentionRule) -> Result<(usize, Vec<String>), String> {
+    let (query, action) = validate_retention_rule(&rule.query, &rule.action)?;
+    let matched = query_entry_paths(conn, &query)?;
+    
+    let affected = match action {
+        RetentionAction::Delete => matched.clone(),
+        // Already-archived screenshots stay where they are
+        RetentionAction::Archive => {
+            let watch_dirs = resolve_watch_dirs();
+            matched.iter()
+                .filter(|path| is_within_dirs(Path::new(path), &watch_dirs))
+                .cloned()
+                .collect()
+        }
+        RetentionAction::KeepNewestDuplicate => {
+            let matched_set: HashSet<&String> = matched.iter().collect();
+            let hashes: HashMap<String, Vec<u8>> = load_perceptual_hashes(conn)?
+                .into_iter()
+                .filter(|(path, _)| matched_set.contains(path))
+                .collect();
+            let mut tree = hash_index::BkTree::default();
+            for (path, hash) in &hashes {
+                tree.insert(path, hash.clone());
+            }
+            
+            // Same selection as a manual "keep newest" resolution (pinned screenshots are kept)
+            let mut affected = Vec::new();
+            for group in hash_index::cluster(&tree, &hashes, DEFAULT_DUPLICATE_THRESHOLD, hash_index::Linkage::Single) {
+                let plan = resolve_duplicates(app.clone(), group, "newest".to_string(), Some(false), Some(true))?;
+                affected.extend(plan.delete);
+            }
+            affected
+        }
+    };
+    
+    Ok((matched.len(), affected))
+}
+
+#[tauri::command]
+fn create_retention_rule(app: AppHandle, name: String, query: String, action: String) -> Result<RetentionRule, String> {
+    validate_retention_rule(&query, &action)?;
+    let conn = init_database(&app)
+        .map_err(|e| format!("Database error: {}", e))?;
+    
+    conn.execute(
+        "INSERT INTO retention_rules (name, query, action, enabled, created_at) VALUES (?1, ?2, ?3, 1, ?4)",
+        rusqlite::params![name, query, action, now_millis_string()],
+    ).map_err(|e| format!("Failed to save retention rule: {}", e))?;
+    
+    let rule = load_retention_rule(&conn, conn.last_insert_rowid())?;
+    println!("[RETENTION] ✅ Created rule '{}' ({}): {}", rule.name, rule.action, rule.query);
+    Ok(rule)
+}
+
+#[tauri::command]
+fn list_retention_rules(app: AppHandle) -> Result<Vec<RetentionRule>, String> {
+    let conn = init_database(&app)
+        .map_err(|e| format!("Database error: {}", e))?;
+    load_retention_rules(&conn, None)
+}
+
+// Changing what a rule matches or does invalidates its preview and approval
+#[tauri::command]
+fn update_retention_rule(
+    app: AppHandle,
+    id: i64,
+    name: Option<String>,
+    query: Option<String>,
+    action: Option<String>,
+    enabled: Option<bool>,
+) -> Result<RetentionRule, String> {
+    let conn = init_database(&app)
+        .map_err(|e| format!("Database error: {}", e))?;
+    let rule = load_retention_rule(&conn, id)?;
+    
+    let new_query = query.unwrap_or_else(|| rule.query.clone());
+    let new_action = action.unwrap_or_else(|| rule.action.clone());
+    validate_retention_rule(&new_query, &new_action)?;
+    let changed = new_query != rule.query || new_action != rule.action;
+    
+    conn.execute(
+        "UPDATE retention_rules SET name = ?1, query = ?2, action = ?3, enabled = ?4,
+             previewed_at = CASE WHEN ?5 THEN NULL ELSE previewed_at END,
+             approved_at = CASE WHEN ?5 THEN NULL ELSE approved_at END
+         WHERE id = ?6",
+        rusqlite::params![
+            name.unwrap_or(rule.name),
+            new_query,
+            new_action,
+            enabled.unwrap_or(rule.enabled) as i64,
+            changed,
+            id
+        ],
+    ).map_err(|e| format!("Failed to update retention rule: {}", e))?;
+    
+    println!("[RETENTION] ✅ Updated rule {}", id);
+    load_retention_rule(&conn, id)
+}
+
+#[tauri::command]
+fn delete_retention_rule(app: AppHandle, id: i64) -> Result<(), String> {
+    let conn = init_database(&app)
+        .map_err(|e| format!("Database error: {}", e))?;
+    conn.execute("DELETE FROM retention_rules WHERE id = ?1", rusqlite::params![id])
+        .map_err(|e| format!("Failed to delete retention rule: {}", e))?;
+    println!("[RETENTION] ✅ Deleted rule {}", id);
+    Ok(())
+}
+
+#[tauri::command]
+fn preview_retention_rule(app: AppHandle, id: i64) -> Result<RetentionPreview, String> {
+    let conn = init_database(&app)
+        .map_err(|e| format!("Database error: {}", e))?;
+    let rule = load_retention_rule(&conn, id)?;
+    let (matched, affected) = plan_retention_rule(&app, &conn, &rule)?;
+    
+    conn.execute(
+        "UPDATE retention_rules SET previewed_at = ?1 WHERE id = ?2",
+        rusqlite::params![now_millis_string(), id],
+    ).map_err(|e| format!("Failed to record preview: {}", e))?;
+    
+    println!("[RETENTION] Preview of '{}': {} matched, {} affected", rule.name, matched, affected.len());
+    Ok(RetentionPreview { rule_id: id, matched, affected })
+}
+
+#[tauri::command]
+fn approve_retention_rule(app: AppHandle, id: i64) -> Result<RetentionRule, String> {
+    let conn = init_database(&app)
+        .map_err(|e| format!("Database error: {}", e))?;
+    let rule = load_retention_rule(&conn, id)?;
+    if rule.previewed_at.is_none() {
+        return Err("Preview the rule before approving it".to_string());
+    }
+    
+    conn.execute(
+        "UPDATE retention_rules SET approved_at = ?1 WHERE id = ?2",
+        rusqlite::params![now_millis_string(), id],
+    ).map_err(|e| format!("Failed to approve rule: {}", e))?;
+    
+    println!("[RETENTION] ✅ Approved rule '{}'", rule.name);
+    load_retention_rule(&conn, id)
+}
+
+// Run one rule now; goes through delete_files / archive_entries like a manual action
+// (watch-dir check, trash)
+fn execute_retention_rule(app: &AppHandle, conn: &Connection, rule: &RetentionRule) -> Result<RetentionRun, String> {
+    if rule.approved_at.is_none() {
+        return Err(format!("Rule '{}' has not been previewed and approved", rule.name));
+    }
+    
+    let (_, affected) = plan_retention_rule(app, conn, rule)?;
+    let result = if affected.is_empty() {
+        RetentionRun { processed: vec![], failed: vec![] }
+    } else if RetentionAction::parse(&rule.action)? == RetentionAction::Archive {
+        let archived = archive_entries(app.clone(), affected)?;
+        RetentionRun {
+            processed: archived.archived.into_iter().map(|entry| entry.from).collect(),
+            failed: archived.failed,
+        }
+    } else {
+        let deleted = delete_files(app.clone(), affected)?;
+        RetentionRun { processed: deleted.deleted, failed: deleted.failed }
+    };
+    
+    conn.execute(
+        "UPDATE retention_rules SET last_run_at = ?1 WHERE id = ?2",
+        rusqlite::params![now_millis_string(), rule.id],
+    ).map_err(|e| format!("Failed to record run: {}", e))?;
+    
+    if !result.processed.is_empty() || !result.failed.is_empty() {
+        println!("[RETENTION] ✅ Rule '{}' ({}): processed {}, failed {}",
+                 rule.name, rule.action, result.processed.len(), result.failed.len());
+    }
+    Ok(result)
+}
+
+#[tauri::command]
+fn run_retention_rule(app: AppHandle, id: i64) -> Result<RetentionRun, String> {
+    let conn = init_database(&app)
+        .map_err(|e| format!("Database error: {}", e))?;
+    let rule = load_retention_rule(&conn, id)?;
+    execute_retention_rule(&app, &conn, &rule)
+}
+
+fn run_scheduled_retention(app: &AppHandle) -> Result<(), String> {
+    let conn = init_database(app)
+        .map_err(|e| format!("Database error: {}", e))?;
+    
+    for rule in load_retention_rules(&conn, None)? {
+        // Unapproved rules wait for the user to review their preview
+        if !rule.enabled || rule.approved_at.is_none() {
+            continue;
+        }
+        if let Err(e) = execute_retention_rule(app, &conn, &rule) {
+            eprintln!("[RETENTION] Rule '{}' failed: {}", rule.name, e);
+        }
+    }
+    Ok(())
+}
+
+// ============== ARCHIVE ==============
+
+// Root of the managed library (setting, defaults to ~/Pictures/Chronicle Library)
+const LIBRARY_DIR_KEY: &str = "library_dir";
+// Archive screenshots from watch folders once they're this many days old (unset/0 = off)
+const AUTO_ARCHIVE_DAYS_KEY: &str = "auto_archive_after_days";
+
+#[derive(Serialize)]
+struct ArchivedEntry {
+    from: String,
+    to: String,
+}
+
+#[derive(Serialize)]
+struct ArchiveResult {
+    archived: Vec<ArchivedEntry>,
+    failed: Vec<String>,
+}
+
+fn library_dir(app: &AppHandle) -> Option<PathBuf> {
+    let configured: Option<String> = init_database(app)
+        .ok()
+        .and_then(|conn| read_setting(&conn, LIBRARY_DIR_KEY));
+    library_root(configured)
+}
+
+fn library_root(configured: Option<String>) -> Option<PathBuf> {
+    match configured {
+        Some(dir) if !dir.trim().is_empty() => Some(PathBuf::from(dir)),
+        _ => std::env::var("HOME")
+            .ok()
+            .map(|home| PathBuf::from(home).join("Pictures").join("Chronicle Library")),
+    }
+}
+
+// The library is both an asset scope and a folder Chronicle moves and deletes in, so it can't be
+// a broad one. Returns the folder asked for (None = the default)
+fn validate_library_dir(value: &serde_json::Value) -> Result<Option<String>, String> {
+    let dir = match value {
+        serde_json::Value::Null => return Ok(None),
+        serde_json::Value::String(dir) if dir.trim().is_empty() => return Ok(None),
+        serde_json::Value::String(dir) => dir,
+        _ => return Err("Library folder must be a path".to_string()),
+    };
+    let path = Path::new(dir);
+    if !path.is_absolute() {
+        return Err(format!("Library folder must be an absolute path: {}", dir));
+    }
+    let canonical = fs::canonicalize(path)
+        .ok()
+        .filter(|canonical| canonical.is_dir())
+        .ok_or_else(|| format!("Library folder must be an existing folder: {}", dir))?;
+    let home = std::env::var("HOME").ok().and_then(|home| fs::canonicalize(home).ok());
+    if canonical.parent().is_none() || Some(&canonical) == home.as_ref() {
+        return Err(format!("{} is too broad for the library - pick a folder of its own", dir));
+    }
+    for watch_dir in resolve_watch_dirs() {
+        let watch_dir = fs::canonicalize(&watch_dir).unwrap_or(watch_dir);
+        if watch_dir.starts_with(&canonical) {
+            return Err(format!("The library can't contain the watch folder {}", watch_dir.display()));
+        }
+    }
+    Ok(Some(dir.clone()))
+}
+
+// Move archived screenshots to the new library, keeping their place in the tree; otherwise they'd
+// fall outside managed_dirs. All or nothing: a failure moves everything back
+fn move_library(app: &AppHandle, conn: &Connection, requested: Option<String>) -> Result<(), String> {
+    let (Some(current), Some(next)) = (library_root(read_setting(conn, LIBRARY_DIR_KEY)), library_root(requested)) else {
+        return Ok(());
+    };
+    // Entries may have been archived under the folder as configured or as resolved
+    let configured = current.clone();
+    let current = fs::canonicalize(&current).unwrap_or(current);
+    let next = fs::canonicalize(&next).unwrap_or(next);
+    if current == next {
+        return Ok(());
+    }
+
+    let archived: Vec<(String, bool)> = {
+        let mut stmt = conn.prepare("SELECT path, COALESCE(trashed, 0) FROM entries")
+            .map_err(|e| format!("Query error: {}", e))?;
+        let rows = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)? == 1)))
+            .map_err(|e| format!("Query map error: {}", e))?;
+        rows.filter_map(|row| row.ok())
+            .filter(|(path, _)| Path::new(path).starts_with(&current) || Path::new(path).starts_with(&configured))
+            .collect()
+    };
+    if archived.is_empty() {
+        return Ok(());
+    }
+    if next.starts_with(&current) || current.starts_with(&next) {
+        return Err("The new library can't be inside the current one, or the other way around".to_string());
+    }
+
+    let mut moved: Vec<(PathBuf, PathBuf, bool)> = Vec::new();
+    for (path, trashed) in archived {
+        let from = PathBuf::from(&path);
+        let Ok(relative) = from.strip_prefix(&current).or_else(|_| from.strip_prefix(&configured)) else {
+            continue;
+        };
+        let to = next.join(relative);
+        // Trashed screenshots are in the trash folder; they'll be restored into the new library
+        let result = if trashed {
+            update_entry_path(app, conn, &path, &to.to_string_lossy())
+        } else {
+            relocate_entry(app, conn, &from, &to, FILE_OP_ARCHIVE)
+        };
+        if let Err(e) = result {
+            for (from, to, trashed) in moved.into_iter().rev() {
+                let undone = if trashed {
+                    update_entry_path(app, conn, &to.to_string_lossy(), &from.to_string_lossy())
+                } else {
+                    relocate_entry(app, conn, &to, &from, FILE_OP_ARCHIVE)
+                };
+                if let Err(e) = undone {
+                    eprintln!("[ARCHIVE] Failed to move {} back: {}", to.display(), e);
+                }
+            }
+            return Err(format!("Failed to move {} to the new library: {}", path, e));
+        }
+        moved.push((from, to, trashed));
+    }
+    println!("[ARCHIVE] ✅ Moved {} archived screenshots to {}", moved.len(), next.display());
+    Ok(())
+}
+
+// Everywhere Chronicle may move or delete files: watch folders plus the library
+fn managed_dirs(app: &AppHandle) -> Vec<PathBuf> {
+    let mut dirs = resolve_watch_dirs();
+    dirs.extend(library_dir(app));
+    dirs
+}
+
+fn is_within_dirs(path: &Path, dirs: &[PathBuf]) -> bool {
+    let Ok(canonical_path) = fs::canonicalize(path) else {
+        return false;
+    };
+    dirs.iter().any(|dir| {
+        fs::canonicalize(dir)
+            .map(|canonical_dir| canonical_path.starts_with(&canonical_dir))
+            .unwrap_or(false)
+    })
+}
+
+// The static asset scope only covers the watch folders; the library location is configurable
+fn allow_library_assets(app: &AppHandle) {
+    let Some(dir) = library_dir(app) else {
+        return;
+    };
+    if let Err(e) = fs::create_dir_all(&dir) {
+        eprintln!("[ARCHIVE] Failed to create library {}: {}", dir.display(), e);
+        return;
+    }
+    match app.asset_protocol_scope().allow_directory(&dir, true) {
+        Ok(_) => println!("[ARCHIVE] Library at {}", dir.display()),
+        Err(e) => eprintln!("[ARCHIVE] Failed to allow library assets: {}", e),
+    }
+}
+
+// Point an entry (and everything keyed by its path) at a new location
+fn update_entry_path(app: &AppHandle, conn: &Connection, old_path: &str, new_path: &str) -> Result<(), String> {
+    conn.execute(
+        "UPDATE entries SET path = ?1 WHERE path = ?2",
+        rusqlite::params![new_path, old_path],
+    ).map_err(|e| format!("Failed to update entry path: {}", e))?;
+    conn.execute(
+        "UPDATE embeddings SET path = ?1 WHERE path = ?2",
+        rusqlite::params![new_path, old_path],
+    ).map_err(|e| format!("Failed to update embedding path: {}", e))?;
+    conn.execute(
+        "UPDATE entities SET path = ?1 WHERE path = ?2",
+        rusqlite::params![new_path, old_path],
+    ).map_err(|e| format!("Failed to update entity path: {}", e))?;
+    conn.execute(
+        "UPDATE receipts SET path = ?1 WHERE path = ?2",
+        rusqlite::params![new_path, old_path],
+    ).map_err(|e| format!("Failed to update receipt path: {}", e))?;
+    
+    unindex_hash(app, old_path);
+    let hash: Option<Vec<u8>> = conn.query_row(
+        "SELECT perceptual_hash FROM entries WHERE path = ?1",
+        rusqlite::params![new_path],
+        |row| row.get(0),
+    ).ok().flatten();
+    if let Some(hash) = hash {
+        index_hash(app, new_path, &hash);
+    }
+    Ok(())
+}
+
+// Move an indexed screenshot's file, update its entry and journal the move under `reason`
+fn relocate_entry(app: &AppHandle, conn: &Connection, from: &Path, to: &Path, reason: &str) -> Result<(), String> {
+    if to.exists() {
+        return Err(format!("{} already exists", to.display()));
+    }
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    move_file(from, to)?;
+    update_entry_path(app, conn, &from.to_string_lossy(), &to.to_string_lossy())?;
+    if let Err(e) = record_file_operation(conn, from, to, reason) {
+        eprintln!("[FILEOPS] {}", e);
+    }
+    Ok(())
+}
+
+// Keep tag names usable as folder names
+fn folder_name(tag: &str) -> String {
+    let cleaned: String = tag.chars()
+        .map(|c| if c.is_alphanumeric() || c == ' ' || c == '-' || c == '_' { c } else { '-' })
+        .collect();
+    let cleaned = cleaned.trim().trim_matches('-').to_string();
+    if cleaned.is_empty() { "Unsorted".to_string() } else { cleaned }
+}
+
+// Library/YYYY/MM/Tag/<file name>, numbered on collision
+fn archive_destination(library: &Path, created_at_ms: i64, tag: &str, file_name: &str) -> PathBuf {
+    use chrono::{Datelike, Local, TimeZone};
+    
+    let date = Local.timestamp_millis_opt(created_at_ms).single().unwrap_or_else(Local::now);
+    let dir = library
+        .join(format!("{:04}", date.year()))
+        .join(format!("{:02}", date.month()))
+        .join(folder_name(tag));
+    
+    let candidate = dir.join(file_name);
+    if !candidate.exists() {
+        return candidate;
+    }
+    let path = Path::new(file_name);
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("screenshot");
+    let ext = path.extension().and_then(|s| s.to_str()).unwrap_or("png");
+    (2..)
+        .map(|n| dir.join(format!("{}-{}.{}", stem, n, ext)))
+        .find(|candidate| !candidate.exists())
+        .unwrap_or(candidate)
+}
+
+// Move screenshots out of the watch folders into the library tree
+#[tauri::command]
+fn archive_entries(app: AppHandle, paths: Vec<String>) -> Result<ArchiveResult, String> {
+    let library = library_dir(&app).ok_or_else(|| "Library directory not available".to_string())?;
+    let watch_dirs = resolve_watch_dirs();
+    let conn = init_database(&app)
+        .map_err(|e| format!("Database error: {}", e))?;
+    
+    let mut archived = Vec::new();
+    let mut failed = Vec::new();
+    
+    for path_str in paths {
+        let path = PathBuf::from(&path_str);
+        // Same safety rule as delete_files: only touch files inside the watch folders
+        if !is_within_dirs(&path, &watch_dirs) {
+            eprintln!("[ARCHIVE] Path not in watch directories: {}", path_str);
+            failed.push(path_str);
+            continue;
+        }
+        
+        let row: Option<(String, Option<String>, Option<String>)> = conn.query_row(
+            "SELECT created_at, tags, custom_tags FROM entries WHERE path = ?1 AND COALESCE(trashed, 0) = 0",
+            rusqlite::params![path_str],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        ).ok();
+        let Some((created_at, tags, custom_tags)) = row else {
+            eprintln!("[ARCHIVE] {} is not indexed", path_str);
+            failed.push(path_str);
+            continue;
+        };
+        
+        // Custom tags are the user's own organization, so they win over auto tags
+        let tag = parse_json_list(custom_tags).into_iter()
+            .chain(parse_json_list(tags))
+            .next()
+            .unwrap_or_else(|| "Unsorted".to_string());
+        let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("screenshot.png");
+        let destination = archive_destination(&library, created_at.parse().unwrap_or(0), &tag, file_name);
+        
+        match relocate_entry(&app, &conn, &path, &destination, FILE_OP_ARCHIVE) {
+            Ok(_) => {
+                println!("[ARCHIVE] ✅ {} -> {}", path_str, destination.display());
+                archived.push(ArchivedEntry { from: path_str, to: destination.to_string_lossy().to_string() });
+            }
+            Err(e) => {
+                eprintln!("[ARCHIVE] Failed to archive {}: {}", path_str, e);
+                failed.push(path_str);
+            }
+        }
+    }
+    
+    Ok(ArchiveResult { archived, failed })
+}
+
+fn run_auto_archive(app: &AppHandle) -> Result<(), String> {
+    let conn = init_database(app)
+        .map_err(|e| format!("Database error: {}", e))?;
+    let days: u64 = read_setting(&conn, AUTO_ARCHIVE_DAYS_KEY).unwrap_or(0);
+    if days == 0 {
+        return Ok(());
+    }
+    
+    let query = query::Query::parse(&format!("older:{}d", days))?;
+    let watch_dirs = resolve_watch_dirs();
+    let due: Vec<String> = query_entry_paths(&conn, &query)?
+        .into_iter()
+        .filter(|path| is_within_dirs(Path::new(path), &watch_dirs))
+        .collect();
+    if due.is_empty() {
+        return Ok(());
+    }
+    
+    let result = archive_entries(app.clone(), due)?;
+    println!("[ARCHIVE] ✅ Auto-archived {} screenshots older than {} days ({} failed)",
+             result.archived.len(), days, result.failed.len());
+    Ok(())
+}
+
+// ============== RENAMING ==============
+
+// Template for naming screenshots (see rename.rs), defaults to {slug}-{timestamp}
+const RENAME_TEMPLATE_KEY: &str = "rename_template";
+// Watch folder -> whether new screenshots there get renamed (folders not listed are on)
+const RENAME_FOLDERS_KEY: &str = "rename_folders";
+
+#[derive(Serialize)]
+struct RenamedEntry {
+    from: String,
+    to: String,
+}
+
+#[derive(Serialize)]
+struct RenamePreview {
+    renames: Vec<RenamedEntry>,
+    skipped: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct RenameResult {
+    renamed: Vec<RenamedEntry>,
+    skipped: Vec<String>,
+    failed: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct RenameFolder {
+    path: String,
+    enabled: bool,
+}
+
+fn rename_template(conn: &Connection) -> rename::Template {
+    let configured: Option<String> = read_setting(conn, RENAME_TEMPLATE_KEY);
+    configured
+        .filter(|template| !template.trim().is_empty())
+        .and_then(|template| match rename::Template::parse(&template) {
+            Ok(template) => Some(template),
+            Err(e) => {
+                eprintln!("[RENAME] {}, using the default template", e);
+                None
+            }
+        })
+        .unwrap_or_else(|| rename::Template::parse(rename::DEFAULT_TEMPLATE).expect("Default rename template is valid"))
+}
+
+fn rename_folders(conn: &Connection) -> HashMap<String, bool> {
+    read_setting(conn, RENAME_FOLDERS_KEY).unwrap_or_default()
+}
+
+// On by default in each watch folder; never for screenshots outside them (e.g. in the library)
+fn rename_enabled(conn: &Connection, path: &Path) -> bool {
+    let folders = rename_folders(conn);
+    resolve_watch_dirs()
+        .iter()
+        .find(|dir| path.starts_with(dir))
+        .is_some_and(|dir| folders.get(dir.to_string_lossy().as_ref()).copied().unwrap_or(true))
+}
+
+// Where `path` would be renamed to under `template`, avoiding paths in `taken`
+fn rename_target(
+    conn: &Connection,
+    template: &rename::Template,
+    path: &Path,
+    text: &str,
+    tag: &str,
+    created_at_ms: i64,
+    taken: &HashSet<PathBuf>,
+) -> Result<PathBuf, String> {
+    let dir = path
+        .parent()
+        .ok_or_else(|| "Screenshot path missing parent directory".to_string())?;
+    // Keywords of a sensitive screenshot could be the code or key itself
+    let sensitive = !secret_kinds(text).is_empty() || is_sensitive_entry(conn, &path.to_string_lossy());
+    let slug = if sensitive {
+        "screenshot".to_string()
+    } else {
+        slugify_text(&summarize_text(conn, text))
+    };
+    let tag = slugify_text(tag);
+    let original = path.file_stem().and_then(|s| s.to_str()).unwrap_or("screenshot");
+    let extension = path.extension().and_then(|s| s.to_str()).unwrap_or("png");
+    let facts = rename::RenameFacts { slug: &slug, tag: &tag, created_at_ms, original };
+    Ok(template.resolve(dir, &facts, extension, Some(path), taken))
+}
+
+// (path, new path) pairs, plus the paths that were left alone
+type RenamePlan = (Vec<(String, PathBuf)>, Vec<String>);
+
+// New names for indexed screenshots
+// Without explicit paths, covers every screenshot in watch folders that have renaming on
+fn plan_renames(
+    app: &AppHandle,
+    conn: &Connection,
+    paths: Option<Vec<String>>,
+    template: Option<String>,
+) -> Result<RenamePlan, String> {
+    let template = match template {
+        Some(template) => rename::Template::parse(&template)?,
+        None => rename_template(conn),
+    };
+    let paths = match paths {
+        Some(paths) => paths,
+        None => query_entry_paths(conn, &query::Query::default())?
+            .into_iter()
+            .filter(|path| rename_enabled(conn, Path::new(path)))
+            .collect(),
+    };
+    let dirs = managed_dirs(app);
+    
+    let mut renames = Vec::new();
+    let mut skipped = Vec::new();
+    // Names handed out earlier in this batch, so two screenshots never get the same one
+    let mut taken = HashSet::new();
+    
+    for path_str in paths {
+        let path = PathBuf::from(&path_str);
+        if !is_within_dirs(&path, &dirs) {
+            eprintln!("[RENAME] Path not in managed directories: {}", path_str);
+            skipped.push(path_str);
+            continue;
+        }
+        
+        let row: Option<(String, String, Option<String>, Option<String>)> = conn.query_row(
+            "SELECT text, created_at, tags, custom_tags FROM entries WHERE path = ?1 AND COALESCE(trashed, 0) = 0",
+            rusqlite::params![path_str],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+        ).ok();
+        let Some((text, created_at, tags, custom_tags)) = row else {
+            eprintln!("[RENAME] {} is not indexed", path_str);
+            skipped.push(path_str);
+            continue;
+        };
+        
+        let tag = parse_json_list(custom_tags).into_iter()
+            .chain(parse_json_list(tags))
+            .next()
+            .unwrap_or_else(|| "Unsorted".to_string());
+        let target = match rename_target(conn, &template, &path, &text, &tag, created_at.parse().unwrap_or(0), &taken) {
+            Ok(target) if target != path => target,
+            // Already named by the template
+            Ok(_) => {
+                skipped.push(path_str);
+                continue;
+            }
+            Err(e) => {
+                eprintln!("[RENAME] Can't rename {}: {}", path_str, e);
+                skipped.push(path_str);
+                continue;
+            }
+        };
+        taken.insert(target.clone());
+        renames.push((path_str, target));
+    }
+    
+    Ok((renames, skipped))
+}
+
+// Dry run of rename_entries: the names screenshots would get, without touching any files
+// `template` overrides the saved one, so a template can be tried out before saving it
+#[tauri::command]
+fn preview_rename(app: AppHandle, paths: Option<Vec<String>>, template: Option<String>) -> Result<RenamePreview, String> {
+    let conn = init_database(&app)
+        .map_err(|e| format!("Database error: {}", e))?;
+    let (renames, skipped) = plan_renames(&app, &conn, paths, template)?;
+    Ok(RenamePreview {
+        renames: renames
+            .into_iter()
+            .map(|(from, to)| RenamedEntry { from, to: to.to_string_lossy().to_string() })
+            .collect(),
+        skipped,
+    })
+}
+
+// Rename already-indexed screenshots in bulk; entries keep their text, tags and pins
+#[tauri::command]
+fn rename_entries(app: AppHandle, paths: Option<Vec<String>>, template: Option<String>) -> Result<RenameResult, String> {
+    let conn = init_database(&app)
+        .map_err(|e| format!("Database error: {}", e))?;
+    let (renames, skipped) = plan_renames(&app, &conn, paths, template)?;
+    let ignore_map = Arc::clone(&app.state::<WatcherIgnoreState>().0);
+    
+    let mut renamed = Vec::new();
+    let mut failed = Vec::new();
+    
+    for (from, to) in renames {
+        // Already indexed - don't let the watcher OCR the new name
+        remember_ignore(&ignore_map, &to);
+        match relocate_entry(&app, &conn, Path::new(&from), &to, FILE_OP_RENAME) {
+            Ok(_) => {
+                println!("[RENAME] ✅ {} -> {}", from, to.display());
+                renamed.push(RenamedEntry { from, to: to.to_string_lossy().to_string() });
+            }
+            Err(e) => {
+                eprintln!("[RENAME] Failed to rename {}: {}", from, e);
+                failed.push(from);
+            }
+        }
+    }
+    
+    println!("[RENAME] ✅ Renamed {} screenshots ({} skipped, {} failed)", renamed.len(), skipped.len(), failed.len());
+    Ok(RenameResult { renamed, skipped, failed })
+}
+
+#[tauri::command]
+fn get_rename_folders(app: AppHandle) -> Result<Vec<RenameFolder>, String> {
+    let conn = init_database(&app)
+        .map_err(|e| format!("Database error: {}", e))?;
+    let folders = rename_folders(&conn);
+    Ok(resolve_watch_dirs()
+        .into_iter()
+        .map(|dir| {
+            let path = dir.to_string_lossy().to_string();
+            let enabled = folders.get(&path).copied().unwrap_or(true);
+            RenameFolder { path, enabled }
+        })
+        .collect())
+}
+
+#[tauri::command]
+fn set_rename_folder(app: AppHandle, path: String, enabled: bool) -> Result<(), String> {
+    let conn = init_database(&app)
+        .map_err(|e| format!("Database error: {}", e))?;
+    if !resolve_watch_dirs().iter().any(|dir| dir.to_string_lossy() == path) {
+        return Err(format!("{} is not a watch folder", path));
+    }
+    let mut folders = rename_folders(&conn);
+    folders.insert(path.clone(), enabled);
+    write_setting(&conn, RENAME_FOLDERS_KEY, &folders)?;
+    println!("[RENAME] ✅ Renaming {} for {}", if enabled { "enabled" } else { "disabled" }, path);
+    Ok(())
+}
+
+// ============== FILE OPERATIONS ==============
+
+// Reasons recorded in the file_operations journal
+const FILE_OP_RENAME: &str = "rename";
+const FILE_OP_ARCHIVE: &str = "archive";
+// Secrets blacked out in place; recorded for the history, there's nothing to move back
+const FILE_OP_REDACT: &str = "redact";
+
+#[derive(Serialize)]
+struct FileOperation {
+    id: i64,
+    old_path: String,
+    new_path: String,
+    reason: String,
+    created_at: String,
+    undone_at: Option<String>,
+}
+
+#[derive(Serialize)]
+struct RevertResult {
+    reverted: Vec<FileOperation>,
+    failed: Vec<i64>,
+}
+
+fn record_file_operation(conn: &Connection, old_path: &Path, new_path: &Path, reason: &str) -> Result<(), String> {
+    conn.execute(
+        "INSERT INTO file_operations (old_path, new_path, reason, created_at) VALUES (?1, ?2, ?3, ?4)",
+        rusqlite::params![old_path.to_string_lossy(), new_path.to_string_lossy(), reason, now_millis_string()],
+    ).map_err(|e| format!("Failed to record file operation: {}", e))?;
+    Ok(())
+}
+
+fn file_operation_from_row(row: &rusqlite::Row) -> rusqlite::Result<FileOperation> {
+    Ok(FileOperation {
+        id: row.get(0)?,
+        old_path: row.get(1)?,
+        new_path: row.get(2)?,
+        reason: row.get(3)?,
+        created_at: row.get(4)?,
+        undone_at: row.get(5)?,
+    })
+}
+
+fn load_file_operation(conn: &Connection, id: i64) -> Result<FileOperation, String> {
+    conn.query_row(
+        "SELECT id, old_path, new_path, reason, created_at, undone_at FROM file_operations WHERE id = ?1",
+        rusqlite::params![id],
+        file_operation_from_row,
+    ).map_err(|_| format!("File operation {} not found", id))
+}
+
+// Move a file back to where it was before the operation
+fn revert_file_operation(app: &AppHandle, conn: &Connection, id: i64) -> Result<FileOperation, String> {
+    let operation = load_file_operation(conn, id)?;
+    if operation.undone_at.is_some() {
+        return Err(format!("File operation {} was already undone", id));
+    }
+    if operation.reason == FILE_OP_REDACT {
+        return Err(format!("File operation {} redacted {} and can't be undone", id, operation.new_path));
+    }
+    
+    let current = PathBuf::from(&operation.new_path);
+    let original = PathBuf::from(&operation.old_path);
+    if !current.exists() {
+        // Renamed or moved again since - those later operations need undoing first
+        return Err(format!("{} no longer exists", operation.new_path));
+    }
+    if original.exists() {
+        return Err(format!("{} already exists", operation.old_path));
+    }
+    
+    // Same safety rule as delete_files: only move files between managed folders
+    let dirs = managed_dirs(app);
+    let original_dir_managed = original.parent().map(|dir| is_within_dirs(dir, &dirs)).unwrap_or(false);
+    if !is_within_dirs(&current, &dirs) || !original_dir_managed {
+        return Err(format!("{} is outside the managed directories", operation.new_path));
+    }
+    
+    // The entry keeps its text and tags - don't let the watcher OCR it again
+    let ignore_map = Arc::clone(&app.state::<WatcherIgnoreState>().0);
+    remember_ignore(&ignore_map, &original);
+    move_file(&current, &original)?;
+    update_entry_path(app, conn, &operation.new_path, &operation.old_path)?;
+    
+    conn.execute(
+        "UPDATE file_operations SET undone_at = ?1 WHERE id = ?2",
+        rusqlite::params![now_millis_string(), id],
+    ).map_err(|e| format!("Failed to update file operation: {}", e))?;
+    
+    println!("[FILEOPS] ✅ Undid {} {}: {} -> {}", operation.reason, id, operation.new_path, operation.old_path);
+    load_file_operation(conn, id)
+}
+
+// Most recent operations first
+#[tauri::command]
+fn list_file_operations(app: AppHandle, limit: Option<usize>) -> Result<Vec<FileOperation>, String> {
+    let conn = init_database(&app)
+        .map_err(|e| format!("Database error: {}", e))?;
+    let mut stmt = conn.prepare(
+        "SELECT id, old_path, new_path, reason, created_at, undone_at FROM file_operations ORDER BY id DESC LIMIT ?1"
+    ).map_err(|e| format!("Query error: {}", e))?;
+    let rows = stmt.query_map(rusqlite::params![limit.unwrap_or(200) as i64], file_operation_from_row)
+        .map_err(|e| format!("Query map error: {}", e))?;
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Row error: {}", e))
+}
+
+#[tauri::command]
+fn undo_operation(app: AppHandle, id: i64) -> Result<FileOperation, String> {
+    let conn = init_database(&app)
+        .map_err(|e| format!("Database error: {}", e))?;
+    revert_file_operation(&app, &conn, id)
+}
+
+// Restore original names for every rename since `since` (ms timestamp, all renames when omitted)
+// Newest first, so files renamed more than once unwind back to their first name
+#[tauri::command]
+fn revert_all_renames(app: AppHandle, since: Option<u64>) -> Result<RevertResult, String> {
+    let conn = init_database(&app)
+        .map_err(|e| format!("Database error: {}", e))?;
+    let ids: Vec<i64> = {
+        let mut stmt = conn.prepare(
+            "SELECT id FROM file_operations
+             WHERE reason = ?1 AND undone_at IS NULL AND CAST(created_at AS INTEGER) >= ?2
+             ORDER BY id DESC"
+        ).map_err(|e| format!("Query error: {}", e))?;
+        let rows = stmt.query_map(rusqlite::params![FILE_OP_RENAME, since.unwrap_or(0) as i64], |row| row.get(0))
+            .map_err(|e| format!("Query map error: {}", e))?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Row error: {}", e))?
+    };
+    
+    let mut reverted = Vec::new();
+    let mut failed = Vec::new();
+    for id in ids {
+        match revert_file_operation(&app, &conn, id) {
+            Ok(operation) => reverted.push(operation),
+            Err(e) => {
+                eprintln!("[FILEOPS] Failed to undo rename {}: {}", id, e);
+                failed.push(id);
+            }
+        }
+    }
+    
+    println!("[FILEOPS] ✅ Reverted {} renames ({} failed)", reverted.len(), failed.len());
+    Ok(RevertResult { reverted, failed })
+}
+
+// ============== RECEIPTS ==============
+
+const RECEIPTS_TAG: &str = "Receipts";
+
+#[derive(Serialize)]
+struct StoredReceipt {
+    path: String,
+    #[serde(flatten)]
+    receipt: receipts::Receipt,
+}
+
+// Inclusive YYYY-MM-DD bounds; either side may be open
+#[derive(serde::Deserialize)]
+struct DateRange {
+    from: Option<String>,
+    to: Option<String>,
+}
+
+fn is_receipt(conn: &Connection, path: &str) -> bool {
+    let row: Option<(Option<String>, Option<String>)> = conn.query_row(
+        "SELECT tags, custom_tags FROM entries WHERE path = ?1",
+        rusqlite::params![path],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    ).ok();
+    let Some((tags, custom_tags)) = row else {
+        return false;
+    };
+    parse_json_list(tags).iter()
+        .chain(parse_json_list(custom_tags).iter())
+        .any(|tag| tag.eq_ignore_ascii_case(RECEIPTS_TAG))
+}
+
+// Parse and store the receipt for an entry tagged Receipts (drops a stale one otherwise)
+// Returns Ok(false) when the entry isn't a receipt
+// No text means image-only (excluded) or unreadable: never OCR it here behind the rules' back
+fn index_receipt(conn: &Connection, path: &str, text: &str) -> Result<bool, String> {
+    if text.trim().is_empty() || !is_receipt(conn, path) {
+        conn.execute("DELETE FROM receipts WHERE path = ?1", rusqlite::params![path])
+            .map_err(|e| format!("Failed to clear receipt: {}", e))?;
+        return Ok(false);
+    }
+    
+    // Word boxes keep descriptions and right-aligned prices on the same line
+    let words = ocr_word_boxes(Path::new(path)).unwrap_or_else(|e| {
+        eprintln!("[RECEIPTS] No word boxes for {} ({}), using OCR text", path, e);
+        Vec::new()
+    });
+    let receipt = receipts::parse_receipt(text, &words);
+    let items_json = serde_json::to_string(&receipt.items).unwrap_or_else(|_| "[]".to_string());
+    conn.execute(
+        "INSERT OR REPLACE INTO receipts (path, merchant, date, currency, subtotal, tax, total, items, parsed_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        rusqlite::params![
+            path, receipt.merchant, receipt.date, receipt.currency,
+            receipt.subtotal, receipt.tax, receipt.total, items_json, now_millis_string()
+        ],
+    ).map_err(|e| format!("Failed to save receipt: {}", e))?;
+    
+    println!("[RECEIPTS] ✅ Parsed {}: {:?} {:?} {} items, total {:?} {}",
+             path, receipt.merchant, receipt.date, receipt.items.len(), receipt.total,
+             receipt.currency.as_deref().unwrap_or(""));
+    Ok(true)
+}
+
+fn load_receipts(conn: &Connection, path: Option<&str>) -> Result<Vec<StoredReceipt>, String> {
+    let mut stmt = conn.prepare(
+        "SELECT r.path, r.merchant, r.date, r.currency, r.subtotal, r.tax, r.total, r.items, e.created_at
+         FROM receipts r JOIN entries e ON e.path = r.path
+         WHERE COALESCE(e.trashed, 0) = 0 AND (?1 IS NULL OR r.path = ?1)
+         ORDER BY e.created_at"
+    ).map_err(|e| format!("Query error: {}", e))?;
+    let rows = stmt.query_map(rusqlite::params![path], |row| {
+        let items: String = row.get(7)?;
+        let created_at: String = row.get(8)?;
+        let date: Option<String> = row.get(2)?;
+        Ok(StoredReceipt {
+            path: row.get(0)?,
+            receipt: receipts::Receipt {
+                merchant: row.get(1)?,
+                // Screenshot date when the receipt's own date couldn't be read
+                date: date.or_else(|| millis_to_date(&created_at)),
+                currency: row.get(3)?,
+                subtotal: row.get(4)?,
+                tax: row.get(5)?,
+                total: row.get(6)?,
+                items: serde_json::from_str(&items).unwrap_or_default(),
+            },
+        })
+    }).map_err(|e| format!("Query map error: {}", e))?;
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Row error: {}", e))
+}
+
+fn millis_to_date(millis: &str) -> Option<String> {
+    use chrono::{Local, TimeZone};
+    let millis: i64 = millis.parse().ok()?;
+    Local.timestamp_millis_opt(millis).single().map(|date| date.format("%Y-%m-%d").to_string())
+}
+
+// Receipt-tagged entries that haven't been parsed yet (e.g. tagged by hand after indexing)
+fn parse_missing_receipts(conn: &Connection) -> Result<usize, String> {
+    let pending: Vec<(String, String)> = {
+        let mut stmt = conn.prepare(
+            "SELECT e.path, e.text FROM entries e LEFT JOIN receipts r ON r.path = e.path
+             WHERE r.path IS NULL AND COALESCE(e.trashed, 0) = 0
+               AND (e.tags LIKE '%\"Receipts\"%' OR e.custom_tags LIKE '%\"Receipts\"%')"
+        ).map_err(|e| format!("Query error: {}", e))?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(|e| format!("Query map error: {}", e))?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Row error: {}", e))?
+    };
+    
+    let mut parsed = 0;
+    for (path, text) in pending {
+        match index_receipt(conn, &path, &text) {
+            Ok(true) => parsed += 1,
+            Ok(false) => {}
+            Err(e) => eprintln!("[RECEIPTS] Failed to parse {}: {}", path, e),
+        }
+    }
+    Ok(parsed)
+}
+
+// RFC 4180 quoting; OCR text starting like a formula gets a ' so spreadsheets show it as text
+fn csv_field(value: &str) -> String {
+    let value = if value.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        format!("'{}", value)
+    } else {
+        value.to_string()
+    };
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+fn csv_amount(amount: Option<f64>) -> String {
+    amount.map(|amount| format!("{:.2}", amount)).unwrap_or_default()
+}
+
+#[tauri::command]
+fn get_receipt(app: AppHandle, path: String) -> Result<Option<StoredReceipt>, String> {
+    let conn = init_database(&app)
+        .map_err(|e| format!("Database error: {}", e))?;
+    Ok(load_receipts(&conn, Some(&path))?.into_iter().next())
+}
+
+// Receipts as CSV for expense reports
+// format "csv" (default) gives one row per receipt, "items_csv" one row per line item
+#[tauri::command]
+fn export_receipts(app: AppHandle, range: Option<DateRange>, format: Option<String>) -> Result<String, String> {
+    let conn = init_database(&app)
+        .map_err(|e| format!("Database error: {}", e))?;
+    let parsed = parse_missing_receipts(&conn)?;
+    if parsed > 0 {
+        println!("[RECEIPTS] Parsed {} receipts before export", parsed);
+    }
+    
+    let (from, to) = range.map(|range| (range.from, range.to)).unwrap_or((None, None));
+    let in_range = |date: &Option<String>| match date {
+        Some(date) => from.as_deref().is_none_or(|from| date.as_str() >= from)
+            && to.as_deref().is_none_or(|to| date.as_str() <= to),
+        None => from.is_none() && to.is_none(),
+    };
+    let stored: Vec<StoredReceipt> = load_receipts(&conn, None)?
+        .into_iter()
+        .filter(|stored| in_range(&stored.receipt.date))
+        .collect();
+    
+    let mut lines: Vec<String> = Vec::new();
+    match format.as_deref().unwrap_or("csv") {
+        "csv" => {
+            lines.push("date,merchant,currency,subtotal,tax,total,items,path".to_string());
+            for StoredReceipt { path, receipt } in &stored {
+                lines.push([
+                    csv_field(receipt.date.as_deref().unwrap_or("")),
+                    csv_field(receipt.merchant.as_deref().unwrap_or("")),
+                    csv_field(receipt.currency.as_deref().unwrap_or("")),
+                    csv_amount(receipt.subtotal),
+                    csv_amount(receipt.tax),
+                    csv_amount(receipt.total),
+                    receipt.items.len().to_string(),
+                    csv_field(path),
+                ].join(","));
+            }
+        }
+        "items_csv" => {
+            lines.push("date,merchant,currency,description,quantity,amount,path".to_string());
+            for StoredReceipt { path, receipt } in &stored {
+                for item in &receipt.items {
+                    lines.push([
+                        csv_field(receipt.date.as_deref().unwrap_or("")),
+                        csv_field(receipt.merchant.as_deref().unwrap_or("")),
+                        csv_field(receipt.currency.as_deref().unwrap_or("")),
+                        csv_field(&item.description),
+                        item.quantity.map(|quantity| quantity.to_string()).unwrap_or_default(),
+                        csv_amount(Some(item.amount)),
+                        csv_field(path),
+                    ].join(","));
+                }
+            }
+        }
+        other => return Err(format!("Unknown receipt export format '{}' (expected 'csv' or 'items_csv')", other)),
+    }
+    
+    println!("[RECEIPTS] ✅ Exported {} receipts", stored.len());
+    Ok(lines.join("\r\n") + "\r\n")
+}
+
+// ============== EXPORT ==============
+
+#[derive(Serialize)]
+struct ExportSummary {
+    format: String,
+    entries: usize,
+    saved_searches: usize,
+    files: Vec<String>,
+}
+
+// Everything stored for the entries matching a query-language filter (all of them without one)
+fn load_exported_entries(conn: &Connection, filter: &query::Query) -> Result<Vec<export::ExportedEntry>, String> {
+    let to_hex = |bytes: Option<Vec<u8>>| bytes.map(|bytes| bytes.iter().map(|byte| format!("{:02x}", byte)).collect::<String>());
+    let mut entries = Vec::new();
+    for path in query_entry_paths(conn, filter)? {
+        let row = conn.query_row(
+            "SELECT text, created_at, tags, custom_tags, urls, emails, COALESCE(pinned, 0), COALESCE(sensitive, 0), sensitive_kinds,
+                    perceptual_hash, phash, block_hash
+             FROM entries WHERE path = ?1",
+            rusqlite::params![path],
+            |row| Ok(export::ExportedEntry {
+                path: path.clone(),
+                content_hash: None,
+                text: row.get(0)?,
+                created_at: row.get(1)?,
+                tags: parse_json_list(row.get(2)?),
+                custom_tags: parse_json_list(row.get(3)?),
+                urls: parse_json_list(row.get(4)?),
+                emails: parse_json_list(row.get(5)?),
+                pinned: row.get::<_, i64>(6)? == 1,
+                sensitive: row.get::<_, i64>(7)? == 1,
+                sensitive_kinds: parse_json_list(row.get(8)?),
+                entities: Vec::new(),
+                perceptual_hash: to_hex(row.get(9)?),
+                phash: to_hex(row.get(10)?),
+                block_hash: to_hex(row.get(11)?),
+            }),
+        ).map_err(|e| format!("Failed to load {}: {}", path, e))?;
+        let entities = load_entities(conn, &path)?
+            .into_iter()
+            .map(|entity| export::ExportedEntity { kind: entity.kind, value: entity.value, start: entity.start, end: entity.end })
+            .collect();
+        let content_hash = content_hash(Path::new(&path)).ok();
+        entries.push(export::ExportedEntry { content_hash, entities, ..row });
+    }
+    Ok(entries)
+}
+
+// Write the index out as "jsonl" (lossless), "bundle" (that plus the images, dest is a folder),
+// "csv" or "markdown" (dest is a folder of notes)
+#[tauri::command]
+fn export_index(app: AppHandle, format: String, filter: Option<String>, dest: String) -> Result<ExportSummary, String> {
+    let conn = init_database(&app)
+        .map_err(|e| format!("Database error: {}", e))?;
+    let filter = query::Query::parse(filter.as_deref().unwrap_or(""))?;
+    let entries = load_exported_entries(&conn, &filter)?;
+    let searches = saved_searches(&conn)?;
+    let dest = PathBuf::from(dest);
+
+    let files = match format.as_str() {
+        "jsonl" => {
+            export::write_jsonl(&dest, &now_millis_string(), &entries, &searches)?;
+            vec![dest]
+        }
+        "bundle" => export::write_bundle(&dest, &now_millis_string(), &entries, &searches)?,
+        "csv" => export::write_csv(&dest, &entries, &searches)?,
+        "markdown" => export::write_markdown(&dest, &entries, &searches)?,
+        other => return Err(format!("Unknown export format '{}' (expected 'jsonl', 'bundle', 'csv' or 'markdown')", other)),
+    };
+
+    println!("[EXPORT] ✅ Exported {} entries and {} saved searches as {}", entries.len(), searches.len(), format);
+    Ok(ExportSummary {
+        format,
+        entries: entries.len(),
+        saved_searches: searches.len(),
+        files: files.iter().map(|file| file.to_string_lossy().into_owned()).collect(),
+    })
+}
+
+// ============== IMPORT ==============
+
+#[derive(Serialize, Default)]
+struct ImportSummary {
+    imported: usize,
+    merged: usize,
+    skipped: usize,
+    saved_searches: usize,
+    // Exported paths whose image isn't here (or has changed since), so they weren't imported
+    missing: Vec<String>,
+}
+
+// Content hash -> path of every entry outside the trash; entries from before the column get theirs now
+fn entries_by_content_hash(conn: &Connection) -> Result<HashMap<String, String>, String> {
+    let rows: Vec<(String, Option<String>)> = {
+        let mut stmt = conn.prepare("SELECT path, content_hash FROM entries WHERE COALESCE(trashed, 0) = 0")
+            .map_err(|e| format!("Query error: {}", e))?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(|e| format!("Query map error: {}", e))?;
+        rows.collect::<Result<_, _>>()
+            .map_err(|e| format!("Row error: {}", e))?
+    };
+    let mut by_hash = HashMap::new();
+    for (path, hash) in rows {
+        let hash = match hash {
+            Some(hash) => hash,
+            None => {
+                let Ok(hash) = content_hash(Path::new(&path)) else {
+                    continue;
+                };
+                conn.execute(
+                    "UPDATE entries SET content_hash = ?1 WHERE path = ?2",
+                    rusqlite::params![hash, path],
+                ).map_err(|e| format!("Failed to store content hash: {}", e))?;
+                hash
+            }
+        };
+        by_hash.insert(hash, path);
+    }
+    Ok(by_hash)
+}
+
+// Our text and tags stay; imported custom tags are added and a pin on either side is kept
+fn merge_imported(conn: &Connection, path: &str, entry: &export::ExportedEntry) -> Result<(), String> {
+    for tag in &entry.custom_tags {
+        add_entry_tag(conn, path, tag)?;
+    }
+    if entry.pinned {
+        conn.execute(
+            "UPDATE entries SET pinned = 1 WHERE path = ?1",
+            rusqlite::params![path],
+        ).map_err(|e| format!("Failed to pin {}: {}", path, e))?;
+    }
+    Ok(())
+}
+
+// The imported entry's image on this machine: copied from a bundle into the library tree,
+// or for a plain JSONL export the exported path itself, if the file there is still the same
+fn imported_image(entry: &export::ExportedEntry, bundle: Option<&Path>, library: Option<&Path>) -> Result<Option<PathBuf>, String> {
+    let Some(bundle) = bundle else {
+        let path = PathBuf::from(&entry.path);
+        let unchanged = match &entry.content_hash {
+            Some(hash) => content_hash(&path).is_ok_and(|actual| &actual == hash),
+            None => path.exists(),
+        };
+        return Ok(unchanged.then_some(path));
+    };
+    let Some(image) = export::bundle_image(bundle, entry).filter(|image| image.exists()) else {
+        return Ok(None);
+    };
+    // A symlink in the bundle could point anywhere on this machine
+    let images = fs::canonicalize(bundle.join(export::BUNDLE_IMAGES))
+        .map_err(|e| format!("Failed to read {}: {}", bundle.display(), e))?;
+    let image = fs::canonicalize(&image)
+        .map_err(|e| format!("Failed to read {}: {}", image.display(), e))?;
+    if !image.starts_with(&images) {
+        return Err(format!("{} is outside the bundle's images", image.display()));
+    }
+    let library = library.ok_or_else(|| "Library directory not available".to_string())?;
+    let tag = entry.custom_tags.iter()
+        .chain(&entry.tags)
+        .next()
+        .map(String::as_str)
+        .unwrap_or("Unsorted");
+    let file_name = Path::new(&entry.path).file_name().and_then(|n| n.to_str()).unwrap_or("screenshot.png");
+    let destination = archive_destination(library, entry.created_at.parse().unwrap_or(0), tag, file_name);
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    fs::copy(&image, &destination)
+        .map_err(|e| format!("Failed to copy {}: {}", image.display(), e))?;
+    // The hash is what later copies merge on, so it has to be the image's
+    let copied = content_hash(&destination);
+    if copied.as_ref().ok() != entry.content_hash.as_ref() {
+        let _ = fs::remove_file(&destination);
+        return Err(format!("{} doesn't match its content hash", image.display()));
+    }
+    Ok(Some(destination))
+}
+
+// Merge a JSONL export, or a bundle folder, into this index
+// Screenshots already here are matched on content hash; new ones keep their exported text and only
+// go through OCR when they have none
+fn import_from(indexer: &Indexer, conn: &Connection, source: &Path, library: Option<&Path>) -> Result<ImportSummary, String> {
+    let bundle = source.is_dir().then_some(source);
+    let index = match bundle {
+        Some(dir) => dir.join(export::BUNDLE_INDEX),
+        None => source.to_path_buf(),
+    };
+    let (mut entries, searches) = export::read_jsonl(&index)?;
+    // Copies of one screenshot then merge into the one with text, and OCR only runs if none has any
+    entries.sort_by_key(|entry| entry.text.trim().is_empty());
+    let mut by_hash = entries_by_content_hash(conn)?;
+    let rules = exclusion_rules(conn);
+    let mut summary = ImportSummary::default();
+
+    for (i, entry) in entries.iter().enumerate() {
+        println!("[IMPORT] [{}/{}] {}", i + 1, entries.len(), entry.path);
+        let known = match &entry.content_hash {
+            Some(hash) => by_hash.get(hash).cloned(),
+            // Without a hash only the path is left; a bundle's paths are another machine's,
+            // so only a JSONL export can match on it
+            None if bundle.is_none() => load_entry_by_path(conn, &entry.path).map(|known| known.path),
+            None => None,
+        };
+        if let Some(path) = known {
+            merge_imported(conn, &path, entry)?;
+            summary.merged += 1;
+            continue;
+        }
+
+        let image = match imported_image(entry, bundle, library) {
+            Ok(Some(image)) => image,
+            Ok(None) => {
+                eprintln!("[IMPORT] No image for {}", entry.path);
+                summary.missing.push(entry.path.clone());
+                continue;
+            }
+            Err(e) => {
+                eprintln!("[IMPORT] Skipped {}: {}", entry.path, e);
+                summary.skipped += 1;
+                continue;
+            }
+        };
+        let text = Some(entry.text.as_str()).filter(|text| !text.trim().is_empty());
+        let indexed = match indexer.index_with_text(conn, &rules, &image, &entry.created_at, text) {
+            Ok(indexed) => indexed,
+            Err(e) => {
+                eprintln!("[IMPORT] Skipped {}: {}", entry.path, e);
+                false
+            }
+        };
+        if !indexed {
+            // Don't leave a copy in the library that isn't in the index
+            if bundle.is_some() {
+                let _ = fs::remove_file(&image);
+            }
+            summary.skipped += 1;
+            continue;
+        }
+        let path = image.to_string_lossy().into_owned();
+        merge_imported(conn, &path, entry)?;
+        if let Some(hash) = &entry.content_hash {
+            by_hash.insert(hash.clone(), path);
+        }
+        summary.imported += 1;
+    }
+
+    // Ours win on a name clash
+    let existing: HashSet<String> = saved_searches(conn)?.into_iter().map(|search| search.name).collect();
+    for search in searches.iter().filter(|search| !existing.contains(&search.name)) {
+        conn.execute(
+            "INSERT INTO saved_searches (name, query, collection_filter, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+            rusqlite::params![search.name, search.query, search.collection_filter, search.created_at, search.updated_at],
+        ).map_err(|e| format!("Failed to import saved search '{}': {}", search.name, e))?;
+        summary.saved_searches += 1;
+    }
+
+    println!("[IMPORT] ✅ Imported {}, merged {}, skipped {}, {} images missing, {} saved searches",
+        summary.imported, summary.merged, summary.skipped, summary.missing.len(), summary.saved_searches);
+    Ok(summary)
+}
+
+// Import a JSONL export or a bundle folder written by export_index
+#[tauri::command]
+fn import_index(app: AppHandle, source: String) -> Result<ImportSummary, String> {
+    let conn = init_database(&app)
+        .map_err(|e| format!("Database error: {}", e))?;
+    // Enrichment runs inline: a thread per entry would pile up on large imports
+    let indexer = Indexer::new(data_dir(&app), database_key(&app), Arc::new(TauriSink(app.clone())));
+    let library = library_dir(&app);
+    import_from(&indexer, &conn, Path::new(&source), library.as_deref())
+}
+
+// ============== EXCLUSIONS ==============
+
+const EXCLUSION_RULES_KEY: &str = "exclusion_rules";
+
+fn load_exclusion_rules(app: &AppHandle) -> exclusions::Rules {
+    match init_database(app) {
+        Ok(conn) => exclusion_rules(&conn),
+        Err(e) => {
+            eprintln!("[EXCLUDE] Failed to load rules: {}", e);
+            exclusions::Rules::default()
+        }
+    }
+}
+
+fn exclusion_rules(conn: &Connection) -> exclusions::Rules {
+    let rules: Vec<exclusions::ExclusionRule> = read_setting(conn, EXCLUSION_RULES_KEY).unwrap_or_default();
+    // Rules are validated when saved, so this only fails if the setting was edited by hand
+    exclusions::Rules::compile(&rules).unwrap_or_else(|e| {
+        eprintln!("[EXCLUDE] Ignoring invalid rules: {}", e);
+        exclusions::Rules::default()
+    })
+}
+
+// Text to index a screenshot with, and the word boxes when OCR produced it
+type OcrText = (String, Option<Vec<WordBox>>);
+
+// OCR text for a screenshot the rules allow; None when it's excluded entirely,
+// empty text when only the image may be indexed (path rules skip OCR altogether)
+// Comes with the OCR pass's word boxes, for layout analysis
+fn ocr_unless_excluded(rules: &exclusions::Rules, path: &Path) -> Result<Option<OcrText>, String> {
+    text_unless_excluded(rules, path, None)
+}
+
+// Same, with text that's already known (imports) in place of OCR; no word boxes then
+fn text_unless_excluded(rules: &exclusions::Rules, path: &Path, known_text: Option<&str>) -> Result<Option<OcrText>, String> {
+    let exclusion = match rules.check_path(path) {
+        Some(exclusion) => Some(exclusion),
+        None => {
+            let (text, words) = match known_text {
+                Some(text) => (text.to_string(), None),
+                None => run_ocr(path).map(|(text, words)| (text, Some(words)))?,
+            };
+            match rules.check_text(&text) {
+                None => return Ok(Some((text, words))),
+                exclusion => exclusion,
+            }
+        }
+    };
+    if exclusion == Some(exclusions::Exclusion::Skip) {
+        println!("[EXCLUDE] Not indexing {}", path.display());
+        return Ok(None);
+    }
+    // No words either, so layout analysis sticks to colors instead of reading the text
+    println!("[EXCLUDE] Indexing {} without its text", path.display());
+    Ok(Some((String::new(), Some(Vec::new()))))
+}
+
+#[tauri::command]
+fn get_exclusion_rules(app: AppHandle) -> Result<Vec<exclusions::ExclusionRule>, String> {
+    let conn = init_database(&app)
+        .map_err(|e| format!("Database error: {}", e))?;
+    Ok(read_setting(&conn, EXCLUSION_RULES_KEY).unwrap_or_default())
+}
+
+// Replace all rules; they apply to screenshots indexed from now on
+#[tauri::command]
+fn set_exclusion_rules(app: AppHandle, rules: Vec<exclusions::ExclusionRule>) -> Result<(), String> {
+    exclusions::Rules::compile(&rules)?;
+    let conn = init_database(&app)
+        .map_err(|e| format!("Database error: {}", e))?;
+    write_setting(&conn, EXCLUSION_RULES_KEY, &rules)?;
+    println!("[EXCLUDE] ✅ Saved {} exclusion rules", rules.len());
+    Ok(())
+}
+
+// ============== SECRETS ==============
+
+// What happens to screenshots of one-time codes once they expire: "off", "trash" or "redact"
+const OTP_EXPIRY_ACTION_KEY: &str = "otp_expiry_action";
+// Minutes after capture before an OTP screenshot expires
+const OTP_EXPIRY_MINUTES_KEY: &str = "otp_expiry_minutes";
+const DEFAULT_OTP_EXPIRY_MINUTES: u64 = 15;
+// Stands in for redacted secrets in the stored OCR text
+const REDACTED_TEXT: &str = "[redacted]";
+// Stands in for the text of sensitive entries handed to other programs (MCP, the local API)
+const WITHHELD_TEXT: &str = "[withheld: flagged sensitive]";
+
+#[derive(Serialize)]
+struct SecretRecord {
+    kind: String,
+    value: String,
+    start: usize,
+    end: usize,
+}
+
+// Distinct kinds of secret in the text, in order of appearance
+fn secret_kinds(text: &str) -> Vec<&'static str> {
+    let mut kinds: Vec<&'static str> = Vec::new();
+    for secret in secrets::detect_secrets(text) {
+        if !kinds.contains(&secret.kind) {
+            kinds.push(secret.kind);
+        }
+    }
+    kinds
+}
+
+fn set_secret_kinds(conn: &Connection, path: &str, kinds: &[&str]) -> SqlResult<usize> {
+    let kinds_json = serde_json::to_string(kinds).unwrap_or_else(|_| "[]".to_string());
+    conn.execute(
+        "UPDATE entries SET sensitive = ?1, sensitive_kinds = ?2 WHERE path = ?3",
+        rusqlite::params![!kinds.is_empty(), kinds_json, path],
+    )
+}
+
+// Entries saved before secret detection have no sensitive_kinds yet
+fn scan_unscanned_secrets(conn: &Connection) -> SqlResult<()> {
+    let rows: Vec<(String, String)> = {
+        let mut stmt = conn.prepare("SELECT path, text FROM entries WHERE sensitive_kinds IS NULL")?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect::<SqlResult<Vec<_>>>()?
+    };
+    if rows.is_empty() {
+        return Ok(());
+    }
+
+    let mut flagged = 0;
+    for (path, text) in &rows {
+        let kinds = secret_kinds(text);
+        if !kinds.is_empty() {
+            flagged += 1;
+        }
+        set_secret_kinds(conn, path, &kinds)?;
+    }
+    println!("[SECRETS] Scanned {} entries, {} sensitive", rows.len(), flagged);
+    Ok(())
+}
+
+// Black out secrets of the given kinds in the screenshot itself and in its stored text
+fn redact_secrets(app: &AppHandle, conn: &Connection, path: &str, kinds: &[&str]) -> Result<usize, String> {
+    let text: String = conn.query_row(
+        "SELECT text FROM entries WHERE path = ?1",
+        rusqlite::params![path],
+        |row| row.get(0),
+    ).map_err(|e| format!("Entry not found: {}", e))?;
+    let found: Vec<secrets::Secret> = secrets::detect_secrets(&text)
+        .into_iter()
+        .filter(|secret| kinds.contains(&secret.kind))
+        .collect();
+    if found.is_empty() {
+        return Ok(0);
+    }
+
+    // Find them again in the word boxes to know where they are in the image
+    let file = Path::new(path);
+    let words = ocr_word_boxes(file)?;
+    let layout = redact::Layout::new(&words);
+    let boxes = layout.boxes(&redact::find_spans(layout.text(), kinds, &[]));
+    if boxes.is_empty() {
+        return Err(format!("Couldn't locate the {} in the image", kinds.join("/")));
+    }
+    let ignore_map = Arc::clone(&app.state::<WatcherIgnoreState>().0);
+    remember_ignore(&ignore_map, file);
+    redact::black_out(file, file, &boxes)?;
+    if let Err(e) = record_file_operation(conn, file, file, FILE_OP_REDACT) {
+        eprintln!("[FILEOPS] {}", e);
+    }
+    rehash_redacted_image(app, conn, path)?;
+
+    // Back to front so earlier spans keep their offsets
+    let mut redacted = text.clone();
+    for secret in found.iter().rev() {
+        redacted.replace_range(secret.start..secret.end, REDACTED_TEXT);
+    }
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs().to_string();
+    // secure_delete (set in open_index) zeroes the pages that held the unredacted text
+    conn.execute(
+        "UPDATE entries SET text = ?1, updated_at = ?2 WHERE path = ?3",
+        rusqlite::params![redacted, now, path],
+    ).map_err(|e| format!("Failed to update entry: {}", e))?;
+    save_entities(conn, path, &redacted)
+        .map_err(|e| format!("Failed to update entities: {}", e))?;
+    set_secret_kinds(conn, path, &secret_kinds(&redacted))
+        .map_err(|e| format!("Failed to update sensitive flag: {}", e))?;
+
+    println!("[SECRETS] ✅ Redacted {} secrets in {}", found.len(), path);
+    Ok(found.len())
+}
+
+// The image changed under the entry: its hashes and cached visual analysis describe the old pixels
+fn rehash_redacted_image(app: &AppHandle, conn: &Connection, path: &str) -> Result<(), String> {
+    let file = Path::new(path);
+    let perceptual_hash = compute_perceptual_hash(file).ok();
+    let (phash, block_hash) = match compute_similarity_hashes(file) {
+        Ok((phash, block_hash)) => (Some(phash), Some(block_hash)),
+        Err(_) => (None, None),
+    };
+    conn.execute(
+        "UPDATE entries SET content_hash = ?1, perceptual_hash = ?2, phash = ?3, block_hash = ?4,
+            visual_features = NULL, clip_scores = NULL, clip_embedding = NULL
+         WHERE path = ?5",
+        rusqlite::params![content_hash(file).ok(), perceptual_hash, phash, block_hash, path],
+    ).map_err(|e| format!("Failed to update hashes: {}", e))?;
+    match &perceptual_hash {
+        Some(hash) => index_hash(app, path, hash),
+        None => unindex_hash(app, path),
+    }
+    Ok(())
+}
+
+// Trash or redact OTP screenshots older than the expiry window (pinned ones are kept)
+fn expire_one_time_codes(app: &AppHandle) -> Result<usize, String> {
+    let conn = init_database(app)
+        .map_err(|e| format!("Database error: {}", e))?;
+    let action: String = read_setting(&conn, OTP_EXPIRY_ACTION_KEY).unwrap_or_else(|| "off".to_string());
+    if action == "off" {
+        return Ok(0);
+    }
+    let minutes: u64 = read_setting(&conn, OTP_EXPIRY_MINUTES_KEY).unwrap_or(DEFAULT_OTP_EXPIRY_MINUTES);
+    let now_ms = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as u64;
+    let cutoff = now_ms.saturating_sub(minutes * 60 * 1000);
+
+    let paths: Vec<String> = {
+        let mut stmt = conn.prepare(
+            "SELECT path FROM entries
+             WHERE COALESCE(trashed, 0) = 0 AND COALESCE(pinned, 0) = 0
+               AND sensitive_kinds LIKE '%\"otp\"%' AND CAST(created_at AS INTEGER) < ?1"
+        ).map_err(|e| format!("Query error: {}", e))?;
+        let rows = stmt.query_map(rusqlite::params![cutoff as i64], |row| row.get(0))
+            .map_err(|e| format!("Query map error: {}", e))?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Row error: {}", e))?
+    };
+    if paths.is_empty() {
+        return Ok(0);
+    }
+
+    let expired = match action.as_str() {
+        "trash" => {
+            let result = delete_files(app.clone(), paths)?;
+            for failure in &result.failed {
+                eprintln!("[SECRETS] Failed to trash {}", failure);
+            }
+            result.deleted.len()
+        }
+        "redact" => paths
+            .iter()
+            .filter(|path| match redact_secrets(app, &conn, path, &["otp"]) {
+                Ok(_) => true,
+                Err(e) => {
+                    eprintln!("[SECRETS] Failed to redact {}: {}", path, e);
+                    false
+                }
+            })
+            .count(),
+        other => return Err(format!("Unknown OTP expiry action '{}'", other)),
+    };
+    if expired > 0 {
+        println!("[SECRETS] ✅ Expired {} one-time code screenshots ({})", expired, action);
+    }
+    Ok(expired)
+}
+
+fn is_sensitive_entry(conn: &Connection, path: &str) -> bool {
+    conn.query_row(
+        "SELECT COALESCE(sensitive, 0) FROM entries WHERE path = ?1",
+        rusqlite::params![path],
+        |row| row.get::<_, i64>(0),
+    ).map(|sensitive| sensitive == 1).unwrap_or(false)
+}
+
+// Overwrite a file with zeros before unlinking it so its pixels don't linger in free blocks
+// Best effort: SSDs and copy-on-write filesystems (APFS) may still hold the old blocks
+fn shred_file(path: &Path) -> std::io::Result<()> {
+    use std::io::Write;
+
+    let length = fs::metadata(path)?.len();
+    let mut file = fs::OpenOptions::new().write(true).open(path)?;
+    let zeros = [0u8; 64 * 1024];
+    let mut remaining = length;
+    while remaining > 0 {
+        let chunk = remaining.min(zeros.len() as u64) as usize;
+        file.write_all(&zeros[..chunk])?;
+        remaining -= chunk as u64;
+    }
+    file.sync_all()?;
+    drop(file);
+    fs::remove_file(path)
+}
+
+// Journal rows for every name the file has had; slugged names carry words from its text
+fn purge_file_history(conn: &Connection, path: &str) -> SqlResult<usize> {
+    let mut pending = vec![path.to_string()];
+    let mut purged = 0;
+    while let Some(current) = pending.pop() {
+        let earlier: Vec<String> = {
+            let mut stmt = conn.prepare("SELECT old_path FROM file_operations WHERE new_path = ?1")?;
+            let rows = stmt.query_map(rusqlite::params![current], |row| row.get(0))?;
+            rows.collect::<SqlResult<Vec<_>>>()?
+        };
+        // Deleting before following the chain also ends any rename cycles
+        purged += conn.execute(
+            "DELETE FROM file_operations WHERE new_path = ?1 OR old_path = ?1",
+            rusqlite::params![current],
+        )?;
+        pending.extend(earlier);
+    }
+    Ok(purged)
+}
+
+// Secrets found in one screenshot's text, in reading order
+#[tauri::command]
+fn get_secrets(app: AppHandle, path: String) -> Result<Vec<SecretRecord>, String> {
+    let conn = init_database(&app)
+        .map_err(|e| format!("Database error: {}", e))?;
+    let text: String = conn.query_row(
+        "SELECT text FROM entries WHERE path = ?1",
+        rusqlite::params![path],
+        |row| row.get(0),
+    ).map_err(|e| format!("Entry not found: {}", e))?;
+    Ok(secrets::detect_secrets(&text)
+        .into_iter()
+        .map(|secret| SecretRecord {
+            kind: secret.kind.to_string(),
+            value: text[secret.start..secret.end].to_string(),
+            start: secret.start,
+            end: secret.end,
+        })
+        .collect())
+}
+
+// Override the flag for a false positive (or a secret detection missed); re-OCR recomputes it
+#[tauri::command]
+fn set_sensitive(app: AppHandle, path: String, sensitive: bool) -> Result<(), String> {
+    let conn = init_database(&app)
+        .map_err(|e| format!("Database error: {}", e))?;
+    let updated = conn.execute(
+        "UPDATE entries SET sensitive = ?1 WHERE path = ?2",
+        rusqlite::params![sensitive, path],
+    ).map_err(|e| format!("Failed to update entry: {}", e))?;
+    if updated == 0 {
+        return Err(format!("Entry not found: {}", path));
+    }
+    println!("[SECRETS] ✅ Marked {} as {}", path, if sensitive { "sensitive" } else { "not sensitive" });
+    Ok(())
+}
+
+// ============== REDACTION ==============
+
+#[derive(Serialize)]
+struct RedactResult {
+    // The redacted copy; None when it went to the clipboard
+    path: Option<String>,
+    // Words blacked out
+    regions: usize,
+}
+
+// photo.png -> Library/Redacted/photo-redacted.png, numbered on collision
+// Outside the watch folders, so the copy is never indexed
+fn redacted_copy_path(library: &Path, source: &Path) -> Result<PathBuf, String> {
+    let stem = source.file_stem()
+        .and_then(|s| s.to_str())
+        .ok_or_else(|| format!("Invalid file name: {}", source.display()))?;
+    let dir = library.join("Redacted");
+    (1..)
+        .map(|n| match n {
+            1 => dir.join(format!("{}-redacted.png", stem)),
+            n => dir.join(format!("{}-redacted-{}.png", stem, n)),
+        })
+        .find(|candidate| !candidate.exists())
+        .ok_or_else(|| format!("No free name for a redacted copy of {}", source.display()))
+}
+
+// Copy of a screenshot with emails, phone numbers, names, secrets, ... blacked out, for sharing
+// `kinds` are entity kinds, secret kinds or "name"; `patterns` are extra regexes to black out
+#[tauri::command]
+fn redact_entry(
+    app: AppHandle,
+    path: String,
+    kinds: Vec<String>,
+    patterns: Option<Vec<String>>,
+    to_clipboard: Option<bool>,
+) -> Result<RedactResult, String> {
+    let source = PathBuf::from(&path);
+    if !source.exists() {
+        return Err(format!("File does not exist: {}", path));
+    }
+    if let Some(kind) = kinds.iter().find(|kind| !redact::is_kind(kind)) {
+        return Err(format!("Unknown redaction kind '{}'", kind));
+    }
+    let patterns = patterns
+        .unwrap_or_default()
+        .iter()
+        .map(|pattern| Regex::new(pattern).map_err(|e| format!("Invalid pattern '{}': {}", pattern, e)))
+        .collect::<Result<Vec<_>, _>>()?;
+    if kinds.is_empty() && patterns.is_empty() {
+        return Err("Nothing to redact: pick at least one kind or pattern".to_string());
+    }
+
+    let words = ocr_word_boxes(&source)?;
+    let layout = redact::Layout::new(&words);
+    let kinds: Vec<&str> = kinds.iter().map(String::as_str).collect();
+    let boxes = layout.boxes(&redact::find_spans(layout.text(), &kinds, &patterns));
+
+    if to_clipboard.unwrap_or(false) {
+        let temp = std::env::temp_dir().join(format!("chronicle-redacted-{}.png", now_millis_string()));
+        redact::black_out(&source, &temp, &boxes)?;
+        let copied = copy_image_to_clipboard(temp.to_string_lossy().to_string());
+        let _ = fs::remove_file(&temp);
+        copied?;
+        println!("[REDACT] ✅ Copied {} with {} words blacked out", path, boxes.len());
+        return Ok(RedactResult { path: None, regions: boxes.len() });
+    }
+
+    let library = library_dir(&app).ok_or_else(|| "Library directory not available".to_string())?;
+    let dest = redacted_copy_path(&library, &source)?;
+    if let Some(dir) = dest.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    }
+    redact::black_out(&source, &dest, &boxes)?;
+    println!("[REDACT] ✅ Wrote {} with {} words blacked out", dest.display(), boxes.len());
+    Ok(RedactResult { path: Some(dest.to_string_lossy().to_string()), regions: boxes.len() })
+}
+
+// ============== ENCRYPTION ==============
+
+// Encryption is opt-in: chronicle.db is plain SQLite until encrypt_database runs
+// The key only ever lives in memory; encryption.json (next to the database, since settings are
+// inside it) records whether a passphrase or a keyfile unlocks it, and the keyfile's path
+const ENCRYPTION_CONFIG_FILE: &str = "encryption.json";
+const MIN_PASSPHRASE_LENGTH: usize = 8;
+const MIN_KEYFILE_BYTES: u64 = 32;
+// Every plaintext SQLite file starts with this; SQLCipher files look like random bytes
+const SQLITE_HEADER: &[u8; 16] = b"SQLite format 3\0";
+
+// Held (shared) by background work for as long as it has the database open; export_database takes
+// it exclusively so nothing keeps writing to the old file after it's swapped out
+static DATABASE_SWAP: RwLock<()> = RwLock::new(());
+
+pub(crate) fn database_in_use() -> RwLockReadGuard<'static, ()> {
+    DATABASE_SWAP.read().unwrap_or_else(|e| e.into_inner())
+}
+
+// SQLCipher key for this session, set by encrypt_database / unlock_database
+#[derive(Default)]
+struct DatabaseKeyState(Mutex<Option<String>>);
+
+#[derive(Clone, Default, Serialize, serde::Deserialize)]
+struct EncryptionConfig {
+    // "passphrase" or "keyfile"
+    method: String,
+    keyfile: Option<String>,
+}
+
+#[derive(Serialize)]
+struct DatabaseStatus {
+    encrypted: bool,
+    unlocked: bool,
+    // "passphrase" or "keyfile" when encrypted
+    method: Option<String>,
+    keyfile: Option<String>,
+}
+
+fn encryption_config_path(data_dir: &Path) -> PathBuf {
+    data_dir.join(ENCRYPTION_CONFIG_FILE)
+}
+
+fn load_encryption_config(data_dir: &Path) -> Option<EncryptionConfig> {
+    let json = fs::read_to_string(encryption_config_path(data_dir)).ok()?;
+    serde_json::from_str(&json).ok()
+}
+
+fn database_key(app: &AppHandle) -> Option<String> {
+    app.try_state::<DatabaseKeyState>()
+        .and_then(|state| state.0.lock().unwrap().clone())
+}
+
+fn set_database_key(app: &AppHandle, key: Option<String>) {
+    if let Some(state) = app.try_state::<DatabaseKeyState>() {
+        *state.0.lock().unwrap() = key;
+    }
+}
+
+// Judged by the file itself rather than encryption.json, which could be stale or deleted
+fn is_encrypted_file(path: &Path) -> bool {
+    let mut header = [0u8; 16];
+    match fs::File::open(path).and_then(|mut file| std::io::Read::read_exact(&mut file, &mut header)) {
+        Ok(_) => &header != SQLITE_HEADER,
+        // Missing or empty: a fresh plain database gets created
+        Err(_) => false,
+    }
+}
+
+fn database_locked(app: &AppHandle) -> bool {
+    database_key(app).is_none() && is_encrypted_file(&get_db_path(app))
+}
+
+// Open the database, keyed when it's encrypted; the key has to be set before anything reads the file
+fn open_database(db_path: &Path, key: Option<String>) -> SqlResult<Connection> {
+    if key.is_none() && is_encrypted_file(db_path) {
+        return Err(rusqlite::Error::SqliteFailure(
+            rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_AUTH),
+            Some("Database is encrypted - unlock it with your passphrase or keyfile".to_string()),
+        ));
+    }
+    let conn = Connection::open(db_path)?;
+    if let Some(key) = key {
+        conn.pragma_update(None, "key", key)?;
+    }
+    // The app, the CLI and background threads share the file: wait out each other's writes
+    conn.busy_timeout(Duration::from_secs(5))?;
+    Ok(conn)
+}
+
+// Passphrase or keyfile -> SQLCipher key (keyfiles are hex-encoded and go through the same KDF)
+fn derive_database_key(passphrase: Option<String>, keyfile: Option<String>) -> Result<(String, EncryptionConfig), String> {
+    match (passphrase, keyfile) {
+        (Some(passphrase), None) => {
+            if passphrase.chars().count() < MIN_PASSPHRASE_LENGTH {
+                return Err(format!("Passphrase must be at least {} characters", MIN_PASSPHRASE_LENGTH));
+            }
+            Ok((passphrase, EncryptionConfig { method: "passphrase".to_string(), keyfile: None }))
+        }
+        (None, Some(keyfile)) => {
+            let bytes = fs::read(&keyfile)
+                .map_err(|e| format!("Failed to read keyfile {}: {}", keyfile, e))?;
+            if (bytes.len() as u64) < MIN_KEYFILE_BYTES {
+                return Err(format!("Keyfile must be at least {} bytes", MIN_KEYFILE_BYTES));
+            }
+            let key = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+            Ok((key, EncryptionConfig { method: "keyfile".to_string(), keyfile: Some(keyfile) }))
+        }
+        _ => Err("Provide either a passphrase or a keyfile".to_string()),
+    }
+}
+
+fn require_sqlcipher(conn: &Connection) -> Result<(), String> {
+    conn.query_row("PRAGMA cipher_version", [], |row| row.get::<_, String>(0))
+        .map(|_| ())
+        .map_err(|_| "This build of Chronicle doesn't include SQLCipher".to_string())
+}
+
+// Re-export the whole database (schema, FTS, settings, ...) into a file with a new key ('' = plaintext),
+// then swap it in for chronicle.db
+// Callers restart the API server afterwards, with the new key
+fn export_database(app: &AppHandle, conn: Connection, new_key: &str) -> Result<(), String> {
+    // Its connection stays open between requests; indexing and maintenance finish what they're doing
+    stop_api_server(app);
+    let _swap = DATABASE_SWAP.write().unwrap_or_else(|e| e.into_inner());
+    let db_path = get_db_path(app);
+    let temp_path = db_path.with_extension("db.tmp");
+    let _ = fs::remove_file(&temp_path);
+
+    conn.execute(
+        "ATTACH DATABASE ?1 AS export KEY ?2",
+        rusqlite::params![temp_path.to_string_lossy(), new_key],
+    ).map_err(|e| format!("Failed to create new database: {}", e))?;
+    let exported = conn.query_row("SELECT sqlcipher_export('export')", [], |_| Ok(()));
+    let detached = conn.execute("DETACH DATABASE export", []);
+    drop(conn);
+    if let Err(e) = exported.and(detached.map(|_| ())) {
+        let _ = fs::remove_file(&temp_path);
+        return Err(format!("Failed to export database: {}", e));
+    }
+
+    fs::rename(&temp_path, &db_path)
+        .map_err(|e| format!("Failed to replace {}: {}", db_path.display(), e))
+}
+
+// Work that reads the index from the start; waits for unlock when the database is encrypted
+fn start_background_work(app: &AppHandle) {
+    static STARTED: std::sync::Once = std::sync::Once::new();
+    STARTED.call_once(|| {
+        start_watcher(app.clone());
+        start_maintenance(app.clone());
+        allow_library_assets(app);
+        start_startup_reprocessing(app.clone());
+        restart_api_server(app);
+    });
+}
+
+// Keyfile databases unlock on their own when the keyfile is where it was
+fn unlock_with_keyfile(app: &AppHandle) -> bool {
+    let Some(keyfile) = load_encryption_config(&data_dir(app)).and_then(|config| config.keyfile) else {
+        return false;
+    };
+    match unlock_database(app.clone(), None, Some(keyfile.clone())) {
+        Ok(_) => true,
+        Err(e) => {
+            eprintln!("[DB] 🔒 Couldn't unlock with keyfile {}: {}", keyfile, e);
+            false
+        }
+    }
+}
+
+#[tauri::command]
+fn get_database_status(app: AppHandle) -> DatabaseStatus {
+    let encrypted = is_encrypted_file(&get_db_path(&app));
+    let config = load_encryption_config(&data_dir(&app)).filter(|_| encrypted);
+    DatabaseStatus {
+        encrypted,
+        unlocked: !encrypted || database_key(&app).is_some(),
+        method: config.as_ref().map(|config| config.method.clone()),
+        keyfile: config.and_then(|config| config.keyfile),
+    }
+}
+
+#[tauri::command]
+fn unlock_database(app: AppHandle, passphrase: Option<String>, keyfile: Option<String>) -> Result<(), String> {
+    let db_path = get_db_path(&app);
+    if !is_encrypted_file(&db_path) {
+        return Err("Database isn't encrypted".to_string());
+    }
+    let (key, _) = derive_database_key(passphrase, keyfile)?;
+
+    // A wrong key only shows up on the first read
+    let conn = Connection::open(&db_path)
+        .map_err(|e| format!("Database error: {}", e))?;
+    conn.pragma_update(None, "key", &key)
+        .map_err(|e| format!("Failed to set key: {}", e))?;
+    conn.query_row("SELECT COUNT(*) FROM sqlite_master", [], |row| row.get::<_, i64>(0))
+        .map_err(|_| "Wrong passphrase or keyfile".to_string())?;
+    drop(conn);
+
+    set_database_key(&app, Some(key));
+    println!("[DB] 🔓 Database unlocked");
+    start_background_work(&app);
+    Ok(())
+}
+
+// Encrypt the existing (plain) database in place
+#[tauri::command]
+fn encrypt_database(app: AppHandle, passphrase: Option<String>, keyfile: Option<String>) -> Result<(), String> {
+    if is_encrypted_file(&get_db_path(&app)) {
+        return Err("Database is already encrypted".to_string());
+    }
+    let (key, config) = derive_database_key(passphrase, keyfile)?;
+    let conn = init_database(&app)
+        .map_err(|e| format!("Database error: {}", e))?;
+    require_sqlcipher(&conn)?;
+
+    let config_json = serde_json::to_string_pretty(&config)
+        .map_err(|e| format!("Failed to serialize encryption config: {}", e))?;
+    fs::write(encryption_config_path(&data_dir(&app)), config_json)
+        .map_err(|e| format!("Failed to save encryption config: {}", e))?;
+    if let Err(e) = export_database(&app, conn, &key) {
+        let _ = fs::remove_file(encryption_config_path(&data_dir(&app)));
+        restart_api_server(&app);
+        return Err(e);
+    }
+    set_database_key(&app, Some(key));
+    restart_api_server(&app);
+
+    println!("[DB] 🔒 Database encrypted ({})", config.method);
+    Ok(())
+}
+
+// Turn encryption back off; the database has to be unlocked
+#[tauri::command]
+fn decrypt_database(app: AppHandle) -> Result<(), String> {
+    if !is_encrypted_file(&get_db_path(&app)) {
+        return Err("Database isn't encrypted".to_string());
+    }
+    let conn = init_database(&app)
+        .map_err(|e| format!("Database error: {}", e))?;
+    if let Err(e) = export_database(&app, conn, "") {
+        restart_api_server(&app);
+        return Err(e);
+    }
+    set_database_key(&app, None);
+    let _ = fs::remove_file(encryption_config_path(&data_dir(&app)));
+    restart_api_server(&app);
+
+    println!("[DB] 🔓 Database decrypted");
+    Ok(())
+}
+
+// ============== LOCAL API ==============
+
+const API_ENABLED_KEY: &str = "api_enabled";
+const API_PORT_KEY: &str = "api_port";
+const API_TOKEN_KEY: &str = "api_token";
+const DEFAULT_API_PORT: u16 = 7420;
+
+// The running local API server, if it's turned on
+#[derive(Default)]
+struct ApiServerState(Mutex<Option<api::ApiServer>>);
+
+#[derive(Serialize)]
+struct ApiSettings {
+    enabled: bool,
+    port: u16,
+    token: Option<String>,
+    running: bool,
+}
+
+fn load_api_settings(app: &AppHandle, conn: &Connection) -> ApiSettings {
+    ApiSettings {
+        enabled: read_setting(conn, API_ENABLED_KEY).unwrap_or(false),
+        port: read_setting(conn, API_PORT_KEY).unwrap_or(DEFAULT_API_PORT),
+        token: read_setting(conn, API_TOKEN_KEY),
+        running: app.state::<ApiServerState>().0.lock().unwrap().is_some(),
+    }
+}
+
+fn stop_api_server(app: &AppHandle) {
+    if let Some(running) = app.state::<ApiServerState>().0.lock().unwrap().take() {
+        running.stop();
+    }
+}
+
+// Stop the server and start it again with the current settings and database key (off stays off)
+fn restart_api_server(app: &AppHandle) {
+    stop_api_server(app);
+    let state = app.state::<ApiServerState>();
+    let mut server = state.0.lock().unwrap();
+
+    let conn = match init_database(app) {
+        Ok(conn) => conn,
+        Err(e) => {
+            eprintln!("[API] Database error: {}", e);
+            return;
+        }
+    };
+    let enabled = read_setting(&conn, API_ENABLED_KEY).unwrap_or(false);
+    let (true, Some(token)) = (enabled, read_setting::<String>(&conn, API_TOKEN_KEY)) else {
+        return;
+    };
+    let port = read_setting(&conn, API_PORT_KEY).unwrap_or(DEFAULT_API_PORT);
+    match api::ApiServer::start(indexer(app), port, token) {
+        Ok(started) => *server = Some(started),
+        Err(e) => eprintln!("[API] ❌ {}", e),
+    }
+}
+
+#[tauri::command]
+fn get_api_settings(app: AppHandle) -> Result<ApiSettings, String> {
+    let conn = init_database(&app)
+        .map_err(|e| format!("Database error: {}", e))?;
+    Ok(load_api_settings(&app, &conn))
+}
+
+// Turning the API on for the first time creates its token
+#[tauri::command]
+fn set_api_settings(app: AppHandle, enabled: bool, port: Option<u16>) -> Result<ApiSettings, String> {
+    let conn = init_database(&app)
+        .map_err(|e| format!("Database error: {}", e))?;
+    if port == Some(0) {
+        return Err("Port must be between 1 and 65535".to_string());
+    }
+    write_setting(&conn, API_ENABLED_KEY, &enabled)?;
+    if let Some(port) = port {
+        write_setting(&conn, API_PORT_KEY, &port)?;
+    }
+    if enabled && read_setting::<String>(&conn, API_TOKEN_KEY).is_none() {
+        write_setting(&conn, API_TOKEN_KEY, &api::generate_token()?)?;
+    }
+
+    restart_api_server(&app);
+    let settings = load_api_settings(&app, &conn);
+    if enabled && !settings.running {
+        return Err(format!("Couldn't start the API on port {} - see the log", settings.port));
+    }
+    println!("[API] ✅ {}", if enabled { "Enabled" } else { "Disabled" });
+    Ok(settings)
+}
+
+// A new token locks out every client that had the old one
+#[tauri::command]
+fn regenerate_api_token(app: AppHandle) -> Result<ApiSettings, String> {
+    let conn = init_database(&app)
+        .map_err(|e| format!("Database error: {}", e))?;
+    write_setting(&conn, API_TOKEN_KEY, &api::generate_token()?)?;
+    restart_api_server(&app);
+    Ok(load_api_settings(&app, &conn))
+}
+
+// ============== MAINTENANCE ==============
+
+const MAINTENANCE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+// One-time codes expire within minutes, so they're checked more often than the hourly jobs
+const OTP_CHECK_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+// Background housekeeping: runs shortly after startup, then hourly (OTP expiry every few minutes)
+fn start_maintenance(app: AppHandle) {
+    tauri::async_runtime::spawn_blocking(move || {
+        // Let startup indexing get going first
+        thread::sleep(Duration::from_secs(30));
+        let mut last_hourly: Option<Instant> = None;
+        loop {
+            let in_use = database_in_use();
+            if last_hourly.is_none_or(|at| at.elapsed() >= MAINTENANCE_INTERVAL) {
+                if let Err(e) = run_auto_archive(&app) {
+                    eprintln!("[MAINTENANCE] Auto-archive failed: {}", e);
+                }
+                if let Err(e) = run_scheduled_retention(&app) {
+                    eprintln!("[MAINTENANCE] Retention rules failed: {}", e);
+                }
+                if let Err(e) = purge_expired_trash(&app) {
+                    eprintln!("[MAINTENANCE] Trash purge failed: {}", e);
+                }
+                last_hourly = Some(Instant::now());
+            }
+            if let Err(e) = expire_one_time_codes(&app) {
+                eprintln!("[MAINTENANCE] OTP expiry failed: {}", e);
+            }
+            drop(in_use);
+            thread::sleep(OTP_CHECK_INTERVAL);
+        }
+    });
+}
@@ -0,0 +1,263 @@
+// MCP (Model Context Protocol) server over stdio, so local assistants can look screenshots up
+// Newline-delimited JSON-RPC 2.0: one request per line on stdin, one response per line on stdout
+//
+//   search_screenshots   query language, semantic or hybrid search
+//   get_screenshot_text  OCR text, tags and entities of one screenshot
+//   list_tags            every tag with its screenshot count
+//   find_similar         near-duplicates and lookalikes of one screenshot
+//
+// Text of screenshots flagged sensitive (codes, keys, passwords) is never handed out
+
+use crate::{
+    format_millis, load_entry_by_path, load_entry_details, parse_json_list, query, query_shared_entry_paths,
+    ranked_search, similar_entries, tag_counts, DbEntry, WITHHELD_TEXT,
+};
+use rusqlite::Connection;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::io::BufRead;
+use std::path::Path;
+
+const PROTOCOL_VERSION: &str = "2025-06-18";
+const DEFAULT_LIMIT: usize = 10;
+const MAX_LIMIT: usize = 100;
+// Search results carry a preview; get_screenshot_text has the whole text
+const PREVIEW_CHARS: usize = 300;
+
+// JSON-RPC error codes
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+// Answer requests until stdin closes
+pub(crate) fn serve(data_dir: &Path, conn: &Connection, input: impl BufRead, mut send: impl FnMut(&Value)) {
+    eprintln!("[MCP] ✅ Serving on stdio");
+    for line in input.lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(e) => {
+                eprintln!("[MCP] Failed to read stdin: {}", e);
+                break;
+            }
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(response) = handle_message(data_dir, conn, &line) {
+            send(&response);
+        }
+    }
+}
+
+// None for notifications, which get no response
+fn handle_message(data_dir: &Path, conn: &Connection, line: &str) -> Option<Value> {
+    let message: Value = match serde_json::from_str(line) {
+        Ok(message) => message,
+        Err(e) => return Some(error_response(Value::Null, PARSE_ERROR, format!("Invalid JSON: {}", e))),
+    };
+    let id = message.get("id").cloned();
+    let Some(method) = message.get("method").and_then(Value::as_str) else {
+        // Responses to requests we never send
+        return id.map(|id| error_response(id, INVALID_REQUEST, "Missing method".to_string()));
+    };
+    let id = id?;
+    let params = message.get("params").cloned().unwrap_or(Value::Null);
+
+    let result = match method {
+        "initialize" => Ok(initialize(&params)),
+        "ping" => Ok(json!({})),
+        "tools/list" => Ok(json!({ "tools": tool_definitions() })),
+        "tools/call" => call_tool(data_dir, conn, &params),
+        other => Err((METHOD_NOT_FOUND, format!("Unknown method '{}'", other))),
+    };
+    Some(match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err((code, message)) => error_response(id, code, message),
+    })
+}
+
+fn error_response(id: Value, code: i64, message: String) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+fn initialize(params: &Value) -> Value {
+    // Newer clients still speak the version we offer; use theirs only if it's older
+    let version = params
+        .get("protocolVersion")
+        .and_then(Value::as_str)
+        .filter(|requested| *requested < PROTOCOL_VERSION)
+        .unwrap_or(PROTOCOL_VERSION);
+    json!({
+        "protocolVersion": version,
+        "capabilities": { "tools": {} },
+        "serverInfo": { "name": "chronicle", "version": env!("CARGO_PKG_VERSION") },
+        "instructions": "Search the user's screenshot library by OCR text, tags and dates. Paths returned by one tool can be passed to the others."
+    })
+}
+
+fn tool_definitions() -> Value {
+    json!([
+        {
+            "name": "search_screenshots",
+            "description": "Search screenshots by their OCR text. In the default \"query\" mode every word must appear, and filters narrow it down: tag:Receipts, newer:1d (also 2w, 3m, 1y), older:30d, is:pinned, has:url, has:email, \"exact phrase\", -word to exclude. \"semantic\" ranks by meaning and \"hybrid\" mixes meaning and keywords; both need the optional embedding model, and hybrid falls back to keywords without it. Returns the newest matches first, with a text preview.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "query": { "type": "string", "description": "Words and filters, e.g. \"error newer:1d\"" },
+                    "mode": { "type": "string", "enum": ["query", "semantic", "hybrid"], "default": "query" },
+                    "limit": { "type": "integer", "minimum": 1, "maximum": MAX_LIMIT, "default": DEFAULT_LIMIT }
+                },
+                "required": ["query"]
+            }
+        },
+        {
+            "name": "get_screenshot_text",
+            "description": "The full OCR text of one screenshot, with its date, tags and detected entities (phone numbers, amounts, dates, ...).",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "path": { "type": "string", "description": "Screenshot path as returned by search_screenshots" }
+                },
+                "required": ["path"]
+            }
+        },
+        {
+            "name": "list_tags",
+            "description": "Every tag in the library (automatic and user-added) with how many screenshots have it, most used first.",
+            "inputSchema": { "type": "object", "properties": {} }
+        },
+        {
+            "name": "find_similar",
+            "description": "Screenshots that look like the given one: near-duplicates, other shots of the same screen.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "path": { "type": "string", "description": "Screenshot path as returned by search_screenshots" },
+                    "limit": { "type": "integer", "minimum": 1, "maximum": MAX_LIMIT, "default": DEFAULT_LIMIT }
+                },
+                "required": ["path"]
+            }
+        }
+    ])
+}
+
+// Tool failures are results with isError, so the assistant sees what went wrong
+fn call_tool(data_dir: &Path, conn: &Connection, params: &Value) -> Result<Value, (i64, String)> {
+    let name = params.get("name").and_then(Value::as_str)
+        .ok_or((INVALID_PARAMS, "Missing tool name".to_string()))?;
+    let arguments = params.get("arguments").cloned().unwrap_or_else(|| json!({}));
+
+    let output = match name {
+        "search_screenshots" => search_screenshots(data_dir, conn, &arguments),
+        "get_screenshot_text" => get_screenshot_text(conn, &arguments),
+        "list_tags" => tag_counts(conn).and_then(|tags| to_json(&tags)),
+        "find_similar" => find_similar(conn, &arguments),
+        other => return Err((INVALID_PARAMS, format!("Unknown tool '{}'", other))),
+    };
+    Ok(match output {
+        Ok(value) => json!({ "content": [{ "type": "text", "text": value.to_string() }] }),
+        Err(e) => json!({ "content": [{ "type": "text", "text": e }], "isError": true }),
+    })
+}
+
+#[derive(Serialize)]
+struct ScreenshotSummary {
+    path: String,
+    date: Option<String>,
+    tags: Vec<String>,
+    text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    score: Option<f32>,
+}
+
+impl ScreenshotSummary {
+    fn new(entry: DbEntry, score: Option<f32>) -> ScreenshotSummary {
+        let mut text = if entry.sensitive { WITHHELD_TEXT.to_string() } else { entry.text };
+        if let Some((cut, _)) = text.char_indices().nth(PREVIEW_CHARS) {
+            text.truncate(cut);
+            text.push('…');
+        }
+        ScreenshotSummary {
+            date: format_millis(&entry.at),
+            tags: all_tags(entry.tags, entry.custom_tags),
+            path: entry.path,
+            text,
+            score,
+        }
+    }
+}
+
+fn search_screenshots(data_dir: &Path, conn: &Connection, arguments: &Value) -> Result<Value, String> {
+    let query = string_argument(arguments, "query")?;
+    let limit = limit_argument(arguments);
+    let results: Vec<ScreenshotSummary> = match arguments.get("mode").and_then(Value::as_str).unwrap_or("query") {
+        "query" => query_shared_entry_paths(conn, &query::Query::parse(query)?)?
+            .iter()
+            .take(limit)
+            .filter_map(|path| load_entry_by_path(conn, path))
+            .map(|entry| ScreenshotSummary::new(entry, None))
+            .collect(),
+        mode @ ("semantic" | "hybrid") => ranked_search(data_dir, conn, query, limit, mode == "hybrid", true)?
+            .into_iter()
+            .map(|hit| ScreenshotSummary::new(hit.entry, Some(hit.score)))
+            .collect(),
+        other => return Err(format!("Unknown search mode '{}' (use query, semantic or hybrid)", other)),
+    };
+    to_json(&results)
+}
+
+fn get_screenshot_text(conn: &Connection, arguments: &Value) -> Result<Value, String> {
+    let details = load_entry_details(conn, string_argument(arguments, "path")?)?;
+    let entry = details.entry;
+    let sensitive = entry.sensitive;
+    Ok(json!({
+        "path": entry.path,
+        "date": format_millis(&entry.at),
+        "tags": all_tags(entry.tags, entry.custom_tags),
+        "pinned": entry.pinned,
+        "sensitive": sensitive,
+        "text": if sensitive { WITHHELD_TEXT.to_string() } else { entry.text },
+        "entities": if sensitive { json!([]) } else { to_json(&details.entities)? },
+    }))
+}
+
+fn find_similar(conn: &Connection, arguments: &Value) -> Result<Value, String> {
+    let path = string_argument(arguments, "path")?;
+    let (results, _) = similar_entries(conn, path, limit_argument(arguments))?;
+    let results: Vec<ScreenshotSummary> = results
+        .into_iter()
+        .map(|similar| ScreenshotSummary::new(similar.entry, Some(similar.score)))
+        .collect();
+    to_json(&results)
+}
+
+fn string_argument<'a>(arguments: &'a Value, name: &str) -> Result<&'a str, String> {
+    arguments
+        .get(name)
+        .and_then(Value::as_str)
+        .filter(|value| !value.trim().is_empty())
+        .ok_or_else(|| format!("Missing argument '{}'", name))
+}
+
+fn limit_argument(arguments: &Value) -> usize {
+    arguments
+        .get("limit")
+        .and_then(Value::as_u64)
+        .map(|limit| (limit as usize).clamp(1, MAX_LIMIT))
+        .unwrap_or(DEFAULT_LIMIT)
+}
+
+fn all_tags(tags: Option<String>, custom_tags: Option<String>) -> Vec<String> {
+    let mut all = parse_json_list(tags);
+    for tag in parse_json_list(custom_tags) {
+        if !all.contains(&tag) {
+            all.push(tag);
+        }
+    }
+    all
+}
+
+fn to_json<T: Serialize>(value: &T) -> Result<Value, String> {
+    serde_json::to_value(value).map_err(|e| format!("Failed to serialize result: {}", e))
+}
@@ -0,0 +1,82 @@
+// Shared ONNX Runtime plumbing for the optional model-backed features
+// Models live under <app data>/models/<name>/ and the runtime library is loaded
+// dynamically, so a missing model or runtime just means the heuristic pipeline runs
+
+use std::path::{Path, PathBuf};
+
+// Directory holding one installed model (e.g. models/clip)
+pub(crate) fn model_dir(data_dir: &Path, name: &str) -> PathBuf {
+    data_dir.join("models").join(name)
+}
+
+// Identifies an installed model file by size + mtime so cached results can be
+// invalidated when the user swaps in a different model
+pub(crate) fn model_fingerprint(model_file: &Path) -> Option<String> {
+    let metadata = std::fs::metadata(model_file).ok()?;
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0);
+    let name = model_file
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("model");
+    Some(format!("{}:{}:{}", name, metadata.len(), modified))
+}
+
+#[cfg(feature = "onnx")]
+pub(crate) fn l2_normalize(values: &mut [f32]) {
+    let norm = values.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        for v in values.iter_mut() {
+            *v /= norm;
+        }
+    }
+}
+
+// Dot product of two L2-normalized vectors
+pub(crate) fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b.iter()).map(|(x, y)| x * y).sum()
+}
+
+// Load the ONNX Runtime shared library once per process
+// ort panics if the library can't be opened, so we only initialize when we can
+// see a library file: either next to the models or via ORT_DYLIB_PATH
+#[cfg(feature = "onnx")]
+pub(crate) fn ensure_runtime(models_root: &Path) -> bool {
+    use std::sync::OnceLock;
+
+    static RUNTIME_READY: OnceLock<bool> = OnceLock::new();
+
+    *RUNTIME_READY.get_or_init(|| {
+        let bundled = ["libonnxruntime.dylib", "libonnxruntime.so", "onnxruntime.dll"]
+            .iter()
+            .map(|name| models_root.join(name))
+            .find(|path| path.exists());
+
+        let library = match bundled {
+            Some(path) => path,
+            None => match std::env::var("ORT_DYLIB_PATH") {
+                Ok(path) if Path::new(&path).exists() => PathBuf::from(path),
+                _ => {
+                    println!("[ONNX] No ONNX Runtime library found in {} or ORT_DYLIB_PATH - model features disabled",
+                             models_root.display());
+                    return false;
+                }
+            },
+        };
+
+        match ort::init_from(library.to_string_lossy()).with_name("chronicle").commit() {
+            Ok(_) => {
+                println!("[ONNX] ✅ Loaded ONNX Runtime from {}", library.display());
+                true
+            }
+            Err(e) => {
+                eprintln!("[ONNX] ❌ Failed to initialize ONNX Runtime: {}", e);
+                false
+            }
+        }
+    })
+}
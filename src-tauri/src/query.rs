@@ -0,0 +1,257 @@
+// Search query language for backend filters (retention rules, exports, ...)
+//
+//   flight delay          entries whose text or path contains every word
+//   "exact phrase"        quoted phrase, always literal: "note:1" searches for the text note:1
+//   tag:Receipts          auto or custom tag (case-insensitive)
+//   is:pinned             pinned entries
+//   is:sensitive          OCR text with one-time codes, keys, passwords or recovery phrases
+//   is:untagged           no custom tags
+//   older:90d newer:2w    age in d(ays), w(eeks), m(onths), y(ears)
+//   has:url has:email     extracted URLs / emails, has:text for any OCR text, and entity kinds:
+//                         has:phone date money iban tracking order color ip path sha crypto
+//   -term                 negates any term, e.g. -is:pinned
+//
+// Unknown filters are errors rather than text, so a typo can't turn "-has:phon" into "every entry";
+// URLs and times (https://..., 10:30) still match literally
+
+use crate::entities;
+use std::collections::HashSet;
+
+const DAY_MS: u64 = 24 * 60 * 60 * 1000;
+
+// What a query can look at for one entry
+pub(crate) struct EntryFacts<'a> {
+    pub(crate) path: &'a str,
+    pub(crate) text: &'a str,
+    pub(crate) tags: &'a [String],
+    pub(crate) custom_tags: &'a [String],
+    pub(crate) pinned: bool,
+    pub(crate) sensitive: bool,
+    pub(crate) created_at_ms: u64,
+    // Lower-cased kinds present on the entry (url, email, ...), checked by has:
+    pub(crate) has: &'a HashSet<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Term {
+    Text(String),
+    Tag(String),
+    Pinned,
+    Sensitive,
+    Untagged,
+    OlderThan(u64),
+    NewerThan(u64),
+    Has(String),
+}
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Query {
+    terms: Vec<(bool, Term)>, // (negated, term)
+}
+
+impl Query {
+    pub(crate) fn parse(input: &str) -> Result<Query, String> {
+        let mut terms = Vec::new();
+        for (token, quoted) in tokenize(input) {
+            let (negated, token) = match token.strip_prefix('-') {
+                Some(rest) if !rest.is_empty() => (true, rest.to_string()),
+                _ => (false, token),
+            };
+            let term = if quoted { Term::Text(token.to_lowercase()) } else { parse_term(&token)? };
+            terms.push((negated, term));
+        }
+        Ok(Query { terms })
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    pub(crate) fn matches(&self, entry: &EntryFacts, now_ms: u64) -> bool {
+        self.terms
+            .iter()
+            .all(|(negated, term)| term_matches(term, entry, now_ms) != *negated)
+    }
+}
+
+// Split on whitespace, keeping "quoted phrases" (optionally prefixed, e.g. -"foo bar") together
+// Each token comes with whether it had quotes
+fn tokenize(input: &str) -> Vec<(String, bool)> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut quoted = false;
+    for c in input.chars() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                quoted = true;
+            }
+            c if c.is_whitespace() && !in_quotes => {
+                if !current.is_empty() {
+                    tokens.push((std::mem::take(&mut current), quoted));
+                }
+                quoted = false;
+            }
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        tokens.push((current, quoted));
+    }
+    tokens
+}
+
+const FILTERS: [&str; 5] = ["tag", "is", "has", "older", "newer"];
+
+fn parse_term(token: &str) -> Result<Term, String> {
+    let Some((key, value)) = token.split_once(':') else {
+        // tag;x or has=url: a filter with the wrong separator
+        let lower = token.to_lowercase();
+        if let Some(filter) = FILTERS.iter().find(|filter| {
+            lower.strip_prefix(**filter).is_some_and(|rest| rest.starts_with([';', '=', '.', ',']))
+        }) {
+            return Err(format!("Invalid filter '{}' (did you mean {}:...? Quote it to search for the text)", token, filter));
+        }
+        return Ok(Term::Text(lower));
+    };
+    match key.to_lowercase().as_str() {
+        "tag" => Ok(Term::Tag(value.to_lowercase())),
+        "is" => match value.to_lowercase().as_str() {
+            "pinned" => Ok(Term::Pinned),
+            "sensitive" => Ok(Term::Sensitive),
+            "untagged" => Ok(Term::Untagged),
+            other => Err(format!("Unknown filter is:{}", other)),
+        },
+        "older" => Ok(Term::OlderThan(parse_age(value)?)),
+        "newer" => Ok(Term::NewerThan(parse_age(value)?)),
+        "has" => match value.to_lowercase().as_str() {
+            kind if kind == "text" || entities::is_kind(kind) => Ok(Term::Has(kind.to_string())),
+            other => Err(format!("Unknown filter has:{}", other)),
+        },
+        // A URL, path or time in the text - match it literally
+        key if value.starts_with(['/', '\\']) || !key.chars().all(char::is_alphabetic) => Ok(Term::Text(token.to_lowercase())),
+        key => Err(format!("Unknown filter '{}:' (quote \"{}\" to search for the text)", key, token)),
+    }
+}
+
+// "90d", "2w", "6m", "1y" -> milliseconds
+fn parse_age(value: &str) -> Result<u64, String> {
+    let value = value.trim().to_lowercase();
+    let invalid = || format!("Invalid age '{}' (use e.g. 90d, 2w, 6m, 1y)", value);
+    let (number, days_per_unit) = [("d", 1), ("w", 7), ("m", 30), ("y", 365)]
+        .iter()
+        .find_map(|(unit, days)| value.strip_suffix(unit).map(|number| (number, *days)))
+        .ok_or_else(invalid)?;
+    let count: u64 = number.parse().map_err(|_| invalid())?;
+    count
+        .checked_mul(days_per_unit * DAY_MS)
+        .ok_or_else(invalid)
+}
+
+fn term_matches(term: &Term, entry: &EntryFacts, now_ms: u64) -> bool {
+    let age = now_ms.saturating_sub(entry.created_at_ms);
+    match term {
+        Term::Text(needle) => {
+            entry.text.to_lowercase().contains(needle) || entry.path.to_lowercase().contains(needle)
+        }
+        Term::Tag(tag) => entry
+            .tags
+            .iter()
+            .chain(entry.custom_tags.iter())
+            .any(|t| t.to_lowercase() == *tag),
+        Term::Pinned => entry.pinned,
+        Term::Sensitive => entry.sensitive,
+        Term::Untagged => entry.custom_tags.is_empty(),
+        Term::OlderThan(ms) => age > *ms,
+        Term::NewerThan(ms) => age <= *ms,
+        Term::Has(kind) => match kind.as_str() {
+            "text" => !entry.text.trim().is_empty(),
+            kind => entry.has.contains(kind),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: u64 = 1_000 * DAY_MS;
+
+    fn matches(query: &str, text: &str, has: &[&str]) -> bool {
+        let has: HashSet<String> = has.iter().map(|kind| kind.to_string()).collect();
+        let tags = vec!["Receipts".to_string()];
+        let facts = EntryFacts {
+            path: "/shots/Screenshot 10.30.png",
+            text,
+            tags: &tags,
+            custom_tags: &[],
+            pinned: true,
+            sensitive: false,
+            created_at_ms: NOW - 10 * DAY_MS,
+            has: &has,
+        };
+        Query::parse(query).unwrap().matches(&facts, NOW)
+    }
+
+    #[test]
+    fn words_must_all_match_text_or_path() {
+        assert!(matches("flight delay", "Your flight has a delay", &[]));
+        assert!(!matches("flight cancelled", "Your flight has a delay", &[]));
+        assert!(matches("screenshot", "", &[]));
+    }
+
+    #[test]
+    fn quoted_phrases_are_literal() {
+        assert!(matches("\"note:1\"", "see note:1 below", &[]));
+        assert!(!matches("\"has a\"", "has  a", &[]));
+        assert!(matches("-\"gate change\"", "flight delay", &[]));
+    }
+
+    #[test]
+    fn filters() {
+        assert!(matches("tag:receipts is:pinned is:untagged", "", &[]));
+        assert!(!matches("is:sensitive", "", &[]));
+        assert!(matches("-is:sensitive", "", &[]));
+        assert!(matches("older:1w newer:2w", "", &[]));
+        assert!(!matches("older:2w", "", &[]));
+        assert!(matches("has:iban has:text", "DE89...", &["iban"]));
+        assert!(!matches("has:text", "  \n", &[]));
+    }
+
+    #[test]
+    fn urls_and_times_stay_text() {
+        assert!(matches("10:30", "boarding at 10:30", &[]));
+        assert!(matches("https://example.com", "go to https://example.com now", &[]));
+        assert!(matches("C:\\Users", "c:\\users\\me", &[]));
+    }
+
+    #[test]
+    fn typos_are_errors() {
+        for query in ["-has:phon", "is:pinnned", "tag;x", "has=url", "foo:bar", "older:90"] {
+            assert!(Query::parse(query).is_err(), "{} should not parse", query);
+        }
+        assert!(Query::parse("").unwrap().is_empty());
+        assert!(Query::parse("-").unwrap().matches(&EntryFacts {
+            path: "a-b",
+            text: "",
+            tags: &[],
+            custom_tags: &[],
+            pinned: false,
+            sensitive: false,
+            created_at_ms: 0,
+            has: &HashSet::new(),
+        }, 0));
+    }
+
+    #[test]
+    fn ages() {
+        assert_eq!(parse_age("90d"), Ok(90 * DAY_MS));
+        assert_eq!(parse_age("2W"), Ok(14 * DAY_MS));
+        assert_eq!(parse_age("6m"), Ok(180 * DAY_MS));
+        assert_eq!(parse_age("1y"), Ok(365 * DAY_MS));
+        assert!(parse_age("d").is_err());
+        assert!(parse_age("-1d").is_err());
+        assert!(parse_age("99999999999999999y").is_err());
+    }
+}
@@ -0,0 +1,295 @@
+// Receipt parsing: merchant, date, line items and totals from OCR text
+// When Tesseract word boxes are available, lines are rebuilt from them so a description
+// and its price on the far right stay on one line (plain OCR text often splits the columns)
+
+use crate::entities;
+use crate::WordBox;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct LineItem {
+    pub(crate) description: String,
+    pub(crate) quantity: Option<f64>,
+    pub(crate) amount: f64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub(crate) struct Receipt {
+    pub(crate) merchant: Option<String>,
+    // YYYY-MM-DD
+    pub(crate) date: Option<String>,
+    pub(crate) currency: Option<String>,
+    pub(crate) items: Vec<LineItem>,
+    pub(crate) subtotal: Option<f64>,
+    pub(crate) tax: Option<f64>,
+    pub(crate) total: Option<f64>,
+}
+
+// A price at the end of a line, optionally followed by a one- or two-letter tax code
+static TRAILING_AMOUNT: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(-?\s?[$€£¥₹]?\s?-?\d{1,3}(?:[,.\s]\d{3})*[.,]\d{2}|-?\s?[$€£¥₹]?\s?-?\d+[.,]\d{2})\s*(?:[A-Z]{1,2}|[$€£¥₹]|USD|EUR|GBP)?\s*$").unwrap()
+});
+// "2 x Coffee", "2x", "2 @"
+static QUANTITY: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^(\d{1,3})\s*[xX@*]\s+").unwrap());
+
+const SUBTOTAL_WORDS: &[&str] = &["subtotal", "sub total", "sub-total", "zwischensumme", "sous-total", "subtotale"];
+const TAX_WORDS: &[&str] = &["tax", "vat", "gst", "hst", "pst", "mwst", "tva", "iva", "ust"];
+const TOTAL_WORDS: &[&str] = &["total", "amount due", "balance due", "summe", "gesamt", "importe", "montant", "totale"];
+// Payment and change lines repeat the total rather than adding to it
+const PAYMENT_WORDS: &[&str] = &[
+    "cash", "change", "card", "visa", "mastercard", "amex", "debit", "credit", "tender", "paid", "payment",
+    "auth", "approval",
+];
+
+pub(crate) fn parse_receipt(text: &str, words: &[WordBox]) -> Receipt {
+    let lines = if words.is_empty() {
+        text.lines().map(|line| line.trim().to_string()).filter(|line| !line.is_empty()).collect()
+    } else {
+        lines_from_words(words)
+    };
+
+    let mut receipt = Receipt {
+        merchant: find_merchant(&lines),
+        date: find_date(text),
+        currency: find_currency(text),
+        ..Default::default()
+    };
+
+    let mut past_items = false;
+    let mut largest: Option<f64> = None;
+    for line in &lines {
+        let Some((description, amount)) = split_amount(line) else {
+            continue;
+        };
+        largest = Some(largest.map_or(amount, |largest: f64| largest.max(amount)));
+        let label = description.to_lowercase();
+
+        // "Total incl. VAT" is a total, "Sales tax" is tax
+        let starts_total = TOTAL_WORDS.iter().any(|word| label.starts_with(word));
+        if contains_word(&label, SUBTOTAL_WORDS) {
+            receipt.subtotal = Some(amount);
+            past_items = true;
+        } else if !starts_total && contains_word(&label, TAX_WORDS) {
+            receipt.tax = Some(receipt.tax.unwrap_or(0.0) + amount);
+            past_items = true;
+        } else if contains_word(&label, TOTAL_WORDS) {
+            // The first total wins; later ones are usually "total paid" or loyalty summaries
+            if receipt.total.is_none() {
+                receipt.total = Some(amount);
+            }
+            past_items = true;
+        } else if contains_word(&label, PAYMENT_WORDS) || past_items {
+            continue;
+        } else if description.chars().filter(|c| c.is_alphabetic()).count() >= 2 {
+            receipt.items.push(line_item(&description, amount));
+        }
+    }
+
+    if receipt.total.is_none() {
+        receipt.total = match (receipt.subtotal, receipt.tax) {
+            (Some(subtotal), Some(tax)) => Some(round_cents(subtotal + tax)),
+            // No total line - the largest amount on a receipt is nearly always the total
+            _ => largest,
+        };
+    }
+    receipt
+}
+
+fn round_cents(amount: f64) -> f64 {
+    (amount * 100.0).round() / 100.0
+}
+
+// Whole-word keyword match, so "tax" doesn't match "taxi"
+fn contains_word(label: &str, keywords: &[&str]) -> bool {
+    keywords.iter().any(|keyword| {
+        label.match_indices(keyword).any(|(i, _)| {
+            let before = label[..i].chars().next_back();
+            let after = label[i + keyword.len()..].chars().next();
+            !before.is_some_and(char::is_alphanumeric) && !after.is_some_and(char::is_alphanumeric)
+        })
+    })
+}
+
+fn split_amount(line: &str) -> Option<(String, f64)> {
+    let found = TRAILING_AMOUNT.captures(line)?;
+    let whole = found.get(0)?;
+    let raw = found.get(1)?.as_str();
+    let negative = raw.contains('-');
+    let digits: String = raw.chars().filter(|c| c.is_ascii_digit() || *c == '.' || *c == ',').collect();
+    let amount = entities::parse_amount(&digits)?;
+    let description = line[..whole.start()].trim().trim_end_matches([':', '.', '-']).trim().to_string();
+    Some((description, if negative { -amount } else { amount }))
+}
+
+fn line_item(description: &str, amount: f64) -> LineItem {
+    match QUANTITY.captures(description) {
+        Some(found) => LineItem {
+            description: description[found.get(0).map_or(0, |m| m.end())..].trim().to_string(),
+            quantity: found.get(1).and_then(|m| m.as_str().parse().ok()),
+            amount,
+        },
+        None => LineItem { description: description.to_string(), quantity: None, amount },
+    }
+}
+
+// Group words into lines by vertical overlap, left to right within a line
+fn lines_from_words(words: &[WordBox]) -> Vec<String> {
+    let mut sorted: Vec<&WordBox> = words.iter().filter(|word| !word.text.trim().is_empty()).collect();
+    sorted.sort_by_key(|word| (word.top, word.left));
+
+    let mut rows: Vec<(u32, u32, Vec<&WordBox>)> = Vec::new(); // (top, bottom, words)
+    for word in sorted {
+        let (top, bottom) = (word.top, word.top + word.height);
+        let middle = (top + bottom) / 2;
+        match rows.iter_mut().find(|(row_top, row_bottom, _)| middle >= *row_top && middle <= *row_bottom) {
+            Some(row) => row.2.push(word),
+            None => rows.push((top, bottom, vec![word])),
+        }
+    }
+
+    rows.into_iter()
+        .map(|(_, _, mut row)| {
+            row.sort_by_key(|word| word.left);
+            row.iter().map(|word| word.text.trim()).collect::<Vec<_>>().join(" ")
+        })
+        .collect()
+}
+
+// Store name: the first line near the top that reads like a name rather than an address or number
+fn find_merchant(lines: &[String]) -> Option<String> {
+    lines.iter().take(6).find_map(|line| {
+        let letters = line.chars().filter(|c| c.is_alphabetic()).count();
+        let digits = line.chars().filter(|c| c.is_ascii_digit()).count();
+        let lower = line.to_lowercase();
+        let boilerplate = ["receipt", "welcome", "invoice", "thank"].iter().any(|word| lower.contains(word));
+        (letters >= 3 && digits * 2 < letters && !boilerplate).then(|| line.trim().to_string())
+    })
+}
+
+fn find_date(text: &str) -> Option<String> {
+    entities::extract_entities(text)
+        .into_iter()
+        .filter(|entity| entity.kind == "date")
+        .find_map(|entity| normalize_date(&entity.value))
+}
+
+// The currency most amounts on the receipt are in
+fn find_currency(text: &str) -> Option<String> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for entity in entities::extract_entities(text) {
+        if entity.kind == "money" {
+            if let Some(code) = entity.value.split_whitespace().nth(1) {
+                *counts.entry(code.to_string()).or_default() += 1;
+            }
+        }
+    }
+    counts.into_iter().max_by_key(|(_, count)| *count).map(|(code, _)| code)
+}
+
+// Dates as found by the entity extractor -> YYYY-MM-DD
+// Numeric dates are read month-first unless that's impossible (13/10/2026)
+pub(crate) fn normalize_date(value: &str) -> Option<String> {
+    use chrono::NaiveDate;
+
+    let cleaned: String = value
+        .replace(',', " ")
+        .split_whitespace()
+        .map(|part| {
+            let part = part.trim_end_matches('.');
+            // 16th -> 16, but leave "August" alone
+            match part.len().checked_sub(2).filter(|&i| part.is_char_boundary(i)) {
+                Some(i) if i > 0 && part[..i].chars().all(|c| c.is_ascii_digit())
+                    && ["st", "nd", "rd", "th"].contains(&&part[i..]) => &part[..i],
+                _ => part,
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+    let numeric = cleaned.replace('.', "/");
+    // chrono's %Y happily reads "26" as year 26
+    let two_digit_year = numeric.contains('/') && numeric.rsplit('/').next().is_some_and(|year| year.len() == 2);
+    let formats: &[&str] = if two_digit_year {
+        &["%m/%d/%y", "%d/%m/%y"]
+    } else {
+        &["%Y-%m-%d", "%m/%d/%Y", "%d/%m/%Y", "%d %B %Y", "%d %b %Y", "%B %d %Y", "%b %d %Y"]
+    };
+    formats
+        .iter()
+        .find_map(|format| {
+            NaiveDate::parse_from_str(&cleaned, format)
+                .or_else(|_| NaiveDate::parse_from_str(&numeric, format))
+                .ok()
+        })
+        .map(|date| date.format("%Y-%m-%d").to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GROCERY: &str = "Corner Market\n12 High Street\n10/18/2026\n2 x Coffee 7.00\nBagel 3.50\nSubtotal 10.50\nSales Tax 0.84\nTotal $11.34\nVisa 11.34\nChange 0.00";
+
+    #[test]
+    fn totals_and_items() {
+        let receipt = parse_receipt(GROCERY, &[]);
+        assert_eq!(receipt.merchant.as_deref(), Some("Corner Market"));
+        assert_eq!(receipt.date.as_deref(), Some("2026-10-18"));
+        assert_eq!(receipt.currency.as_deref(), Some("USD"));
+        assert_eq!(receipt.subtotal, Some(10.5));
+        assert_eq!(receipt.tax, Some(0.84));
+        assert_eq!(receipt.total, Some(11.34));
+        assert_eq!(receipt.items.len(), 2);
+        assert_eq!(receipt.items[0].description, "Coffee");
+        assert_eq!(receipt.items[0].quantity, Some(2.0));
+        assert_eq!(receipt.items[1].amount, 3.5);
+    }
+
+    #[test]
+    fn total_falls_back_to_subtotal_plus_tax_then_largest() {
+        let receipt = parse_receipt("Cafe\nLatte 4.10\nSubtotal 4.10\nVAT 0.82", &[]);
+        assert_eq!(receipt.total, Some(4.92));
+        let receipt = parse_receipt("Kiosk\nWater 1.20\nCrisps 2.30", &[]);
+        assert_eq!(receipt.total, Some(2.3));
+    }
+
+    #[test]
+    fn first_total_wins_and_totals_incl_tax_are_totals() {
+        let receipt = parse_receipt("Shop\nItem 5.00\nTotal incl. VAT 5.00\nTotal paid 20.00", &[]);
+        assert_eq!(receipt.total, Some(5.0));
+        assert_eq!(receipt.tax, None);
+        // "taxi" isn't tax
+        let receipt = parse_receipt("Cab Co\nTaxi fare 12.00", &[]);
+        assert_eq!(receipt.items.len(), 1);
+        assert_eq!(receipt.tax, None);
+    }
+
+    #[test]
+    fn european_amounts_and_refunds() {
+        let receipt = parse_receipt("Bäckerei\nBrot 1.234,50 €\nRückgabe -2,00\nSumme 1.232,50", &[]);
+        assert_eq!(receipt.items[0].amount, 1234.5);
+        assert_eq!(receipt.items[1].amount, -2.0);
+        assert_eq!(receipt.total, Some(1232.5));
+    }
+
+    #[test]
+    fn word_boxes_rebuild_split_columns() {
+        let word = |text: &str, left, top| WordBox { text: text.to_string(), left, top, width: 40, height: 10, confidence: 90.0 };
+        let words = [word("Shop", 0, 0), word("Coffee", 0, 20), word("3.50", 300, 22), word("Total", 0, 40), word("3.50", 300, 41)];
+        let receipt = parse_receipt("Shop\nCoffee\nTotal\n3.50\n3.50", &words);
+        assert_eq!(receipt.items.len(), 1);
+        assert_eq!(receipt.items[0].description, "Coffee");
+        assert_eq!(receipt.total, Some(3.5));
+    }
+
+    #[test]
+    fn dates_normalize() {
+        assert_eq!(normalize_date("2026-10-18").as_deref(), Some("2026-10-18"));
+        assert_eq!(normalize_date("13/10/2026").as_deref(), Some("2026-10-13"));
+        assert_eq!(normalize_date("10.18.26").as_deref(), Some("2026-10-18"));
+        assert_eq!(normalize_date("18th October, 2026").as_deref(), Some("2026-10-18"));
+        assert_eq!(normalize_date("Oct 18th 2026").as_deref(), Some("2026-10-18"));
+    }
+}
@@ -0,0 +1,129 @@
+// Redaction: black out parts of a screenshot by what their text is
+// Tesseract word boxes are laid back out as text, so a match in that text maps straight
+// to the boxes it spans - no guessing where in the image an OCR'd string came from
+//
+// Kinds: any entity kind (email, phone, iban, ...), any secret kind (otp, api_key, ...),
+// and "name" for people's names next to a cue ("From: Jane Doe", "Hi Jane,") and @handles
+
+use crate::{entities, secrets, WordBox};
+use regex::Regex;
+use std::path::Path;
+use std::sync::LazyLock;
+
+pub(crate) const NAME_KIND: &str = "name";
+
+// Extra pixels blacked out around each word so anti-aliased edges don't survive
+const PADDING: u32 = 2;
+
+macro_rules! pattern {
+    ($name:ident, $re:expr) => {
+        static $name: LazyLock<Regex> = LazyLock::new(|| Regex::new($re).unwrap());
+    };
+}
+
+// One to four capitalized words, middle initials allowed ("Jane Q. Doe")
+const NAME: &str = r"\p{Lu}[\p{Ll}'’-]+(?: (?:\p{Lu}\.|\p{Lu}[\p{Ll}'’-]+)){0,3}";
+
+static CUED_NAME: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(&format!(
+        r"(?:\b(?i:from|to|cc|bcc|name|sender|recipient|attendees?|organizer)\s*:|\b(?i:dear|hi|hello|hey)\b|\b(?i:thanks|regards|sincerely|cheers|best),)\s*({})",
+        NAME
+    ))
+    .unwrap()
+});
+// "Jane Doe <jane@example.com>"
+static ADDRESSED_NAME: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(&format!(r"({})\s*<[^>\s]+@[^>\s]+>", NAME)).unwrap());
+pattern!(HANDLE, r"(?:^|[\s(])(@[A-Za-z0-9_.]{2,30})\b");
+
+pub(crate) fn is_kind(kind: &str) -> bool {
+    kind == NAME_KIND || entities::is_kind(kind) || secrets::is_kind(kind)
+}
+
+// Word boxes laid out as text, remembering the byte range each box occupies
+pub(crate) struct Layout<'a> {
+    text: String,
+    words: Vec<(usize, usize, &'a WordBox)>,
+}
+
+impl<'a> Layout<'a> {
+    pub(crate) fn new(words: &'a [WordBox]) -> Layout<'a> {
+        let mut text = String::new();
+        let mut spans = Vec::new();
+        let mut previous: Option<&WordBox> = None;
+        for word in words {
+            let word_text = word.text.trim();
+            if word_text.is_empty() {
+                continue;
+            }
+            if let Some(previous) = previous {
+                // Words come in reading order; one left of or below the last starts a new line
+                let new_line = word.left < previous.left || word.top >= previous.top + previous.height;
+                text.push(if new_line { '\n' } else { ' ' });
+            }
+            let start = text.len();
+            text.push_str(word_text);
+            spans.push((start, text.len(), word));
+            previous = Some(word);
+        }
+        Layout { text, words: spans }
+    }
+
+    pub(crate) fn text(&self) -> &str {
+        &self.text
+    }
+
+    // Boxes of the words overlapping any of the byte ranges
+    pub(crate) fn boxes(&self, spans: &[(usize, usize)]) -> Vec<&'a WordBox> {
+        self.words
+            .iter()
+            .filter(|(start, end, _)| spans.iter().any(|(from, to)| start < to && from < end))
+            .map(|(_, _, word)| *word)
+            .collect()
+    }
+}
+
+// Byte ranges of the text to redact
+pub(crate) fn find_spans(text: &str, kinds: &[&str], patterns: &[Regex]) -> Vec<(usize, usize)> {
+    let mut spans: Vec<(usize, usize)> = entities::extract_entities(text)
+        .into_iter()
+        .filter(|entity| kinds.contains(&entity.kind))
+        .map(|entity| (entity.start, entity.end))
+        .collect();
+    spans.extend(
+        secrets::detect_secrets(text)
+            .into_iter()
+            .filter(|secret| kinds.contains(&secret.kind))
+            .map(|secret| (secret.start, secret.end)),
+    );
+    if kinds.contains(&NAME_KIND) {
+        for pattern in [&CUED_NAME, &ADDRESSED_NAME, &HANDLE] {
+            spans.extend(pattern.captures_iter(text).filter_map(|captures| captures.get(1)).map(|m| (m.start(), m.end())));
+        }
+    }
+    for pattern in patterns {
+        spans.extend(pattern.find_iter(text).map(|m| (m.start(), m.end())));
+    }
+    spans
+}
+
+// Fill each box with black and write the result to `dest` (which may be `source`)
+pub(crate) fn black_out(source: &Path, dest: &Path, boxes: &[&WordBox]) -> Result<(), String> {
+    let mut img = image::open(source)
+        .map_err(|e| format!("Failed to open {}: {}", source.display(), e))?
+        .to_rgba8();
+    let (width, height) = img.dimensions();
+    for word in boxes {
+        let left = word.left.saturating_sub(PADDING);
+        let top = word.top.saturating_sub(PADDING);
+        let right = (word.left + word.width + PADDING).min(width);
+        let bottom = (word.top + word.height + PADDING).min(height);
+        for y in top..bottom {
+            for x in left..right {
+                img.put_pixel(x, y, image::Rgba([0, 0, 0, 255]));
+            }
+        }
+    }
+    img.save(dest)
+        .map_err(|e| format!("Failed to save {}: {}", dest.display(), e))
+}
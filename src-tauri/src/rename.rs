@@ -0,0 +1,239 @@
+// Rename templates for screenshots
+//
+//   {slug}             words summarized from the OCR text, e.g. flight-delayed-gate-b12
+//   {tag}              first custom tag, else first auto tag
+//   {date}             capture date as 2026-10-18; {date:%Y-%m-%d_%H%M} takes any chrono format
+//   {timestamp}        capture time in unix seconds
+//   {original}         the file's current name without extension
+//   {seq} {seq:3}      collision counter starting at 1, optionally zero-padded
+//
+// Without {seq}, a taken name gets -2, -3, ... appended
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::{Local, TimeZone};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+// Same names Chronicle has always produced
+pub(crate) const DEFAULT_TEMPLATE: &str = "{slug}-{timestamp}";
+
+const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d";
+
+// Values a template can use for one screenshot, already safe for file names
+pub(crate) struct RenameFacts<'a> {
+    pub(crate) slug: &'a str,
+    pub(crate) tag: &'a str,
+    pub(crate) created_at_ms: i64,
+    pub(crate) original: &'a str,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Part {
+    Literal(String),
+    Slug,
+    Tag,
+    Date(String),
+    Timestamp,
+    Original,
+    Seq(usize), // zero-padding width
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Template {
+    parts: Vec<Part>,
+}
+
+impl Template {
+    pub(crate) fn parse(input: &str) -> Result<Template, String> {
+        let mut parts = Vec::new();
+        let mut rest = input.trim();
+        while let Some(start) = rest.find('{') {
+            if start > 0 {
+                parts.push(Part::Literal(rest[..start].to_string()));
+            }
+            let end = rest[start..]
+                .find('}')
+                .map(|offset| start + offset)
+                .ok_or_else(|| format!("Unclosed '{{' in rename template '{}'", input))?;
+            parts.push(parse_placeholder(&rest[start + 1..end])?);
+            rest = &rest[end + 1..];
+        }
+        if !rest.is_empty() {
+            parts.push(Part::Literal(rest.to_string()));
+        }
+
+        for part in &parts {
+            if let Part::Literal(text) = part {
+                if text.contains(['/', '\\', '}', ':']) {
+                    return Err(format!("Rename template '{}' contains a character not allowed in file names", input));
+                }
+            }
+        }
+        if parts.iter().all(|part| matches!(part, Part::Literal(_))) {
+            return Err(format!("Rename template '{}' has no placeholders", input));
+        }
+        Ok(Template { parts })
+    }
+
+    fn has_seq(&self) -> bool {
+        self.parts.iter().any(|part| matches!(part, Part::Seq(_)))
+    }
+
+    // File name without extension
+    pub(crate) fn render(&self, facts: &RenameFacts, seq: usize) -> String {
+        let mut out = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Literal(text) => out.push_str(text),
+                Part::Slug => out.push_str(facts.slug),
+                Part::Tag => out.push_str(facts.tag),
+                Part::Date(format) => {
+                    let date = Local
+                        .timestamp_millis_opt(facts.created_at_ms)
+                        .single()
+                        .unwrap_or_else(Local::now);
+                    // Formats like %D or %T would otherwise produce path separators
+                    let formatted = date.format(format).to_string().replace(['/', '\\', ':'], "-");
+                    out.push_str(&formatted);
+                }
+                Part::Timestamp => out.push_str(&(facts.created_at_ms / 1000).to_string()),
+                Part::Original => out.push_str(facts.original),
+                Part::Seq(width) => out.push_str(&format!("{:0width$}", seq, width = *width)),
+            }
+        }
+        out
+    }
+
+    // First free path in `dir` for this screenshot
+    // `current` is the file being renamed (keeping its own name isn't a collision),
+    // `taken` holds paths already handed out earlier in the same batch
+    pub(crate) fn resolve(
+        &self,
+        dir: &Path,
+        facts: &RenameFacts,
+        extension: &str,
+        current: Option<&Path>,
+        taken: &HashSet<PathBuf>,
+    ) -> PathBuf {
+        let free = |candidate: &PathBuf| {
+            !taken.contains(candidate) && (current == Some(candidate.as_path()) || !candidate.exists())
+        };
+
+        if self.has_seq() {
+            let first = dir.join(format!("{}.{}", self.render(facts, 1), extension));
+            return (1..)
+                .map(|seq| dir.join(format!("{}.{}", self.render(facts, seq), extension)))
+                .find(free)
+                .unwrap_or(first);
+        }
+
+        let base = self.render(facts, 0);
+        let first = dir.join(format!("{}.{}", base, extension));
+        if free(&first) {
+            return first;
+        }
+        (2..)
+            .map(|n| dir.join(format!("{}-{}.{}", base, n, extension)))
+            .find(free)
+            .unwrap_or(first)
+    }
+}
+
+fn parse_placeholder(placeholder: &str) -> Result<Part, String> {
+    let (name, argument) = match placeholder.split_once(':') {
+        Some((name, argument)) => (name, Some(argument)),
+        None => (placeholder, None),
+    };
+    match (name.trim().to_lowercase().as_str(), argument) {
+        ("slug", None) => Ok(Part::Slug),
+        ("tag", None) => Ok(Part::Tag),
+        ("timestamp", None) => Ok(Part::Timestamp),
+        ("original", None) => Ok(Part::Original),
+        ("date", None) => Ok(Part::Date(DEFAULT_DATE_FORMAT.to_string())),
+        ("date", Some(format)) => {
+            // chrono panics when displaying an invalid format, so reject it up front
+            if format.is_empty() || StrftimeItems::new(format).any(|item| matches!(item, Item::Error)) {
+                return Err(format!("Invalid date format '{}' in rename template", format));
+            }
+            Ok(Part::Date(format.to_string()))
+        }
+        ("seq", None) => Ok(Part::Seq(1)),
+        ("seq", Some(width)) => width
+            .trim()
+            .parse::<usize>()
+            .ok()
+            .filter(|width| (1..=6).contains(width))
+            .map(Part::Seq)
+            .ok_or_else(|| format!("Invalid {{seq:{}}} width (use 1-6)", width)),
+        _ => Err(format!("Unknown placeholder {{{}}} in rename template", placeholder)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 2026-07-01 12:00 UTC, the same calendar year in every time zone
+    const CREATED_AT_MS: i64 = 1_782_907_200_000;
+
+    fn facts() -> RenameFacts<'static> {
+        RenameFacts { slug: "flight-delayed", tag: "Travel", created_at_ms: CREATED_AT_MS, original: "Screenshot 1" }
+    }
+
+    fn render(template: &str, seq: usize) -> String {
+        Template::parse(template).unwrap().render(&facts(), seq)
+    }
+
+    #[test]
+    fn renders_placeholders() {
+        assert_eq!(render(DEFAULT_TEMPLATE, 0), "flight-delayed-1782907200");
+        assert_eq!(render("{tag}_{original}", 0), "Travel_Screenshot 1");
+        assert_eq!(render("{date:%Y}-{slug}", 0), "2026-flight-delayed");
+        assert_eq!(render("{ SLUG }-{seq:3}", 7), "flight-delayed-007");
+    }
+
+    #[test]
+    fn date_formats_never_produce_separators() {
+        let rendered = render("{date:%D %T}", 0);
+        assert!(!rendered.contains(['/', '\\', ':']), "{}", rendered);
+    }
+
+    #[test]
+    fn rejects_bad_templates() {
+        for template in [
+            "plain-name",
+            "{slug",
+            "{slug}/{tag}",
+            "{slug}:{tag}",
+            "{unknown}",
+            "{date:%Q}",
+            "{date:}",
+            "{seq:0}",
+            "{seq:7}",
+            "{slug:x}",
+        ] {
+            assert!(Template::parse(template).is_err(), "{} should be rejected", template);
+        }
+    }
+
+    #[test]
+    fn resolves_collisions() {
+        let dir = std::env::temp_dir().join(format!("chronicle-rename-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("flight-delayed.png"), b"").unwrap();
+
+        let plain = Template::parse("{slug}").unwrap();
+        let mut taken = HashSet::new();
+        assert_eq!(plain.resolve(&dir, &facts(), "png", None, &taken), dir.join("flight-delayed-2.png"));
+        // A file keeping its own name isn't a collision
+        let current = dir.join("flight-delayed.png");
+        assert_eq!(plain.resolve(&dir, &facts(), "png", Some(&current), &taken), current);
+        taken.insert(dir.join("flight-delayed-2.png"));
+        assert_eq!(plain.resolve(&dir, &facts(), "png", None, &taken), dir.join("flight-delayed-3.png"));
+
+        let numbered = Template::parse("{slug}-{seq:2}").unwrap();
+        assert_eq!(numbered.resolve(&dir, &facts(), "png", None, &HashSet::new()), dir.join("flight-delayed-01.png"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}